@@ -0,0 +1,149 @@
+//! Where the store reads task files from.
+//!
+//! Normally that is the working tree, but `--at <rev>` reads the `.tasks/`
+//! tree of a git revision instead. Everything the store reads goes through
+//! the `Backend` trait so listing, finding and loading tasks work the same
+//! either way. Writes always go to the working tree; a backend that is not
+//! the working tree is read-only.
+
+use crate::git;
+use crate::index::FileStamp;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// An entry in a directory listing
+#[derive(Debug)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Read access to the files of a task store
+pub trait Backend {
+    /// Read a file's content
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+
+    /// Whether a directory exists
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// The entries of a directory
+    fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>>;
+
+    /// A stamp that changes whenever the file or directory does, or None if
+    /// the backend can't tell cheaply (the index is only used when it can)
+    fn stamp(&self, path: &Path) -> Result<Option<FileStamp>>;
+
+    /// Git revision being read, or None for the working tree
+    fn revision(&self) -> Option<&str>;
+}
+
+/// The working tree on disk
+pub struct WorkTree;
+
+impl Backend for WorkTree {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            entries.push(DirEntry {
+                path: entry.path(),
+                is_dir: entry.file_type()?.is_dir(),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn stamp(&self, path: &Path) -> Result<Option<FileStamp>> {
+        FileStamp::of(path).map(Some)
+    }
+
+    fn revision(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The task directory as committed at a git revision, read into memory
+/// when the store is opened
+pub struct GitRevision {
+    revision: String,
+    files: HashMap<PathBuf, String>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl GitRevision {
+    /// Read every file below `tasks_dir` at `revision`
+    pub fn load(tasks_dir: &Path, revision: &str) -> std::result::Result<Self, String> {
+        let mut files = HashMap::new();
+        let mut dirs = HashSet::from([tasks_dir.to_path_buf()]);
+        for (path, content) in git::read_tree(tasks_dir, revision)? {
+            let path = tasks_dir.join(path);
+            for dir in path.ancestors().skip(1) {
+                if !dirs.insert(dir.to_path_buf()) {
+                    break;
+                }
+            }
+            files.insert(path, content);
+        }
+        Ok(GitRevision {
+            revision: revision.to_string(),
+            files,
+            dirs,
+        })
+    }
+}
+
+impl Backend for GitRevision {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{} not found at {}", path.display(), self.revision),
+            )
+        })
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains(path)
+    }
+
+    fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
+        if !self.is_dir(dir) {
+            return Err(ErrorKind::NotFound.into());
+        }
+        let children = |paths: &mut dyn Iterator<Item = &PathBuf>, is_dir| {
+            paths
+                .filter(|path| path.parent() == Some(dir))
+                .map(|path| DirEntry {
+                    path: path.clone(),
+                    is_dir,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut entries = children(&mut self.files.keys(), false);
+        entries.extend(children(&mut self.dirs.iter(), true));
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    fn stamp(&self, path: &Path) -> Result<Option<FileStamp>> {
+        if self.files.contains_key(path) || self.is_dir(path) {
+            Ok(None)
+        } else {
+            Err(ErrorKind::NotFound.into())
+        }
+    }
+
+    fn revision(&self) -> Option<&str> {
+        Some(&self.revision)
+    }
+}
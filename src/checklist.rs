@@ -0,0 +1,203 @@
+//! GitHub-style checklists in a task's body.
+//!
+//! ```markdown
+//! ## Acceptance Criteria
+//! - [x] Users can log in
+//! - [ ] Sessions expire after 24h
+//! ```
+//!
+//! Items are `-`, `*`, `+` or numbered list items starting with `[ ]`,
+//! `[x]` or `[X]`, at any indentation. Lines inside fenced code blocks
+//! are not items.
+
+use serde::Serialize;
+
+/// One checklist item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChecklistItem {
+    pub text: String,
+    pub checked: bool,
+    /// Line of the body the item is on
+    #[serde(skip)]
+    line: usize,
+    /// Byte offset of the mark between the brackets
+    #[serde(skip)]
+    mark: usize,
+}
+
+/// The checklist items in a task body, in order
+pub fn items(body: &str) -> Vec<ChecklistItem> {
+    let mut items = vec![];
+    let mut fence: Option<&str> = None;
+    for (line, text) in body.split('\n').enumerate() {
+        let trimmed = text.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        if let Some(mut item) = parse_item(text) {
+            item.line = line;
+            items.push(item);
+        }
+    }
+    items
+}
+
+/// Checked and total items, or None if the body has no checklist
+pub fn progress(body: &str) -> Option<(usize, usize)> {
+    let items = items(body);
+    if items.is_empty() {
+        return None;
+    }
+    let checked = items.iter().filter(|item| item.checked).count();
+    Some((checked, items.len()))
+}
+
+/// The body with `item` checked or unchecked
+pub fn set_checked(body: &str, item: &ChecklistItem, checked: bool) -> String {
+    let mark = if checked { "x" } else { " " };
+    body.split('\n')
+        .enumerate()
+        .map(|(line, text)| {
+            if line == item.line {
+                format!("{}{}{}", &text[..item.mark], mark, &text[item.mark + 1..])
+            } else {
+                text.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Find an item by its 1-based number or by its text. Text matches
+/// ignore case; an exact match wins over items merely containing it.
+pub fn find<'a>(items: &'a [ChecklistItem], query: &str) -> Result<&'a ChecklistItem, String> {
+    if items.is_empty() {
+        return Err("Task has no checklist items".to_string());
+    }
+    if let Ok(n) = query.trim().parse::<usize>() {
+        return n
+            .checked_sub(1)
+            .and_then(|i| items.get(i))
+            .ok_or_else(|| format!("No checklist item {} (task has {})", n, items.len()));
+    }
+
+    let query = query.trim();
+    let needle = query.to_lowercase();
+    if let Some(item) = items.iter().find(|item| item.text.to_lowercase() == needle) {
+        return Ok(item);
+    }
+    let matches: Vec<&ChecklistItem> = items
+        .iter()
+        .filter(|item| item.text.to_lowercase().contains(&needle))
+        .collect();
+    match matches.as_slice() {
+        [item] => Ok(item),
+        [] => Err(format!("No checklist item matches '{}'", query)),
+        _ => Err(format!(
+            "'{}' matches {} checklist items: {}",
+            query,
+            matches.len(),
+            matches
+                .iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Parse a `- [ ] text` line
+fn parse_item(line: &str) -> Option<ChecklistItem> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let bullet = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let after = &rest[bullet..];
+    let spaces = after.len() - after.trim_start_matches(' ').len();
+    if spaces == 0 {
+        return None;
+    }
+    let start = indent + bullet + spaces;
+    let checked = match line[start..].get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &line[start + 3..];
+    if !text.is_empty() && !text.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some(ChecklistItem {
+        checked,
+        text: text.trim().to_string(),
+        line: 0,
+        mark: start + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "Intro\n\n\
+        ## Acceptance Criteria\n\
+        - [x] Users can log in\n\
+        - [ ] Sessions expire\n  \
+          * [X] Nested item\n\
+        1. [ ] Numbered item\n\
+        - [link](url) is not an item\n\
+        -[ ] neither is this\n\
+        ```\n\
+        - [ ] in a code block\n\
+        ```\n";
+
+    #[test]
+    fn test_parses_items() {
+        let items = items(BODY);
+        let texts: Vec<&str> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Users can log in",
+                "Sessions expire",
+                "Nested item",
+                "Numbered item"
+            ]
+        );
+        assert_eq!(progress(BODY), Some((2, 4)));
+        assert_eq!(progress("No list here"), None);
+    }
+
+    #[test]
+    fn test_toggles_items() {
+        let list = items(BODY);
+        let item = find(&list, "expire").unwrap();
+        let body = set_checked(BODY, item, true);
+        assert_eq!(body.len(), BODY.len());
+        assert!(body.contains("- [x] Sessions expire\n"));
+        assert_eq!(progress(&body), Some((3, 4)));
+
+        let list = items(&body);
+        let body = set_checked(&body, find(&list, "3").unwrap(), false);
+        assert!(body.contains("  * [ ] Nested item\n"));
+
+        assert!(find(&list, "5").is_err());
+        assert!(find(&list, "0").is_err());
+        assert!(find(&list, "item").is_err());
+        assert_eq!(find(&list, "NUMBERED ITEM").unwrap().text, "Numbered item");
+    }
+}
@@ -0,0 +1,88 @@
+use super::orphan::detach;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use colored::*;
+use std::path::Path;
+
+/// Make `child_id` a subtask of `parent_id`, moving it from any previous
+/// parent
+pub fn adopt(
+    path: &Path,
+    options: &StoreOptions,
+    child_id: &str,
+    parent_id: &str,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let child_path = store.find(child_id)?;
+    let parent_path = store.find(parent_id)?;
+    let mut child = store.load(&child_path)?;
+    let mut parent = store.load(&parent_path)?;
+
+    if child.frontmatter.parent.as_ref() == Some(parent.id()) {
+        println!(
+            "{} Task {} is already a subtask of {}",
+            "warning:".yellow(),
+            child.id(),
+            parent.id()
+        );
+        return Ok(());
+    }
+
+    // Walk up from the new parent: finding the child there would make a cycle
+    let mut ancestor = Some(parent.clone());
+    while let Some(task) = ancestor {
+        if task.id() == child.id() {
+            return Err(StoreError::NotAllowed(format!(
+                "Cannot make {} a subtask of {}: {} is already a descendant of {}",
+                child.id(),
+                parent.id(),
+                parent.id(),
+                child.id()
+            )));
+        }
+        ancestor = match &task.frontmatter.parent {
+            Some(id) => match store.find(id.full()) {
+                Ok(path) => Some(store.load(&path)?),
+                Err(StoreError::TaskNotFound(_)) => None,
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+    }
+
+    let former_parent = detach(&store, &child)?;
+
+    let author = store.get_author();
+    child.frontmatter.parent = Some(parent.id().clone());
+    child.add_log_entry(
+        LogKind::ParentSet,
+        &[("task", parent.id().full())],
+        &format!("Parent set to: {}", parent.id()),
+        author.as_deref(),
+    );
+    if !parent.frontmatter.children.contains(child.id()) {
+        parent.frontmatter.children.push(child.id().clone());
+    }
+    parent.add_log_entry(
+        LogKind::SubtaskAdded,
+        &[("task", child.id().full())],
+        &format!("Added subtask: {}", child.id()),
+        author.as_deref(),
+    );
+
+    let mut tx = store.transaction();
+    tx.save(&child, &child_path);
+    tx.save(&parent, &parent_path);
+    if let Some((former_path, former)) = &former_parent {
+        tx.save(former, former_path);
+    }
+    tx.commit()?;
+
+    println!(
+        "{} Task {} is now a subtask of {}",
+        "info:".blue(),
+        child.id(),
+        parent.id()
+    );
+    Ok(())
+}
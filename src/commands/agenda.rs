@@ -0,0 +1,141 @@
+use crate::dates;
+use crate::prefix::PrefixResolver;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::{Priority, Task};
+use crate::term::LineFormatter;
+use chrono::{Days, NaiveDate};
+use colored::*;
+use std::path::{Path, PathBuf};
+
+/// Days after today counted as "this week"
+const WEEK_DAYS: u64 = 7;
+
+/// Show active tasks that are overdue, due today or due within the week
+pub fn agenda(path: &Path, options: &StoreOptions) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let mut tasks: Vec<(PathBuf, Task)> = store
+        .list_active()?
+        .into_iter()
+        .filter(|(_, task)| task.frontmatter.due.is_some())
+        .collect();
+    let resolver = PrefixResolver::new(&store)?;
+    store.warn_broken();
+
+    // Soonest first, then most important
+    tasks.sort_by_key(|(_, task)| {
+        let priority_order = match task.priority() {
+            Priority::Critical => 0,
+            Priority::High => 1,
+            Priority::Medium => 2,
+            Priority::Low => 3,
+        };
+        (task.frontmatter.due, priority_order)
+    });
+
+    let today = dates::today();
+    let sections = sections(&tasks, today);
+
+    if sections.iter().all(|(_, tasks)| tasks.is_empty()) {
+        println!("{}", "Nothing due this week.".dimmed());
+        return Ok(());
+    }
+
+    // Fixed columns: short_id (8) + due date (10) + weekday (3) + spaces (6)
+    const FIXED_COLS: usize = 27;
+    let formatter = LineFormatter::auto();
+
+    let mut first = true;
+    for (heading, tasks) in sections {
+        if tasks.is_empty() {
+            continue;
+        }
+        if !first {
+            println!();
+        }
+        first = false;
+
+        let heading = format!("{} ({})", heading, tasks.len());
+        if heading.starts_with("Overdue") {
+            println!("{}", heading.red().bold());
+        } else {
+            println!("{}", heading.bold());
+        }
+        for task in tasks {
+            let due = task.frontmatter.due.unwrap_or(today);
+            println!(
+                "  {}  {} {}  {}",
+                resolver.shortest_prefix(task.id()).cyan(),
+                due,
+                due.format("%a").to_string().dimmed(),
+                formatter.truncate(task.title(), FIXED_COLS)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Split tasks into those overdue, due today and due in the week after
+/// today, keeping their order. Tasks due later (or not at all) are left out.
+fn sections(tasks: &[(PathBuf, Task)], today: NaiveDate) -> [(&'static str, Vec<&Task>); 3] {
+    let week_end = today
+        .checked_add_days(Days::new(WEEK_DAYS))
+        .unwrap_or(NaiveDate::MAX);
+    let due_in = |from: NaiveDate, to: NaiveDate| -> Vec<&Task> {
+        tasks
+            .iter()
+            .map(|(_, task)| task)
+            .filter(|task| task.frontmatter.due.is_some_and(|due| due >= from && due <= to))
+            .collect()
+    };
+    [
+        ("Overdue", due_in(NaiveDate::MIN, today.pred_opt().unwrap_or(today))),
+        ("Due today", due_in(today, today)),
+        (
+            "Due this week",
+            due_in(today.succ_opt().unwrap_or(today), week_end),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_sections() {
+        // A Wednesday
+        let today = date("2025-01-15");
+        let due = |title: &str, due: Option<&str>| {
+            let mut task = Task::new(title, None);
+            task.frontmatter.due = due.map(date);
+            (PathBuf::new(), task)
+        };
+        let tasks = vec![
+            due("Long overdue", Some("2024-12-01")),
+            due("Yesterday", Some("2025-01-14")),
+            due("Today", Some("2025-01-15")),
+            due("Tomorrow", Some("2025-01-16")),
+            due("A week out", Some("2025-01-22")),
+            due("Eight days out", Some("2025-01-23")),
+            due("Whenever", None),
+        ];
+
+        let titles: Vec<(&str, Vec<&str>)> = sections(&tasks, today)
+            .into_iter()
+            .map(|(heading, tasks)| (heading, tasks.iter().map(|task| task.title()).collect()))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                ("Overdue", vec!["Long overdue", "Yesterday"]),
+                ("Due today", vec!["Today"]),
+                ("Due this week", vec!["Tomorrow", "A week out"]),
+            ]
+        );
+    }
+}
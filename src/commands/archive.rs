@@ -0,0 +1,101 @@
+use crate::store::{Store, StoreError, StoreOptions};
+use chrono::{Datelike, Duration, Utc};
+use colored::*;
+use std::path::Path;
+
+/// Move closed and cancelled tasks not updated within `older_than` into
+/// dated archive directories ({status}/YYYY/)
+pub fn archive(
+    path: &Path,
+    options: &StoreOptions,
+    older_than: &str,
+    dry_run: bool,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let cutoff = Utc::now() - parse_age(older_than)?;
+
+    let mut tx = store.transaction();
+    let mut archived = vec![];
+
+    for status in store.statuses() {
+        if !store.is_terminal(&status) {
+            continue;
+        }
+        for (task_path, task) in store.list_by_status(status)? {
+            if task.frontmatter.updated >= cutoff {
+                continue;
+            }
+            let year = task.frontmatter.updated.year();
+            let new_path = tx.archive(&task_path, year)?;
+            archived.push((task, new_path));
+        }
+    }
+    store.warn_broken();
+
+    if archived.is_empty() {
+        println!("{} No tasks to archive", "info:".blue());
+        return Ok(());
+    }
+
+    if !dry_run {
+        tx.commit()?;
+    }
+
+    for (task, new_path) in &archived {
+        let relative = new_path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(store.tasks_dir()).ok())
+            .unwrap_or(new_path);
+        println!(
+            "{} {} ({}) -> {}/",
+            if dry_run {
+                "would archive:".yellow()
+            } else {
+                "archived:".green()
+            },
+            task.id(),
+            task.title(),
+            relative.display()
+        );
+    }
+    println!(
+        "{} {} task(s) {}",
+        "info:".blue(),
+        archived.len(),
+        if dry_run {
+            "would be archived"
+        } else {
+            "archived"
+        }
+    );
+
+    Ok(())
+}
+
+/// Parse a positive age such as `90d` or `12w`
+fn parse_age(age: &str) -> Result<Duration, StoreError> {
+    let invalid =
+        || StoreError::InvalidArgument(format!("Invalid age '{}': expected e.g. 90d or 12w", age));
+    let split = age.char_indices().last().map_or(0, |(i, _)| i);
+    let (number, unit) = age.split_at(split);
+    let number: i64 = number.parse().ok().filter(|n| *n > 0).ok_or_else(invalid)?;
+    match unit {
+        "d" => Duration::try_days(number).ok_or_else(invalid),
+        "w" => Duration::try_weeks(number).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90d").unwrap(), Duration::days(90));
+        assert_eq!(parse_age("2w").unwrap(), Duration::weeks(2));
+        for age in ["-5d", "0d", "d", "5", "5m", "99999999999999w"] {
+            assert!(parse_age(age).is_err(), "{}", age);
+        }
+    }
+}
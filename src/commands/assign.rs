@@ -0,0 +1,104 @@
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use colored::*;
+use std::path::Path;
+
+/// Assign a task to `who` (the current author if None), or unassign it if
+/// `who` is `none`
+pub fn assign(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    who: Option<&str>,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let task_path = store.find(id)?;
+    let mut task = store.load(&task_path)?;
+
+    let author = store.get_author();
+    let assignee = match who {
+        Some(who) if who.eq_ignore_ascii_case("none") => None,
+        Some(who) => Some(who.to_string()),
+        None => Some(author.clone().ok_or(StoreError::UnknownAuthor)?),
+    };
+    if task.frontmatter.assignee == assignee {
+        println!(
+            "{} Task {} is already {}",
+            "info:".blue(),
+            task.id(),
+            match &assignee {
+                Some(assignee) => format!("assigned to {}", assignee),
+                None => "unassigned".to_string(),
+            }
+        );
+        return Ok(());
+    }
+
+    match &assignee {
+        Some(assignee) => task.add_log_entry(
+            LogKind::FieldChange,
+            &[("field", "assignee"), ("value", assignee)],
+            &format!("Assigned to {}.", assignee),
+            author.as_deref(),
+        ),
+        None => task.add_log_entry(
+            LogKind::FieldChange,
+            &[("field", "assignee")],
+            "Unassigned.",
+            author.as_deref(),
+        ),
+    }
+    task.frontmatter.assignee = assignee.clone();
+    store.save(&task, &task_path)?;
+
+    match assignee {
+        Some(assignee) => println!("{} Assigned {} to {}", "info:".blue(), task.id(), assignee),
+        None => println!("{} Unassigned {}", "info:".blue(), task.id()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Status, Task};
+    use crate::test_util::temp_store;
+    use std::fs;
+
+    #[test]
+    fn test_assign_and_unassign() {
+        let (root, store) = temp_store("assign");
+        fs::write(
+            store.tasks_dir().join("config.yaml"),
+            "default_author: alice\n",
+        )
+        .unwrap();
+        let task = Task::new("Fix login", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let options = StoreOptions::default();
+        let id = task.id().full();
+        let load = || Store::open(&root).unwrap().load(&path).unwrap();
+
+        assign(&root, &options, id, Some("bob")).unwrap();
+        let task = load();
+        assert_eq!(task.frontmatter.assignee.as_deref(), Some("bob"));
+        let entry = task.log.last().unwrap();
+        assert_eq!(entry.kind, LogKind::FieldChange);
+        assert_eq!(entry.metadata["value"], "bob");
+
+        // Assigning to the same person again changes nothing
+        assign(&root, &options, id, Some("bob")).unwrap();
+        assert_eq!(load().log.len(), task.log.len());
+
+        // No name means the current author
+        assign(&root, &options, id, None).unwrap();
+        assert_eq!(load().frontmatter.assignee.as_deref(), Some("alice"));
+
+        assign(&root, &options, id, Some("none")).unwrap();
+        let task = load();
+        assert_eq!(task.frontmatter.assignee, None);
+        let entry = task.log.last().unwrap();
+        assert_eq!(entry.body, "Unassigned.");
+        assert!(!entry.metadata.contains_key("value"));
+    }
+}
@@ -1,32 +1,71 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn block(path: &Path, task_id: &str, blocker_id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn block(
+    path: &Path,
+    options: &StoreOptions,
+    task_id: &str,
+    blocker_id: &str,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
 
     let task_path = store.find(task_id)?;
     let blocker_path = store.find(blocker_id)?;
+    if task_path == blocker_path {
+        return Err(StoreError::NotAllowed(format!(
+            "Task {} cannot block itself",
+            task_id
+        )));
+    }
 
     let mut task = store.load(&task_path)?;
-    let blocker = store.load(&blocker_path)?;
+    let mut blocker = store.load(&blocker_path)?;
+
+    if task.frontmatter.blocked_by.contains(blocker.id()) {
+        println!(
+            "{} Task {} is already blocked by {}",
+            "warning:".yellow(),
+            task.id(),
+            blocker.id()
+        );
+        return Ok(());
+    }
 
     // Check if blocker is already resolved
-    let blocker_status = store.status_from_path(&blocker_path);
-    let blocker_resolved =
-        matches!(blocker_status, Some(Status::Closed) | Some(Status::Cancelled));
+    let blocker_resolved = store
+        .status_from_path(&blocker_path)
+        .is_some_and(|status| store.is_terminal(&status));
 
-    // Add to blocked_by list
+    // Add to blocked_by list, and the reverse link on the blocker
     task.frontmatter.blocked_by.push(blocker.id().clone());
+    if !blocker.frontmatter.blocks.contains(task.id()) {
+        blocker.frontmatter.blocks.push(task.id().clone());
+    }
 
     let author = store.get_author();
-    task.add_log(
+    task.add_log_entry(
+        LogKind::BlockerAdded,
+        &[("task", blocker.id().full())],
         &format!("Added blocker: {}", blocker.id()),
         author.as_deref(),
     );
 
-    store.save(&task, &task_path)?;
+    // If the blocker is not resolved, move task to blocked/
+    let current_status = store.status_from_path(&task_path);
+    let mut tx = store.transaction();
+    let moved_to = if !blocker_resolved && !matches!(current_status, Some(Status::Blocked)) {
+        task.stop_work(Utc::now());
+        Some(tx.save_to_status(&task, &task_path, Status::Blocked)?)
+    } else {
+        tx.save(&task, &task_path);
+        None
+    };
+    tx.save(&blocker, &blocker_path);
+    tx.commit()?;
 
     println!(
         "{} Task {} is now blocked by {}",
@@ -35,14 +74,60 @@ pub fn block(path: &Path, task_id: &str, blocker_id: &str) -> Result<(), StoreEr
         blocker.id()
     );
 
-    // If the blocker is not resolved, move task to blocked/
-    if !blocker_resolved {
-        let current_status = store.status_from_path(&task_path);
-        if !matches!(current_status, Some(Status::Blocked)) {
-            let new_path = store.move_to_status(&task_path, Status::Blocked)?;
-            println!("{} Moved to: {}", "info:".blue(), new_path.display());
-        }
+    if let Some(new_path) = moved_to {
+        println!("{} Moved to: {}", "info:".blue(), new_path.display());
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+    use crate::test_util::{assert_blocks_consistent, temp_store};
+
+    #[test]
+    fn test_block_links_both_tasks() {
+        let (root, store) = temp_store("block");
+        let task = Task::new("Deploy", None);
+        let blocker = Task::new("Fix tests", None);
+        store.create(&task, Status::Open).unwrap();
+        let blocker_path = store.create(&blocker, Status::Open).unwrap();
+
+        let options = StoreOptions::default();
+        block(&root, &options, task.id().full(), blocker.id().full()).unwrap();
+        // Blocking twice doesn't duplicate the links
+        block(&root, &options, task.id().full(), blocker.id().full()).unwrap();
+
+        let store = Store::open(&root).unwrap();
+        assert_blocks_consistent(&store);
+        let task_path = store.find(task.id().full()).unwrap();
+        assert_eq!(store.status_from_path(&task_path), Some(Status::Blocked));
+        assert_eq!(
+            store.load(&task_path).unwrap().frontmatter.blocked_by,
+            [blocker.id().clone()]
+        );
+        assert_eq!(
+            store.load(&blocker_path).unwrap().frontmatter.blocks,
+            [task.id().clone()]
+        );
+    }
+
+    #[test]
+    fn test_task_cannot_block_itself() {
+        let (root, store) = temp_store("block-self");
+        let task = Task::new("Deploy", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        let options = StoreOptions::default();
+        let id = task.id().full();
+        let result = block(&root, &options, id, id);
+        assert!(matches!(result, Err(StoreError::NotAllowed(_))));
+
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    }
+}
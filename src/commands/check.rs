@@ -0,0 +1,57 @@
+use crate::checklist;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use colored::*;
+use std::path::Path;
+
+/// Check (or uncheck) a checklist item in a task's body, found by its
+/// number or text
+pub fn check(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    item: &str,
+    checked: bool,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let task_path = store.find(id)?;
+    let mut task = store.load(&task_path)?;
+
+    let items = task.checklist();
+    let item = checklist::find(&items, item).map_err(StoreError::InvalidArgument)?;
+    if item.checked == checked {
+        println!(
+            "{} '{}' is already {}",
+            "info:".blue(),
+            item.text,
+            if checked { "checked" } else { "unchecked" }
+        );
+        return Ok(());
+    }
+
+    let author = store.get_author();
+    task.set_checked(item, checked);
+    let (verb, kind) = if checked {
+        ("Checked", LogKind::ItemChecked)
+    } else {
+        ("Unchecked", LogKind::ItemUnchecked)
+    };
+    task.add_log_entry(
+        kind,
+        &[("item", &item.text)],
+        &format!("{}: {}", verb, item.text),
+        author.as_deref(),
+    );
+    store.save(&task, &task_path)?;
+
+    let (done, total) = task.checklist_progress().unwrap_or_default();
+    println!(
+        "{} {} '{}' ({}/{} done)",
+        "info:".blue(),
+        verb,
+        item.text,
+        done,
+        total
+    );
+    Ok(())
+}
@@ -1,38 +1,60 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn close(path: &Path, id: &str, reason: Option<&str>) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn close(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    reason: Option<&str>,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
 
     // Check if already in a terminal state
-    let current_status = store.status_from_path(&task_path);
-    if matches!(current_status, Some(Status::Closed) | Some(Status::Cancelled)) {
+    let current_status = store
+        .status_from_path(&task_path)
+        .ok_or_else(|| StoreError::UnknownStatusDir(task_path.clone()))?;
+    if store.is_terminal(&current_status) {
         println!("{} Task is already closed", "warning:".yellow());
         return Ok(());
     }
+    store.check_transition(&current_status, &Status::Closed)?;
 
     let mut task = store.load(&task_path)?;
+    store.check_children_closed(&task)?;
+    store.check_checklist_done(&task)?;
     let task_id = task.id().clone();
 
-    let message = match reason {
-        Some(r) => format!("Closed: {}", r),
-        None => "Closed.".to_string(),
+    let (message, metadata) = match reason {
+        Some(r) => (
+            format!("Closed: {}", r),
+            vec![("to", "closed"), ("reason", r.trim())],
+        ),
+        None => ("Closed.".to_string(), vec![("to", "closed")]),
     };
 
     let author = store.get_author();
-    task.add_log(&message, author.as_deref());
+    task.add_log_entry(
+        LogKind::StatusChange,
+        &metadata,
+        &message,
+        author.as_deref(),
+    );
+    task.stop_work(Utc::now());
 
-    store.save(&task, &task_path)?;
-    let new_path = store.move_to_status(&task_path, Status::Closed)?;
+    // Close the task and unblock any tasks that were waiting on it together
+    let mut tx = store.transaction();
+    let new_path = tx.save_to_status(&task, &task_path, Status::Closed)?;
+    let unblocked = store.unblock_waiting_tasks(&mut tx, &task_id)?;
+    tx.commit()?;
 
     println!("{} Closed: {}", "info:".blue(), task.id());
     println!("{} Moved to: {}", "info:".blue(), new_path.display());
 
-    // Unblock any tasks that were waiting on this one
-    let unblocked = store.unblock_waiting_tasks(&task_id)?;
     for unblocked_path in unblocked {
         let unblocked_task = store.load(&unblocked_path)?;
         println!(
@@ -1,30 +1,67 @@
-use crate::id::TaskId;
-use crate::store::{Store, StoreError};
+use crate::dates;
+use crate::prefix::PrefixResolver;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn context(path: &Path, id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn context(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
     let task = store.load(&task_path)?;
     let status = store.status_from_path(&task_path).unwrap_or(Status::Open);
 
-    // Get all tasks for ID prefix computation
-    let all_tasks = store.list_all()?;
-    let all_ids: Vec<&TaskId> = all_tasks.iter().map(|(_, t)| t.id()).collect();
-    let short_id = task.id().shortest_unique_prefix(&all_ids);
+    // Resolve shortest unique prefixes across all tasks, including archived
+    let resolver = PrefixResolver::new(&store)?;
+    let short_id = resolver.shortest_prefix(task.id());
 
     // === Main Task ===
     println!("{}", "=== Task ===".bold());
     println!();
     println!("{} {}", "ID:".dimmed(), short_id);
     println!("{} {}", "Title:".dimmed(), task.title().bold());
-    println!("{} {}", "Status:".dimmed(), format_status(status));
+    println!("{} {}", "Status:".dimmed(), format_status(&status));
     println!("{} {}", "Priority:".dimmed(), task.priority());
     if !task.frontmatter.tags.is_empty() {
         println!("{} {}", "Tags:".dimmed(), task.frontmatter.tags.join(", "));
     }
+    if let Some(assignee) = &task.frontmatter.assignee {
+        println!("{} {}", "Assignee:".dimmed(), assignee);
+    }
+    if let Some(due) = task.frontmatter.due {
+        if task.is_overdue(dates::today()) && store.is_active(&status) {
+            println!("{} {} {}", "Due:".dimmed(), due, "(overdue)".red());
+        } else {
+            println!("{} {}", "Due:".dimmed(), due);
+        }
+    }
+    if let Some(defer_until) = task.frontmatter.defer_until {
+        println!("{} {}", "Deferred until:".dimmed(), defer_until);
+    }
+    if let Some(parent_id) = &task.frontmatter.parent {
+        let title = store
+            .find(parent_id.full())
+            .and_then(|parent_path| store.load(&parent_path))
+            .map(|parent| parent.title().to_string())
+            .unwrap_or_else(|_| "(not found)".to_string());
+        println!(
+            "{} {} {}",
+            "Parent:".dimmed(),
+            resolver.shortest_prefix(parent_id).cyan(),
+            title
+        );
+    }
+    if let Some((done, total)) = store.subtask_progress(&task)? {
+        println!("{} {}/{} subtasks done", "Progress:".dimmed(), done, total);
+    }
+    if let Some((done, total)) = task.checklist_progress() {
+        println!("{} {}/{} items done", "Checklist:".dimmed(), done, total);
+    }
+    if !task.frontmatter.work.is_empty() {
+        let spent = dates::format_duration(task.time_spent(Utc::now()));
+        println!("{} {}", "Time spent:".dimmed(), spent);
+    }
     println!();
 
     // Body (without log section)
@@ -44,7 +81,7 @@ pub fn context(path: &Path, id: &str) -> Result<(), StoreError> {
         println!("{}", "=== Blocked By ===".bold());
         println!();
         for blocker_id in &task.frontmatter.blocked_by {
-            let blocker_short = blocker_id.shortest_unique_prefix(&all_ids);
+            let blocker_short = resolver.shortest_prefix(blocker_id);
             match store.find(blocker_id.full()) {
                 Ok(blocker_path) => {
                     let blocker = store.load(&blocker_path)?;
@@ -53,7 +90,7 @@ pub fn context(path: &Path, id: &str) -> Result<(), StoreError> {
                         "  {} {} [{}]",
                         blocker_short.cyan(),
                         blocker.title(),
-                        format_status(blocker_status)
+                        format_status(&blocker_status)
                     );
                 }
                 Err(_) => {
@@ -65,90 +102,89 @@ pub fn context(path: &Path, id: &str) -> Result<(), StoreError> {
     }
 
     // === Tasks this blocks ===
-    let blocks: Vec<_> = all_tasks
-        .iter()
-        .filter(|(_, t)| t.frontmatter.blocked_by.contains(task.id()))
-        .collect();
-
-    if !blocks.is_empty() {
+    if !task.frontmatter.blocks.is_empty() {
         println!("{}", "=== Blocks ===".bold());
         println!();
-        for (blocked_path, blocked_task) in blocks {
-            let blocked_short = blocked_task.id().shortest_unique_prefix(&all_ids);
-            let blocked_status = store.status_from_path(blocked_path).unwrap_or(Status::Open);
-            println!(
-                "  {} {} [{}]",
-                blocked_short.cyan(),
-                blocked_task.title(),
-                format_status(blocked_status)
-            );
+        for blocked_id in &task.frontmatter.blocks {
+            let blocked_short = resolver.shortest_prefix(blocked_id);
+            match store.find(blocked_id.full()) {
+                Ok(blocked_path) => {
+                    let blocked_task = store.load(&blocked_path)?;
+                    let blocked_status = store.status_from_path(&blocked_path).unwrap_or(Status::Open);
+                    println!(
+                        "  {} {} [{}]",
+                        blocked_short.cyan(),
+                        blocked_task.title(),
+                        format_status(&blocked_status)
+                    );
+                }
+                Err(_) => {
+                    println!("  {} {}", blocked_short.cyan(), "(not found)".dimmed());
+                }
+            }
         }
         println!();
     }
 
-    // === Recent Log Entries ===
-    // Use the task's log field directly
-    if !task.log.trim().is_empty() {
-        let entries: Vec<_> = task
-            .log
-            .split("\n---\n# Log: ")
-            .take(5) // Last 5 entries
-            .collect();
-
-        if !entries.is_empty() {
-            println!("{}", "=== Recent Activity ===".bold());
-            println!();
-            for entry in entries {
-                let lines: Vec<&str> = entry.lines().collect();
-                if lines.is_empty() {
-                    continue;
+    // === Subtasks ===
+    if !task.frontmatter.children.is_empty() {
+        println!("{}", "=== Subtasks ===".bold());
+        println!();
+        for child_id in &task.frontmatter.children {
+            let child_short = resolver.shortest_prefix(child_id);
+            match store.find(child_id.full()) {
+                Ok(child_path) => {
+                    let child = store.load(&child_path)?;
+                    let child_status = store.status_from_path(&child_path).unwrap_or(Status::Open);
+                    println!(
+                        "  {} {} [{}]",
+                        child_short.cyan(),
+                        child.title(),
+                        format_status(&child_status)
+                    );
                 }
-
-                // Parse header: "2025-11-26T23:42:29Z Author Name"
-                // Handle first entry which starts with "---\n# Log: " (no leading newline)
-                let header = lines[0]
-                    .trim_start_matches("---")
-                    .trim_start()
-                    .trim_start_matches("# Log: ");
-                let parts: Vec<&str> = header.splitn(2, ' ').collect();
-                if parts.len() >= 2 {
-                    let timestamp = parts[0];
-                    let author = parts[1];
-                    // Format timestamp nicely
-                    let formatted_time = timestamp
-                        .parse::<chrono::DateTime<chrono::Utc>>()
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
-                        .unwrap_or_else(|_| timestamp.to_string());
-
-                    println!("  {} {}", formatted_time.dimmed(), author.dimmed());
+                Err(_) => {
+                    println!("  {} {}", child_short.cyan(), "(not found)".dimmed());
                 }
+            }
+        }
+        println!();
+    }
 
-                // Get the message (first non-empty line after header)
-                let message: String = lines[1..]
-                    .iter()
-                    .skip_while(|l| l.is_empty())
-                    .take(2) // First 2 lines of message
-                    .map(|s| format!("    {}", s))
-                    .collect::<Vec<_>>()
-                    .join("\n");
+    // === Recent Log Entries ===
+    if !task.log.is_empty() {
+        println!("{}", "=== Recent Activity ===".bold());
+        println!();
+        let recent = &task.log[task.log.len().saturating_sub(5)..]; // Last 5 entries
+        for entry in recent {
+            let time = entry.timestamp.format("%Y-%m-%d %H:%M").to_string();
+            println!("  {} {}", time.dimmed(), entry.author.dimmed());
 
-                if !message.trim().is_empty() {
-                    println!("{}", message);
-                }
-                println!();
+            // First 2 lines of the message
+            let message: String = entry
+                .body
+                .lines()
+                .take(2)
+                .map(|s| format!("    {}", s))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !message.trim().is_empty() {
+                println!("{}", message);
             }
+            println!();
         }
     }
 
     Ok(())
 }
 
-fn format_status(status: Status) -> colored::ColoredString {
+fn format_status(status: &Status) -> colored::ColoredString {
     match status {
         Status::Open => "open".green(),
         Status::InProgress => "in-progress".yellow(),
         Status::Blocked => "blocked".red(),
         Status::Closed => "closed".blue(),
         Status::Cancelled => "cancelled".red(),
+        Status::Custom(name) => name.magenta(),
     }
 }
@@ -0,0 +1,441 @@
+use crate::git;
+use crate::id::TaskId;
+use crate::log::LogEntry;
+use crate::prefix::PrefixResolver;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::{Status, Task};
+use colored::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A task whose status or priority changed
+#[derive(Serialize)]
+struct TaskChange {
+    id: String,
+    #[serde(skip)]
+    short_id: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<String>,
+}
+
+/// Blockers added to a task
+#[derive(Serialize)]
+struct BlockerChange {
+    id: String,
+    #[serde(skip)]
+    short_id: String,
+    title: String,
+    blocked_by: Vec<String>,
+}
+
+/// Log entries added to a task
+#[derive(Serialize)]
+struct LogChange {
+    id: String,
+    #[serde(skip)]
+    short_id: String,
+    title: String,
+    entries: Vec<LogEntry>,
+}
+
+/// Everything that changed between two revisions
+#[derive(Serialize, Default)]
+struct Diff {
+    from: String,
+    to: String,
+    created: Vec<TaskChange>,
+    closed: Vec<TaskChange>,
+    cancelled: Vec<TaskChange>,
+    reopened: Vec<TaskChange>,
+    /// Other status changes (e.g. started or moved to a custom status)
+    moved: Vec<TaskChange>,
+    deleted: Vec<TaskChange>,
+    reprioritised: Vec<TaskChange>,
+    new_blockers: Vec<BlockerChange>,
+    new_log_entries: Vec<LogChange>,
+}
+
+/// Every task at one revision, with its status, keyed by ID
+type Tasks = HashMap<TaskId, (Status, Task)>;
+
+/// Report how tasks changed between revision `from` and revision `to` (or
+/// the working tree)
+pub fn diff(
+    path: &Path,
+    options: &StoreOptions,
+    from: &str,
+    to: Option<&str>,
+    json: bool,
+) -> Result<(), StoreError> {
+    let (diff, old, new) = compare(path, options, from, to)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).unwrap_or_default()
+        );
+    } else {
+        let resolver = PrefixResolver::from_ids(old.keys().chain(new.keys()));
+        let title_of = |id: &TaskId| {
+            new.get(id)
+                .or_else(|| old.get(id))
+                .map(|(_, task)| task.title().to_string())
+        };
+        print_diff(&diff, &resolver, &title_of);
+    }
+    Ok(())
+}
+
+/// The changes between two revisions, and the tasks at each
+fn compare(
+    path: &Path,
+    options: &StoreOptions,
+    from: &str,
+    to: Option<&str>,
+) -> Result<(Diff, Tasks, Tasks), StoreError> {
+    let open_at = |at: Option<&str>| {
+        Store::open_with(
+            path,
+            &StoreOptions {
+                at: at.map(str::to_string),
+                ..options.clone()
+            },
+        )
+    };
+    // A revision from before yatl was set up (or after `.tasks` was
+    // removed) has no tasks: everything shows as created or deleted
+    let current = open_at(None)?;
+    let open_revision = |rev: &str| -> Result<Option<Store>, StoreError> {
+        if git::dir_exists_at(current.tasks_dir(), rev).map_err(StoreError::Git)? {
+            open_at(Some(rev)).map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let old_store = open_revision(from)?;
+    let new_store = match to {
+        Some(rev) => open_revision(rev)?,
+        None => Some(current),
+    };
+    let old = tasks_by_id(old_store.as_ref())?;
+    let new = tasks_by_id(new_store.as_ref())?;
+    let is_terminal = |store: &Option<Store>, status: &Status| {
+        store
+            .as_ref()
+            .is_some_and(|store| store.is_terminal(status))
+    };
+
+    let resolver = PrefixResolver::from_ids(old.keys().chain(new.keys()));
+    let change = |task: &Task, from: Option<String>, to: Option<String>| TaskChange {
+        id: task.id().full().to_string(),
+        short_id: resolver.shortest_prefix(task.id()).to_string(),
+        title: task.title().to_string(),
+        from,
+        to,
+    };
+
+    let mut diff = Diff {
+        from: from.to_string(),
+        to: to.unwrap_or("working tree").to_string(),
+        ..Diff::default()
+    };
+
+    // Oldest tasks first, so the report reads in the order work was added
+    let mut ids: Vec<&TaskId> = old
+        .keys()
+        .chain(new.keys().filter(|id| !old.contains_key(*id)))
+        .collect();
+    ids.sort_by_key(|id| {
+        let (_, task) = new.get(*id).or_else(|| old.get(*id)).unwrap();
+        (task.frontmatter.created, task.id().full().to_string())
+    });
+
+    for id in ids {
+        let (new_status, task) = match (old.get(id), new.get(id)) {
+            (Some((status, task)), None) => {
+                diff.deleted
+                    .push(change(task, Some(status.to_string()), None));
+                continue;
+            }
+            (_, Some((status, task))) => (status, task),
+            (None, None) => continue,
+        };
+        let before = old.get(id);
+        let status_name = Some(new_status.to_string());
+        let was_terminal = before.is_some_and(|(status, _)| is_terminal(&old_store, status));
+        let is_terminal = is_terminal(&new_store, new_status);
+
+        match before {
+            None => diff.created.push(change(task, None, status_name.clone())),
+            Some((old_status, _)) if old_status != new_status => {
+                let from = Some(old_status.to_string());
+                if was_terminal && !is_terminal {
+                    diff.reopened.push(change(task, from, status_name.clone()));
+                } else if was_terminal || !is_terminal {
+                    diff.moved.push(change(task, from, status_name.clone()));
+                }
+            }
+            Some(_) => {}
+        }
+
+        // Includes tasks created and closed between the two revisions
+        if is_terminal && !was_terminal {
+            let from = before.map(|(status, _)| status.to_string());
+            match new_status {
+                Status::Cancelled => diff.cancelled.push(change(task, from, status_name)),
+                _ => diff.closed.push(change(task, from, status_name)),
+            }
+        }
+
+        let old_task = before.map(|(_, task)| task);
+        if let Some(old_task) = old_task {
+            if old_task.priority() != task.priority() {
+                diff.reprioritised.push(change(
+                    task,
+                    Some(old_task.priority().to_string()),
+                    Some(task.priority().to_string()),
+                ));
+            }
+        }
+
+        let blockers: Vec<String> = task
+            .frontmatter
+            .blocked_by
+            .iter()
+            .filter(|blocker| {
+                !old_task.is_some_and(|old_task| old_task.frontmatter.blocked_by.contains(blocker))
+            })
+            .map(|blocker| blocker.full().to_string())
+            .collect();
+        if !blockers.is_empty() {
+            diff.new_blockers.push(BlockerChange {
+                id: task.id().full().to_string(),
+                short_id: resolver.shortest_prefix(task.id()).to_string(),
+                title: task.title().to_string(),
+                blocked_by: blockers,
+            });
+        }
+
+        let old_entries = old_task.map_or(&[][..], |task| &task.log[..]);
+        let entries: Vec<LogEntry> = task
+            .log
+            .iter()
+            .filter(|entry| !old_entries.contains(entry))
+            .cloned()
+            .collect();
+        if !entries.is_empty() {
+            diff.new_log_entries.push(LogChange {
+                id: task.id().full().to_string(),
+                short_id: resolver.shortest_prefix(task.id()).to_string(),
+                title: task.title().to_string(),
+                entries,
+            });
+        }
+    }
+
+    Ok((diff, old, new))
+}
+
+/// Every task at one revision. None is a revision without a `.tasks`
+/// directory.
+fn tasks_by_id(store: Option<&Store>) -> Result<Tasks, StoreError> {
+    let Some(store) = store else {
+        return Ok(HashMap::new());
+    };
+    let tasks = store
+        .list_all_with_archived()?
+        .into_iter()
+        .map(|(path, task)| {
+            let status = store.status_from_path(&path).unwrap_or_default();
+            (task.id().clone(), (status, task))
+        })
+        .collect();
+    store.warn_broken();
+    Ok(tasks)
+}
+
+fn print_diff(
+    diff: &Diff,
+    resolver: &PrefixResolver,
+    title_of: &dyn Fn(&TaskId) -> Option<String>,
+) {
+    let sections = [
+        ("Created", &diff.created),
+        ("Closed", &diff.closed),
+        ("Cancelled", &diff.cancelled),
+        ("Reopened", &diff.reopened),
+        ("Moved", &diff.moved),
+        ("Deleted", &diff.deleted),
+        ("Reprioritised", &diff.reprioritised),
+    ];
+    let empty = sections.iter().all(|(_, changes)| changes.is_empty())
+        && diff.new_blockers.is_empty()
+        && diff.new_log_entries.is_empty();
+    if empty {
+        println!("No task changes between {} and {}.", diff.from, diff.to);
+        return;
+    }
+
+    println!("Changes from {} to {}", diff.from.cyan(), diff.to.cyan());
+    for (heading, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        println!("\n{} ({})", heading.bold(), changes.len());
+        for change in changes {
+            let transition = match (&change.from, &change.to) {
+                (Some(from), Some(to)) => format!("{} -> {}", from, to),
+                (None, Some(to)) => to.clone(),
+                (Some(from), None) => from.clone(),
+                (None, None) => String::new(),
+            };
+            println!(
+                "  {}  {}  {}",
+                change.short_id.cyan(),
+                change.title,
+                transition.dimmed()
+            );
+        }
+    }
+
+    if !diff.new_blockers.is_empty() {
+        println!("\n{} ({})", "New blockers".bold(), diff.new_blockers.len());
+        for change in &diff.new_blockers {
+            println!("  {}  {}", change.short_id.cyan(), change.title);
+            for blocker in &change.blocked_by {
+                let id = TaskId::from_string(blocker.clone());
+                println!(
+                    "      blocked by {}  {}",
+                    resolver.shortest_prefix(&id).cyan(),
+                    title_of(&id).unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    if !diff.new_log_entries.is_empty() {
+        let count: usize = diff.new_log_entries.iter().map(|c| c.entries.len()).sum();
+        println!("\n{} ({})", "New log entries".bold(), count);
+        for change in &diff.new_log_entries {
+            println!("  {}  {}", change.short_id.cyan(), change.title);
+            for entry in &change.entries {
+                println!(
+                    "      {}  {}  {}",
+                    entry
+                        .timestamp
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                        .dimmed(),
+                    entry.author.dimmed(),
+                    entry.summary()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{block, log, reopen};
+    use crate::task::Priority;
+    use crate::test_util::{run_git, temp_store};
+
+    fn titles(changes: &[TaskChange]) -> Vec<&str> {
+        let mut titles: Vec<&str> = changes.iter().map(|c| c.title.as_str()).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn test_revisions_without_tasks_dir() {
+        let (root, store) = temp_store("diff");
+        run_git(&root, &["init", "-q"]);
+        run_git(
+            &root,
+            &["commit", "-q", "--allow-empty", "-m", "Before yatl"],
+        );
+        run_git(&root, &["tag", "before"]);
+        store
+            .create(&Task::new("Open task", None), Status::Open)
+            .unwrap();
+        store
+            .create(&Task::new("Done task", None), Status::Closed)
+            .unwrap();
+        run_git(&root, &["add", "-A"]);
+        run_git(&root, &["commit", "-q", "-m", "Add tasks"]);
+
+        let options = StoreOptions::default();
+        for to in [None, Some("HEAD")] {
+            let (diff, old, new) = compare(&root, &options, "before", to).unwrap();
+            assert!(old.is_empty());
+            assert_eq!(new.len(), 2);
+            assert_eq!(titles(&diff.created), ["Done task", "Open task"]);
+            assert_eq!(titles(&diff.closed), ["Done task"]);
+            assert!(diff.deleted.is_empty());
+        }
+
+        let (diff, _, new) = compare(&root, &options, "HEAD", Some("before")).unwrap();
+        assert!(new.is_empty());
+        assert_eq!(titles(&diff.deleted), ["Done task", "Open task"]);
+        assert!(diff.created.is_empty() && diff.closed.is_empty());
+
+        assert!(compare(&root, &options, "no-such-rev", None).is_err());
+    }
+
+    #[test]
+    fn test_changes_between_revisions() {
+        let (root, store) = temp_store("diff-changes");
+        run_git(&root, &["init", "-q"]);
+        let reopened = Task::new("Reopened", None);
+        let reprioritised = Task::new("Reprioritised", None);
+        let blocked = Task::new("Blocked", None);
+        let logged = Task::new("Logged", None);
+        store.create(&reopened, Status::Closed).unwrap();
+        let reprioritised_path = store.create(&reprioritised, Status::Open).unwrap();
+        store.create(&blocked, Status::Open).unwrap();
+        store.create(&logged, Status::Open).unwrap();
+        run_git(&root, &["add", "-A"]);
+        run_git(&root, &["commit", "-q", "-m", "Add tasks"]);
+
+        let options = StoreOptions::default();
+        reopen(&root, &options, reopened.id().full()).unwrap();
+        let mut task = store.load(&reprioritised_path).unwrap();
+        task.frontmatter.priority = Priority::Critical;
+        store.save(&task, &reprioritised_path).unwrap();
+        block(&root, &options, blocked.id().full(), logged.id().full()).unwrap();
+        log(&root, &options, logged.id().full(), "Made progress", None).unwrap();
+
+        let (diff, _, _) = compare(&root, &options, "HEAD", None).unwrap();
+        assert!(diff.created.is_empty() && diff.closed.is_empty() && diff.deleted.is_empty());
+        assert_eq!(titles(&diff.reopened), ["Reopened"]);
+        assert_eq!(diff.reopened[0].from.as_deref(), Some("closed"));
+        assert_eq!(diff.reopened[0].to.as_deref(), Some("open"));
+        assert_eq!(titles(&diff.moved), ["Blocked"]);
+        assert_eq!(titles(&diff.reprioritised), ["Reprioritised"]);
+        assert_eq!(diff.reprioritised[0].from.as_deref(), Some("medium"));
+        assert_eq!(diff.reprioritised[0].to.as_deref(), Some("critical"));
+
+        assert_eq!(diff.new_blockers.len(), 1);
+        assert_eq!(diff.new_blockers[0].title, "Blocked");
+        assert_eq!(diff.new_blockers[0].blocked_by, [logged.id().full()]);
+
+        // Only entries added since the old revision, not the creation entry
+        let logged_entries = diff
+            .new_log_entries
+            .iter()
+            .find(|change| change.title == "Logged")
+            .unwrap();
+        assert!(logged_entries
+            .entries
+            .iter()
+            .any(|entry| entry.body == "Made progress"));
+        assert!(logged_entries
+            .entries
+            .iter()
+            .all(|entry| !logged.log.contains(entry)));
+    }
+}
@@ -0,0 +1,460 @@
+use crate::fields::FieldType;
+use crate::id::TaskId;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::{Status, Task};
+use chrono::Utc;
+use colored::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// A single integrity problem found in the task store
+struct Problem {
+    /// Stable identifier for the kind of problem (safe to match on in scripts)
+    code: &'static str,
+    path: PathBuf,
+    message: String,
+    fixable: bool,
+}
+
+/// Pending repairs for one task file
+#[derive(Default)]
+struct Repair {
+    task: Option<Task>,
+    status: Option<Status>,
+}
+
+/// Check the task store for integrity problems, optionally repairing them
+pub fn doctor(path: &Path, options: &StoreOptions, fix: bool) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let (mut problems, repairs) = diagnose(&store)?;
+
+    if problems.is_empty() {
+        println!("{} No problems found", "info:".blue());
+        return Ok(());
+    }
+
+    if fix && !repairs.is_empty() {
+        let author = store.get_author();
+        let mut tx = store.transaction();
+
+        for (task_path, repair) in repairs {
+            let mut task = match repair.task {
+                Some(task) => task,
+                None => store.load(&task_path)?,
+            };
+
+            match repair.status {
+                Some(status) => {
+                    task.add_log_entry(
+                        LogKind::StatusChange,
+                        &[("to", status.name())],
+                        &format!("Doctor: moved to {} to match its blockers.", status),
+                        author.as_deref(),
+                    );
+                    task.stop_work(Utc::now());
+                    tx.save_to_status(&task, &task_path, status)?;
+                }
+                None => tx.save(&task, &task_path),
+            }
+        }
+
+        tx.commit()?;
+    }
+
+    problems.sort_by(|a, b| a.path.cmp(&b.path).then(a.code.cmp(b.code)));
+    for problem in &problems {
+        let relative = problem
+            .path
+            .strip_prefix(store.tasks_dir())
+            .unwrap_or(&problem.path);
+        println!(
+            "{} [{}] {}: {}",
+            if fix && problem.fixable {
+                "fixed:".green()
+            } else {
+                "problem:".yellow()
+            },
+            problem.code,
+            relative.display(),
+            problem.message
+        );
+    }
+
+    let remaining = problems.iter().filter(|p| !(fix && p.fixable)).count();
+    if remaining > 0 {
+        let fixable = problems.iter().filter(|p| p.fixable).count();
+        if !fix && fixable > 0 {
+            println!(
+                "{} {} problem(s) can be repaired with --fix",
+                "info:".blue(),
+                fixable
+            );
+        }
+        return Err(StoreError::ProblemsFound(remaining));
+    }
+
+    Ok(())
+}
+
+/// Find every problem in the store, with the repairs for fixable ones
+fn diagnose(store: &Store) -> Result<(Vec<Problem>, HashMap<PathBuf, Repair>), StoreError> {
+    let all_tasks = store.list_all_with_archived()?;
+
+    let mut problems: Vec<Problem> = vec![];
+    let mut repairs: HashMap<PathBuf, Repair> = HashMap::new();
+
+    // === Files that don't parse at all ===
+    for (broken_path, message) in store.broken_files() {
+        problems.push(Problem {
+            code: "unparseable",
+            path: broken_path,
+            message,
+            fixable: false,
+        });
+    }
+
+    // Index tasks by frontmatter ID, remembering every location
+    let mut by_id: HashMap<&TaskId, Vec<&(PathBuf, Task)>> = HashMap::new();
+    for entry in &all_tasks {
+        by_id.entry(entry.1.id()).or_default().push(entry);
+    }
+
+    // === Duplicate IDs (never fixed automatically) ===
+    let mut duplicates: HashSet<&TaskId> = HashSet::new();
+    for (id, entries) in &by_id {
+        if entries.len() > 1 {
+            duplicates.insert(*id);
+            for (dup_path, _) in entries {
+                problems.push(Problem {
+                    code: "duplicate-id",
+                    path: dup_path.clone(),
+                    message: format!("ID {} appears in {} files", id, entries.len()),
+                    fixable: false,
+                });
+            }
+        }
+    }
+
+    // === Filename doesn't match frontmatter ID ===
+    for (task_path, task) in &all_tasks {
+        let stem = task_path.file_stem().unwrap_or_default().to_string_lossy();
+        if stem != task.id().full() {
+            problems.push(Problem {
+                code: "id-mismatch",
+                path: task_path.clone(),
+                message: format!("filename does not match frontmatter id {}", task.id()),
+                fixable: false,
+            });
+        }
+    }
+
+    let status_of = |id: &TaskId| -> Option<Status> {
+        by_id
+            .get(id)
+            .and_then(|entries| store.status_from_path(&entries[0].0))
+    };
+    let is_resolved = |id: &TaskId| status_of(id).is_some_and(|s| store.is_terminal(&s));
+
+    // Expected reverse fields, derived from blocked_by and parent
+    let mut expected_blocks: HashMap<&TaskId, Vec<TaskId>> = HashMap::new();
+    let mut expected_children: HashMap<&TaskId, Vec<TaskId>> = HashMap::new();
+
+    for (task_path, task) in &all_tasks {
+        let status = store.status_from_path(task_path);
+
+        // === Blockers that point at no task ===
+        for blocker_id in &task.frontmatter.blocked_by {
+            match by_id.get_key_value(blocker_id) {
+                Some((key, _)) => expected_blocks
+                    .entry(*key)
+                    .or_default()
+                    .push(task.id().clone()),
+                None => problems.push(Problem {
+                    code: "dangling-blocker",
+                    path: task_path.clone(),
+                    message: format!("blocked_by references missing task {}", blocker_id),
+                    fixable: false,
+                }),
+            }
+        }
+
+        // === Parent that points at no task ===
+        if let Some(parent_id) = &task.frontmatter.parent {
+            match by_id.get_key_value(parent_id) {
+                Some((key, _)) => expected_children
+                    .entry(*key)
+                    .or_default()
+                    .push(task.id().clone()),
+                None => problems.push(Problem {
+                    code: "dangling-parent",
+                    path: task_path.clone(),
+                    message: format!("parent references missing task {}", parent_id),
+                    fixable: false,
+                }),
+            }
+        }
+
+        // === Custom field values that don't match config.yaml ===
+        for field in store.custom_fields() {
+            let Some(value) = task.frontmatter.extra.get(field.name.as_str()) else {
+                continue;
+            };
+            let check = field.check(value).and_then(|()| match value.as_str() {
+                Some(id) if field.field_type == FieldType::TaskRef => {
+                    if by_id.contains_key(&TaskId::from_string(id)) {
+                        Ok(())
+                    } else {
+                        Err(format!("references missing task {}", id))
+                    }
+                }
+                _ => Ok(()),
+            });
+            if let Err(e) = check {
+                problems.push(Problem {
+                    code: "invalid-field",
+                    path: task_path.clone(),
+                    message: format!("{}: {}", field.name, e),
+                    fixable: false,
+                });
+            }
+        }
+
+        if duplicates.contains(task.id()) {
+            continue;
+        }
+
+        // Only known blockers can be resolved; missing ones are reported above
+        let unresolved = task
+            .frontmatter
+            .blocked_by
+            .iter()
+            .any(|b| by_id.contains_key(b) && !is_resolved(b));
+
+        // === Task in blocked/ whose blockers were all closed or removed ===
+        // A task moved to blocked/ by hand without blockers is left alone
+        let stale = if task.frontmatter.blocked_by.is_empty() {
+            blockers_removed_since_blocked(task)
+                .then_some("in blocked/ but its blockers were removed")
+        } else {
+            (!unresolved).then_some("in blocked/ but all blockers are resolved")
+        };
+        if let (Some(Status::Blocked), Some(message)) = (&status, stale) {
+            problems.push(Problem {
+                code: "stale-blocked",
+                path: task_path.clone(),
+                message: message.to_string(),
+                fixable: true,
+            });
+            repairs.entry(task_path.clone()).or_default().status = Some(Status::Open);
+        }
+
+        // === Task in open/ that is still blocked ===
+        if status == Some(Status::Open) && unresolved {
+            problems.push(Problem {
+                code: "unresolved-open",
+                path: task_path.clone(),
+                message: "in open/ but has unresolved blockers".to_string(),
+                fixable: true,
+            });
+            repairs.entry(task_path.clone()).or_default().status = Some(Status::Blocked);
+        }
+    }
+
+    // === Reverse fields out of sync with blocked_by / parent ===
+    for (task_path, task) in &all_tasks {
+        if duplicates.contains(task.id()) {
+            continue;
+        }
+
+        let blocks = sorted(expected_blocks.get(task.id()));
+        let children = sorted(expected_children.get(task.id()));
+        let blocks_ok = sorted(Some(&task.frontmatter.blocks)) == blocks;
+        let children_ok = sorted(Some(&task.frontmatter.children)) == children;
+
+        if !blocks_ok {
+            problems.push(Problem {
+                code: "blocks-mismatch",
+                path: task_path.clone(),
+                message: "blocks does not match the blocked_by lists of other tasks".to_string(),
+                fixable: true,
+            });
+        }
+        if !children_ok {
+            problems.push(Problem {
+                code: "children-mismatch",
+                path: task_path.clone(),
+                message: "children does not match the parent fields of other tasks".to_string(),
+                fixable: true,
+            });
+        }
+
+        if !blocks_ok || !children_ok {
+            let mut fixed = task.clone();
+            fixed.frontmatter.blocks = blocks;
+            fixed.frontmatter.children = children;
+            repairs.entry(task_path.clone()).or_default().task = Some(fixed);
+        }
+    }
+
+    Ok((problems, repairs))
+}
+
+/// Whether the log shows a blocker removed since the task last became
+/// blocked, by `yatl block` or a move
+fn blockers_removed_since_blocked(task: &Task) -> bool {
+    for entry in task.log.iter().rev() {
+        match entry.kind {
+            LogKind::BlockerRemoved => return true,
+            LogKind::BlockerAdded => return false,
+            LogKind::StatusChange
+                if entry.metadata.get("to").map(String::as_str) == Some("blocked") =>
+            {
+                return false
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Sort and dedupe a list of IDs for order-insensitive comparison
+fn sorted(ids: Option<&Vec<TaskId>>) -> Vec<TaskId> {
+    let mut ids = ids.cloned().unwrap_or_default();
+    ids.sort_by(|a, b| a.full().cmp(b.full()));
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_store;
+    use std::fs;
+
+    /// Codes of the problems reported for one file
+    fn codes(problems: &[Problem], path: &Path) -> Vec<&'static str> {
+        let mut codes: Vec<_> = problems
+            .iter()
+            .filter(|p| p.path == path)
+            .map(|p| p.code)
+            .collect();
+        codes.sort();
+        codes
+    }
+
+    #[test]
+    fn test_reports_and_fixes_each_problem() {
+        let (root, store) = temp_store("doctor");
+        fs::write(
+            store.tasks_dir().join("config.yaml"),
+            "fields:\n  - name: estimate\n    type: int\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+
+        let mut closed_blocker = Task::new("Closed blocker", None);
+        let mut stale = Task::new("Stale", None);
+        stale.frontmatter.blocked_by = vec![closed_blocker.id().clone()];
+        closed_blocker.frontmatter.blocks = vec![stale.id().clone()];
+        store.create(&closed_blocker, Status::Closed).unwrap();
+        let stale_path = store.create(&stale, Status::Blocked).unwrap();
+
+        // Moved to blocked/ on purpose: not a problem
+        let parked = Task::new("Parked", None);
+        let parked_path = store.create(&parked, Status::Blocked).unwrap();
+
+        let mut unblocked = Task::new("Unblocked by hand", None);
+        unblocked.add_log_entry(
+            LogKind::BlockerAdded,
+            &[("task", "zzzzzzzz")],
+            "Added blocker: zzzzzzzz",
+            None,
+        );
+        unblocked.add_log_entry(
+            LogKind::BlockerRemoved,
+            &[("task", "zzzzzzzz")],
+            "Removed blocker: zzzzzzzz",
+            None,
+        );
+        let unblocked_path = store.create(&unblocked, Status::Blocked).unwrap();
+
+        let mut open_blocker = Task::new("Open blocker", None);
+        let mut waiting = Task::new("Waiting", None);
+        waiting.frontmatter.blocked_by = vec![open_blocker.id().clone()];
+        open_blocker.frontmatter.blocks = vec![waiting.id().clone()];
+        store.create(&open_blocker, Status::Open).unwrap();
+        let waiting_path = store.create(&waiting, Status::Open).unwrap();
+
+        let mut dangling = Task::new("Dangling", None);
+        dangling.frontmatter.blocked_by = vec![TaskId::from_string("zzzzzzzz")];
+        dangling.frontmatter.parent = Some(TaskId::from_string("yyyyyyyy"));
+        dangling
+            .frontmatter
+            .extra
+            .insert("estimate".into(), "lots".into());
+        let dangling_path = store.create(&dangling, Status::Closed).unwrap();
+
+        let mut reverse = Task::new("Wrong reverse links", None);
+        reverse.frontmatter.blocks = vec![parked.id().clone()];
+        reverse.frontmatter.children = vec![parked.id().clone()];
+        let reverse_path = store.create(&reverse, Status::Open).unwrap();
+
+        let duplicate_path = store
+            .create(&Task::new("Duplicate", None), Status::Open)
+            .unwrap();
+        let copy_path = store
+            .tasks_dir()
+            .join("closed")
+            .join(duplicate_path.file_name().unwrap());
+        fs::copy(&duplicate_path, &copy_path).unwrap();
+
+        let renamed_path = store
+            .create(&Task::new("Renamed", None), Status::Open)
+            .unwrap();
+        let mismatch_path = renamed_path.with_file_name("00000000.md");
+        fs::rename(&renamed_path, &mismatch_path).unwrap();
+
+        let broken_path = store.tasks_dir().join("open").join("broken.md");
+        fs::write(&broken_path, "not a task").unwrap();
+
+        let store = Store::open(&root).unwrap();
+        let (problems, _) = diagnose(&store).unwrap();
+        assert_eq!(codes(&problems, &stale_path), ["stale-blocked"]);
+        assert_eq!(codes(&problems, &unblocked_path), ["stale-blocked"]);
+        assert!(codes(&problems, &parked_path).is_empty());
+        assert_eq!(codes(&problems, &waiting_path), ["unresolved-open"]);
+        assert_eq!(
+            codes(&problems, &dangling_path),
+            ["dangling-blocker", "dangling-parent", "invalid-field"]
+        );
+        assert_eq!(
+            codes(&problems, &reverse_path),
+            ["blocks-mismatch", "children-mismatch"]
+        );
+        assert_eq!(codes(&problems, &duplicate_path), ["duplicate-id"]);
+        assert_eq!(codes(&problems, &copy_path), ["duplicate-id"]);
+        assert_eq!(codes(&problems, &mismatch_path), ["id-mismatch"]);
+        assert_eq!(codes(&problems, &broken_path), ["unparseable"]);
+        assert_eq!(problems.len(), 12);
+
+        // --fix repairs what it can and still reports the rest
+        assert!(matches!(
+            doctor(&root, &StoreOptions::default(), true),
+            Err(StoreError::ProblemsFound(7))
+        ));
+        let store = Store::open(&root).unwrap();
+        let (problems, _) = diagnose(&store).unwrap();
+        assert!(problems.iter().all(|p| !p.fixable));
+        assert_eq!(problems.len(), 7);
+
+        let status = |task: &Task| store.status_from_path(&store.find(task.id().full()).unwrap());
+        assert_eq!(status(&stale), Some(Status::Open));
+        assert_eq!(status(&unblocked), Some(Status::Open));
+        assert_eq!(status(&parked), Some(Status::Blocked));
+        assert_eq!(status(&waiting), Some(Status::Blocked));
+        let reverse = store.load(&reverse_path).unwrap();
+        assert!(reverse.frontmatter.blocks.is_empty());
+        assert!(reverse.frontmatter.children.is_empty());
+    }
+}
@@ -1,27 +1,36 @@
-use crate::store::{Store, StoreError};
+use crate::store::{Store, StoreError, StoreOptions};
 use chrono::Utc;
 use std::path::Path;
 use std::process::Command;
 
-pub fn edit(path: &Path, id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
-    let task_path = store.find(id)?;
+/// The user's editor: `$EDITOR`, or vi if it isn't set
+pub fn editor() -> String {
+    std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string())
+}
 
-    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+pub fn edit(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    // Refuse before the editor opens, not after the user has made changes
+    store.check_writable()?;
+    let task_path = store.find(id)?;
+    let original = store.read_raw(&task_path)?;
 
-    let status = Command::new(&editor)
+    let status = Command::new(editor())
         .arg(&task_path)
         .status()
-        .map_err(|e| StoreError::Parse(format!("Failed to launch editor: {}", e)))?;
+        .map_err(StoreError::EditorLaunch)?;
 
     if !status.success() {
-        return Err(StoreError::Parse("Editor exited with error".to_string()));
+        return Err(StoreError::EditorFailed(status));
     }
 
     // Update the 'updated' timestamp
     let mut task = store.load(&task_path)?;
     task.frontmatter.updated = Utc::now();
-    store.save(&task, &task_path)?;
+    let mut tx = store.transaction();
+    tx.record_original(&task_path, Some(original));
+    tx.save(&task, &task_path);
+    tx.commit()?;
 
     Ok(())
 }
@@ -0,0 +1,298 @@
+use crate::fields::format_value;
+use crate::git;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Task;
+use chrono::{DateTime, Utc};
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A point in a task's history and what changed at it
+struct Event {
+    /// Commit that made the change (None for uncommitted changes and
+    /// entries reconstructed from the log)
+    commit: Option<String>,
+    author: String,
+    date: DateTime<Utc>,
+    changes: Vec<String>,
+}
+
+/// One version of the task file
+struct Version {
+    commit: Option<String>,
+    author: String,
+    date: DateTime<Utc>,
+    path: PathBuf,
+    content: Option<String>,
+}
+
+/// Show when a task changed status and frontmatter fields, from git history
+/// (or from its log when git has none)
+pub fn history(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let task_path = store.find(id)?;
+    let task = store.load(&task_path)?;
+
+    println!("{}  {}", task.id().full().cyan(), task.title().bold());
+    let events = match git_versions(&store, &task_path, &task) {
+        Some(versions) => timeline(&store, &versions),
+        None => {
+            println!(
+                "{}",
+                "(no git history, reconstructed from the task log)".dimmed()
+            );
+            log_timeline(&task)
+        }
+    };
+
+    for event in events {
+        let commit = match &event.commit {
+            Some(commit) => commit.yellow(),
+            None => "-------".dimmed(),
+        };
+        println!(
+            "{}  {}  {}",
+            commit,
+            event.date.format("%Y-%m-%d %H:%M").to_string().dimmed(),
+            event.author
+        );
+        for change in event.changes {
+            println!("    {}", change);
+        }
+    }
+
+    Ok(())
+}
+
+/// Every committed version of the task file plus uncommitted changes, or
+/// None if git can't tell us anything
+fn git_versions(store: &Store, task_path: &Path, task: &Task) -> Option<Vec<Version>> {
+    if !git::is_work_tree(store.tasks_dir()) {
+        return None;
+    }
+    let file_name = task_path.file_name()?.to_str()?;
+    let committed = git::committed_path(store.tasks_dir(), file_name)?;
+    let revisions = git::file_history(store.tasks_dir(), &committed).ok()?;
+    let last_content = revisions.last()?.content.clone();
+
+    let mut versions: Vec<Version> = revisions
+        .into_iter()
+        .map(|revision| Version {
+            commit: Some(revision.commit),
+            author: revision.author,
+            date: revision.date,
+            path: PathBuf::from(revision.path),
+            content: revision.content,
+        })
+        .collect();
+
+    let current = fs::read_to_string(task_path).ok();
+    if current != last_content {
+        // Relative to the top of the work tree, like the committed paths
+        let prefix = git::work_tree_prefix(store.tasks_dir()).ok()?;
+        let relative = task_path.strip_prefix(store.tasks_dir()).ok()?;
+        versions.push(Version {
+            commit: None,
+            author: "(uncommitted)".to_string(),
+            date: task.frontmatter.updated,
+            path: Path::new(&prefix).join(relative),
+            content: current,
+        });
+    }
+    Some(versions)
+}
+
+/// What changed between consecutive versions
+fn timeline(store: &Store, versions: &[Version]) -> Vec<Event> {
+    let status_of = |path: &Path| {
+        store.status_from_path(path).map_or_else(
+            || dir_name(path).unwrap_or("?").to_string(),
+            |status| status.to_string(),
+        )
+    };
+
+    let mut events = vec![];
+    let mut previous: Option<(&Version, Option<Task>)> = None;
+    for version in versions {
+        let task = version
+            .content
+            .as_deref()
+            .and_then(|content| Task::parse(content).ok());
+        let status = status_of(&version.path);
+        let mut changes = vec![];
+
+        match &previous {
+            None => changes.push(format!("created in {}", status.green())),
+            Some((prev, prev_task)) => {
+                if version.content.is_none() {
+                    changes.push("deleted".red().to_string());
+                } else if prev.content.is_none() {
+                    changes.push(format!("restored in {}", status.green()));
+                } else {
+                    let prev_status = status_of(&prev.path);
+                    if prev_status != status {
+                        changes.push(format!("status: {} -> {}", prev_status, status.green()));
+                    } else if prev.path.parent() != version.path.parent() {
+                        changes.push(format!(
+                            "moved to {}/",
+                            version.path.parent().unwrap_or(&version.path).display()
+                        ));
+                    }
+                }
+                if let (Some(before), Some(after)) = (prev_task, &task) {
+                    changes.extend(field_changes(before, after));
+                }
+            }
+        }
+
+        if !changes.is_empty() {
+            events.push(Event {
+                commit: version.commit.clone(),
+                author: version.author.clone(),
+                date: version.date,
+                changes,
+            });
+        }
+        previous = Some((version, task));
+    }
+    events
+}
+
+/// Frontmatter fields (other than `updated`) and body that differ
+fn field_changes(before: &Task, after: &Task) -> Vec<String> {
+    let old = before.frontmatter.fields();
+    let new = after.frontmatter.fields();
+    let mut keys: Vec<_> = old.keys().collect();
+    keys.extend(new.keys().filter(|key| !old.contains_key(*key)));
+
+    let mut changes = vec![];
+    for key in keys {
+        let name = key.as_str().unwrap_or_default();
+        if name == "updated" || name == "yatl_version" {
+            continue;
+        }
+        let (old_value, new_value) = (old.get(key), new.get(key));
+        if old_value != new_value {
+            changes.push(format!(
+                "{}: {} -> {}",
+                name,
+                format_value(old_value),
+                format_value(new_value)
+            ));
+        }
+    }
+    if before.body != after.body {
+        changes.push("body edited".to_string());
+    }
+    changes
+}
+
+/// Status changes recognized in the task's log entries
+fn log_timeline(task: &Task) -> Vec<Event> {
+    let mut events = vec![];
+    let mut status = "open".to_string();
+    for entry in &task.log {
+        let change = match entry.kind {
+            LogKind::Created => format!("created in {}", status.green()),
+            LogKind::StatusChange => {
+                let Some(new_status) = entry.metadata.get("to") else {
+                    continue;
+                };
+                let change = format!("status: {} -> {}", status, new_status.green());
+                status = new_status.clone();
+                change
+            }
+            _ => continue,
+        };
+        events.push(Event {
+            commit: None,
+            author: entry.author.clone(),
+            date: entry.timestamp,
+            changes: vec![change],
+        });
+    }
+    events
+}
+
+fn dir_name(path: &Path) -> Option<&str> {
+    path.parent()?.file_name()?.to_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Priority, Status};
+    use crate::test_util::{run_git, temp_store};
+
+    fn changes(events: &[Event]) -> Vec<Vec<String>> {
+        events.iter().map(|event| event.changes.clone()).collect()
+    }
+
+    #[test]
+    fn test_timeline_from_git() {
+        colored::control::set_override(false);
+        let (root, store) = temp_store("history");
+        let task = Task::new("Fix login", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        run_git(&root, &["init", "-q"]);
+        run_git(&root, &["add", "-A"]);
+        run_git(&root, &["commit", "-q", "-m", "Add task"]);
+
+        let mut tx = store.transaction();
+        let path = tx.move_to_status(&path, Status::InProgress).unwrap();
+        tx.commit().unwrap();
+        run_git(&root, &["add", "-A"]);
+        run_git(&root, &["commit", "-q", "-m", "Start task"]);
+
+        // Uncommitted edits show up last
+        let mut task = store.load(&path).unwrap();
+        task.frontmatter.priority = Priority::High;
+        task.frontmatter.tags = vec!["auth".into(), "web".into()];
+        task.body = "Users can't log in.".into();
+        store.save(&task, &path).unwrap();
+
+        let versions = git_versions(&store, &path, &task).unwrap();
+        assert_eq!(versions.len(), 3);
+        assert_eq!(versions[2].commit, None);
+        assert_eq!(
+            changes(&timeline(&store, &versions)),
+            [
+                vec!["created in open".to_string()],
+                vec!["status: open -> in-progress".to_string()],
+                vec![
+                    "priority: medium -> high".to_string(),
+                    "tags: (not set) -> [auth, web]".to_string(),
+                    "body edited".to_string(),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timeline_from_log_without_git() {
+        colored::control::set_override(false);
+        let (_root, store) = temp_store("history-log");
+        let mut task = Task::new("Fix login", None);
+        task.add_log_entry(
+            LogKind::StatusChange,
+            &[("to", "in-progress")],
+            "Started working.",
+            None,
+        );
+        // Reads like a status change but was written as a comment
+        task.add_log("Moved from open to closed.", None);
+        task.add_log_entry(LogKind::StatusChange, &[("to", "closed")], "Closed.", None);
+        let path = store.create(&task, Status::Closed).unwrap();
+
+        assert!(git_versions(&store, &path, &task).is_none());
+        assert_eq!(
+            changes(&log_timeline(&store.load(&path).unwrap())),
+            [
+                vec!["created in open".to_string()],
+                vec!["status: open -> in-progress".to_string()],
+                vec!["status: in-progress -> closed".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use super::close;
+use crate::git;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+/// First line after the shebang in hooks written by yatl
+const HOOK_MARKER: &str = "# Installed by `yatl hooks install`";
+
+/// Install the commit-msg and post-commit hooks that link commits to tasks
+pub fn install_hooks(path: &Path, options: &StoreOptions, force: bool) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    if !git::is_work_tree(store.tasks_dir()) {
+        return Err(StoreError::NotGitWorkTree);
+    }
+    let hooks_dir = git::hooks_dir(path).map_err(StoreError::Git)?;
+    // Hooks run from the top of the work tree; yatl needs to run where
+    // .tasks is
+    let prefix = git::work_tree_prefix(path).map_err(StoreError::Git)?;
+    let cd = format!("cd \"$(git rev-parse --show-toplevel)/{}\"", prefix);
+
+    let hooks = [
+        (
+            "commit-msg",
+            "expand task references in commit messages",
+            format!(
+                "case \"$1\" in /*) msg=\"$1\" ;; *) msg=\"$PWD/$1\" ;; esac\n\
+                 {} && exec yatl hooks commit-msg \"$msg\"\n",
+                cd
+            ),
+        ),
+        (
+            "post-commit",
+            "log commits on the tasks they reference",
+            format!("{} && exec yatl hooks post-commit\n", cd),
+        ),
+    ];
+
+    fs::create_dir_all(&hooks_dir)?;
+    let hooks_dir = hooks_dir.canonicalize()?;
+    for (name, description, script) in hooks {
+        let hook_path = hooks_dir.join(name);
+        let existing = fs::read_to_string(&hook_path).ok();
+        if existing.is_some_and(|content| !content.contains(HOOK_MARKER)) && !force {
+            eprintln!(
+                "{} {} already exists and wasn't installed by yatl; use --force to replace it",
+                "warning:".yellow(),
+                hook_path.display()
+            );
+            continue;
+        }
+
+        fs::write(
+            &hook_path,
+            format!("#!/bin/sh\n{}: {}\n{}", HOOK_MARKER, description, script),
+        )?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+        }
+        println!("{} Installed {}", "info:".blue(), hook_path.display());
+    }
+
+    Ok(())
+}
+
+/// commit-msg hook: expand task prefixes in the `Task:`/`Refs:`/`Closes:`
+/// trailers to full IDs. A `Task:` or `Closes:` reference that doesn't match
+/// exactly one task fails the hook, which aborts the commit; `Refs:` may
+/// point at other things, so unknown ones there are kept with a warning.
+pub fn commit_msg(path: &Path, options: &StoreOptions, file: &Path) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let message = fs::read_to_string(file)?;
+    let block = git::trailer_block(&message);
+
+    let mut rewritten = String::with_capacity(message.len());
+    let mut unresolved = 0;
+    for (i, line) in message.split_inclusive('\n').enumerate() {
+        let trailer = (block.contains(&i) && !line.starts_with('#'))
+            .then(|| git::parse_trailer(line.trim_end()))
+            .flatten();
+        let Some((trailer, ids)) = trailer else {
+            rewritten.push_str(line);
+            continue;
+        };
+
+        let mut full_ids = vec![];
+        for id in ids {
+            match store.find(id).and_then(|task_path| store.load(&task_path)) {
+                Ok(task) => full_ids.push(task.id().full().to_string()),
+                Err(e) if trailer == "Refs" => {
+                    eprintln!("{} {}: {}: {}", "warning:".yellow(), trailer, id, e);
+                    full_ids.push(id.to_string());
+                }
+                Err(e) => {
+                    eprintln!("{} {}: {}: {}", "error:".red(), trailer, id, e);
+                    unresolved += 1;
+                }
+            }
+        }
+        let newline = if line.ends_with('\n') { "\n" } else { "" };
+        rewritten.push_str(&format!("{}: {}{}", trailer, full_ids.join(", "), newline));
+    }
+
+    if unresolved > 0 {
+        return Err(StoreError::UnresolvedRefs(unresolved));
+    }
+    if rewritten != message {
+        fs::write(file, rewritten)?;
+    }
+    Ok(())
+}
+
+/// post-commit hook: log the commit on every task it references and close
+/// the ones named in `Closes:`
+pub fn post_commit(path: &Path, options: &StoreOptions) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let (hash, subject, message) = git::head_commit(path).map_err(StoreError::Git)?;
+
+    // Closing already logs the commit, so a task that is also closed only
+    // gets the close entry
+    let refs = git::task_refs(&message);
+    let closes: Vec<&str> = refs
+        .iter()
+        .filter(|(trailer, _)| *trailer == "Closes")
+        .map(|(_, id)| id.as_str())
+        .collect();
+    let mut logged: Vec<&str> = vec![];
+
+    for (trailer, id) in &refs {
+        if logged.contains(&id.as_str()) {
+            continue;
+        }
+        logged.push(id);
+
+        let result = if closes.contains(&id.as_str()) {
+            close(
+                path,
+                options,
+                id,
+                Some(&format!("commit {} ({})", hash, subject)),
+            )
+        } else {
+            log_commit(&store, id, &hash, &subject)
+        };
+        if let Err(e) = result {
+            eprintln!("{} {} {}: {}", "warning:".yellow(), trailer, id, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn log_commit(store: &Store, id: &str, hash: &str, subject: &str) -> Result<(), StoreError> {
+    let task_path = store.find(id)?;
+    let mut task = store.load(&task_path)?;
+    let author = store.get_author();
+    task.add_log_entry(
+        LogKind::Commit,
+        &[("commit", hash)],
+        &format!("Commit {}: {}", hash, subject),
+        author.as_deref(),
+    );
+    store.save(&task, &task_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::LogKind;
+    use crate::task::{Status, Task};
+    use crate::test_util::{run_git, temp_store};
+
+    #[test]
+    fn test_commit_msg_expands_task_refs() {
+        let (root, store) = temp_store("commit-msg");
+        let task = Task::new("Fix login", None);
+        store.create(&task, Status::Open).unwrap();
+        let prefix = &task.id().full()[..4];
+
+        let file = root.join("COMMIT_EDITMSG");
+        let message = format!(
+            "Fix login\n\n\
+             Refs: the design doc\n\n\
+             Refs: #123\n\
+             Refs: zzzzzzzz\n\
+             Task: {}\n\
+             # Please enter the commit message\n",
+            prefix
+        );
+        fs::write(&file, &message).unwrap();
+        commit_msg(&root, &StoreOptions::default(), &file).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            message.replace(&format!("Task: {}", prefix), &format!("Task: {}", task.id()))
+        );
+    }
+
+    #[test]
+    fn test_commit_msg_rejects_unknown_task_refs() {
+        let (root, store) = temp_store("commit-msg-unknown");
+        let task = Task::new("Fix login", None);
+        store.create(&task, Status::Open).unwrap();
+
+        let file = root.join("COMMIT_EDITMSG");
+        for trailer in ["Task", "Closes"] {
+            let message = format!(
+                "Fix login\n\nTask: {}\n{}: zzzzzzzz\n",
+                &task.id().full()[..4],
+                trailer
+            );
+            fs::write(&file, &message).unwrap();
+            let result = commit_msg(&root, &StoreOptions::default(), &file);
+            assert!(matches!(result, Err(StoreError::UnresolvedRefs(1))));
+            assert_eq!(fs::read_to_string(&file).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_post_commit_logs_and_closes_tasks() {
+        let (root, store) = temp_store("post-commit");
+        let fixed = Task::new("Fix login", None);
+        let related = Task::new("Login docs", None);
+        store.create(&fixed, Status::InProgress).unwrap();
+        let related_path = store.create(&related, Status::Open).unwrap();
+
+        run_git(&root, &["init", "-q"]);
+        run_git(
+            &root,
+            &[
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                &format!(
+                    "Fix login\n\nTask: {}\nCloses: {}",
+                    related.id(),
+                    fixed.id()
+                ),
+            ],
+        );
+        post_commit(&root, &StoreOptions::default()).unwrap();
+
+        let store = Store::open(&root).unwrap();
+        let fixed_path = store.find(fixed.id().full()).unwrap();
+        assert_eq!(store.status_from_path(&fixed_path), Some(Status::Closed));
+        let fixed = store.load(&fixed_path).unwrap();
+        let closed = fixed.log.last().unwrap();
+        assert_eq!(closed.kind, LogKind::StatusChange);
+        assert!(closed.body.starts_with("Closed: commit "));
+        assert!(closed.body.ends_with("(Fix login)"));
+
+        let related = store.load(&related_path).unwrap();
+        let logged = related.log.last().unwrap();
+        assert_eq!(logged.kind, LogKind::Commit);
+        let hash = &logged.metadata["commit"];
+        assert_eq!(logged.body, format!("Commit {}: Fix login", hash));
+    }
+}
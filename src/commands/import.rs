@@ -1,11 +1,12 @@
 use crate::id::TaskId;
-use crate::store::{Store, StoreError};
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::{Priority, Status, Task};
 use colored::*;
 use serde::Deserialize;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Task definition in import file
 #[derive(Deserialize)]
@@ -26,8 +27,8 @@ struct TaskDef {
     body: Option<String>,
 }
 
-pub fn import(path: &Path, file: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn import(path: &Path, options: &StoreOptions, file: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let author = store.get_author();
 
     // Read and parse the YAML file
@@ -69,7 +70,13 @@ pub fn import(path: &Path, file: &str) -> Result<(), StoreError> {
         tasks_to_create.push((task, def.blocked_by));
     }
 
-    // Second pass: resolve blocked_by aliases and create tasks
+    // Second pass: resolve blocked_by aliases and stage all tasks together,
+    // so a failure part way through doesn't leave a partial import behind
+    let mut tx = store.transaction();
+    let mut resolved: Vec<(Task, Status)> = vec![];
+    // Existing tasks that block imported ones, updated with the reverse link
+    let mut existing_blockers: HashMap<PathBuf, Task> = HashMap::new();
+
     for (mut task, blocked_by_aliases) in tasks_to_create {
         let mut has_unresolved_blockers = false;
 
@@ -82,12 +89,18 @@ pub fn import(path: &Path, file: &str) -> Result<(), StoreError> {
                 // Try to find as existing task ID
                 match store.find(&alias) {
                     Ok(blocker_path) => {
-                        let blocker = store.load(&blocker_path)?;
+                        let blocker = match existing_blockers.entry(blocker_path.clone()) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => entry.insert(store.load(&blocker_path)?),
+                        };
                         task.frontmatter.blocked_by.push(blocker.id().clone());
+                        if !blocker.frontmatter.blocks.contains(task.id()) {
+                            blocker.frontmatter.blocks.push(task.id().clone());
+                        }
 
                         // Check if blocker is unresolved
                         if let Some(status) = store.status_from_path(&blocker_path) {
-                            if !matches!(status, Status::Closed | Status::Cancelled) {
+                            if !store.is_terminal(&status) {
                                 has_unresolved_blockers = true;
                             }
                         }
@@ -99,15 +112,46 @@ pub fn import(path: &Path, file: &str) -> Result<(), StoreError> {
             }
         }
 
-        // Create the task
-        let mut task_path = store.create(&task)?;
+        // Create the task, directly in blocked/ if needed
+        let status = if has_unresolved_blockers {
+            Status::Blocked
+        } else {
+            Status::Open
+        };
+        resolved.push((task, status));
+    }
 
-        // Move to blocked if needed
-        if has_unresolved_blockers {
-            task_path = store.move_to_status(&task_path, Status::Blocked)?;
+    // Reverse links between imported tasks
+    let links: Vec<(TaskId, TaskId)> = resolved
+        .iter()
+        .flat_map(|(task, _)| {
+            task.frontmatter
+                .blocked_by
+                .iter()
+                .map(|blocker| (blocker.clone(), task.id().clone()))
+        })
+        .collect();
+    for (blocker_id, blocked_id) in links {
+        if let Some((blocker, _)) = resolved.iter_mut().find(|(t, _)| *t.id() == blocker_id) {
+            if !blocker.frontmatter.blocks.contains(&blocked_id) {
+                blocker.frontmatter.blocks.push(blocked_id);
+            }
         }
+    }
+
+    let mut created = vec![];
+    for (task, status) in &resolved {
+        let task_path = tx.create(task, status.clone());
+        created.push((task.id().clone(), task_path));
+    }
+    for (blocker_path, blocker) in &existing_blockers {
+        tx.save(blocker, blocker_path);
+    }
+
+    tx.commit()?;
 
-        println!("{}", task.id());
+    for (id, task_path) in created {
+        println!("{}", id);
         println!(
             "{} Created: {}",
             "info:".blue(),
@@ -117,3 +161,37 @@ pub fn import(path: &Path, file: &str) -> Result<(), StoreError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_blocks_consistent, temp_store};
+
+    #[test]
+    fn test_import_links_both_tasks() {
+        let (root, store) = temp_store("import");
+        let existing = Task::new("Existing blocker", None);
+        let existing_path = store.create(&existing, Status::Open).unwrap();
+
+        let file = root.join("tasks.yaml");
+        fs::write(
+            &file,
+            format!(
+                "- alias: design\n  title: Design\n\
+                 - alias: build\n  title: Build\n  blocked_by: [design, {}]\n\
+                 - title: Ship\n  blocked_by: [build, design]\n",
+                existing.id()
+            ),
+        )
+        .unwrap();
+        import(&root, &StoreOptions::default(), file.to_str().unwrap()).unwrap();
+
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 4);
+        assert_blocks_consistent(&store);
+        let blocks = store.load(&existing_path).unwrap().frontmatter.blocks;
+        assert_eq!(blocks.len(), 1);
+        let build = store.load(&store.find(blocks[0].full()).unwrap()).unwrap();
+        assert_eq!(build.title(), "Build");
+    }
+}
@@ -2,8 +2,8 @@ use crate::store::{Store, StoreError};
 use colored::*;
 use std::path::Path;
 
-pub fn init(path: &Path) -> Result<(), StoreError> {
-    match Store::init(path) {
+pub fn init(path: &Path, merge_driver: bool) -> Result<(), StoreError> {
+    let store = match Store::init(path) {
         Ok(store) => {
             println!(
                 "{} Initialized task tracker in {}",
@@ -13,12 +13,30 @@ pub fn init(path: &Path) -> Result<(), StoreError> {
             println!("  {}/open/", store.tasks_dir().display());
             println!("  {}/closed/", store.tasks_dir().display());
             println!("  {}/config.yaml", store.tasks_dir().display());
-            Ok(())
+            store
         }
+        // Re-running init with --merge-driver sets up an existing tracker
+        Err(StoreError::AlreadyInitialized) if merge_driver => Store::open(path)?,
         Err(StoreError::AlreadyInitialized) => {
             println!("{} Task directory already exists", "warning:".yellow());
-            Ok(())
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    if merge_driver {
+        if store.install_merge_driver()? {
+            println!(
+                "{} Registered the yatl merge driver for task files",
+                "info:".blue()
+            );
+        } else {
+            println!(
+                "{} Not in a git work tree; updated .gitattributes only, run again inside the repository to register the merge driver",
+                "warning:".yellow()
+            );
         }
-        Err(e) => Err(e),
     }
+
+    Ok(())
 }
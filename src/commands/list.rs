@@ -1,11 +1,15 @@
+use crate::dates;
+use crate::fields::display_value;
 use crate::prefix::PrefixResolver;
-use crate::store::{Store, StoreError};
-use crate::task::{Priority, Status};
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::{Priority, Status, Task};
 use crate::term::LineFormatter;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use colored::*;
 use serde::Serialize;
-use std::path::Path;
+use serde_yaml::Value;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
 
 /// JSON output structure for a task
 #[derive(Serialize)]
@@ -17,37 +21,83 @@ struct TaskJson {
     priority: Priority,
     tags: Vec<String>,
     blocked_by: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    defer_until: Option<NaiveDate>,
     created: DateTime<Utc>,
     updated: DateTime<Utc>,
     author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+    /// Frontmatter fields yatl doesn't know
+    #[serde(skip_serializing_if = "serde_yaml::Mapping::is_empty")]
+    fields: serde_yaml::Mapping,
     body: String,
 }
 
 /// Options for the list command
 pub struct ListOptions<'a> {
     pub all: bool,
+    pub archived: bool,
     pub long: bool,
     pub status_filter: Option<&'a str>,
     pub priority_filter: Option<&'a str>,
     pub tag_filter: Option<&'a str>,
+    /// Only tasks assigned to the current author
+    pub mine: bool,
+    pub assignee_filter: Option<&'a str>,
+    /// `key=value` filters on frontmatter fields, all of which must match
+    pub field_filters: &'a [String],
+    /// Custom fields to show as extra columns
+    pub columns: &'a [String],
+    /// Custom field to sort by, descending if prefixed with `-`
+    pub sort: Option<&'a str>,
     pub search_query: Option<&'a str>,
     pub limit: Option<usize>,
     pub json: bool,
     pub show_body: bool,
 }
 
-pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn list(
+    path: &Path,
+    store_options: &StoreOptions,
+    opts: ListOptions<'_>,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, store_options)?;
+    let field_filters = opts
+        .field_filters
+        .iter()
+        .map(|filter| {
+            filter.split_once('=').ok_or_else(|| {
+                StoreError::InvalidArgument(format!(
+                    "Invalid field filter '{}': expected key=value",
+                    filter
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let tasks = if opts.all {
+    let mut tasks = if opts.archived {
+        store.list_all_with_archived()?
+    } else if opts.all {
         store.list_all()?
     } else {
         store.list_active()?
     };
+    store.retain_assigned(&mut tasks, opts.mine, opts.assignee_filter)?;
+
+    for column in opts.columns {
+        check_field_name(&store, &tasks, column)?;
+    }
+    if let Some(sort) = opts.sort {
+        sort_tasks(&store, &mut tasks, sort)?;
+    }
 
     // Resolve shortest unique prefixes across ALL tasks (including closed/cancelled)
     // This ensures displayed prefixes work with `yatl edit`, which searches all directories
     let resolver = PrefixResolver::new(&store)?;
+    store.warn_broken();
 
     // Auto-detect terminal width for line truncation (disabled for JSON output)
     let formatter = if opts.json {
@@ -56,13 +106,16 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
         LineFormatter::auto()
     };
 
-    // Fixed columns for compact format: short_id (8) + status (11) + priority (8) + tabs (3) = ~30
+    // Fixed columns for compact format: short_id (8) + status (11) + priority (8) + tabs (3) = ~30,
+    // plus a tab stop for each custom field column
     const COMPACT_FIXED_COLS: usize = 30;
+    let compact_fixed_cols = COMPACT_FIXED_COLS + 8 * opts.columns.len();
     // For long format, title is on its own line but we leave some margin
     const LONG_MARGIN: usize = 2;
     // Body preview indent
     const BODY_INDENT: usize = 4;
 
+    let today = dates::today();
     let mut json_tasks: Vec<TaskJson> = Vec::new();
     let mut count = 0;
 
@@ -96,6 +149,13 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
             }
         }
 
+        if !field_filters
+            .iter()
+            .all(|(key, value)| task.field_matches(key, value))
+        {
+            continue;
+        }
+
         // Search in title and body
         if let Some(query) = opts.search_query {
             let query_lower = query.to_lowercase();
@@ -118,18 +178,23 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
                 priority: task.priority(),
                 tags: task.frontmatter.tags.clone(),
                 blocked_by: task.frontmatter.blocked_by.iter().map(|id| id.full().to_string()).collect(),
+                due: task.frontmatter.due,
+                defer_until: task.frontmatter.defer_until,
                 created: task.frontmatter.created,
                 updated: task.frontmatter.updated,
                 author: task.frontmatter.author.clone(),
+                assignee: task.frontmatter.assignee.clone(),
+                fields: task.frontmatter.extra.clone(),
                 body: task.body.clone(),
             });
         } else {
-            let status_colored = match status {
+            let status_colored = match &status {
                 Status::Open => "open".green(),
                 Status::InProgress => "in-progress".yellow(),
                 Status::Blocked => "blocked".red(),
                 Status::Closed => "closed".blue(),
                 Status::Cancelled => "cancelled".red(),
+                Status::Custom(name) => name.magenta(),
             };
 
             let priority_colored = match task.priority() {
@@ -139,14 +204,42 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
                 Priority::Low => "low".blue(),
             };
 
+            // Overdue is only interesting while the task is still active
+            let overdue = task.is_overdue(today) && store.is_active(&status);
+
             if opts.long {
                 let title = formatter.truncate(task.title(), LONG_MARGIN);
-                println!("{}", title.bold());
+                if overdue {
+                    println!("{}", title.red().bold());
+                } else {
+                    println!("{}", title.bold());
+                }
                 println!("  ID: {}", short_id);
                 println!(
                     "  Status: {}  Priority: {}",
                     status_colored, priority_colored
                 );
+                if let Some(assignee) = &task.frontmatter.assignee {
+                    println!("  Assignee: {}", assignee);
+                }
+                if let Some(due) = task.frontmatter.due {
+                    let overdue_note = if overdue { " (overdue)" } else { "" };
+                    println!("  Due: {}{}", due, overdue_note.red());
+                }
+                if let Some(defer_until) = task.frontmatter.defer_until {
+                    println!("  Deferred until: {}", defer_until);
+                }
+                if let Some((done, total)) = store.subtask_progress(task)? {
+                    println!("  {}/{} subtasks done", done, total);
+                }
+                if let Some((done, total)) = task.checklist_progress() {
+                    println!("  {}/{} checklist items done", done, total);
+                }
+                for column in opts.columns {
+                    if let Some(value) = field_value(&store, task, column) {
+                        println!("  {}: {}", column, display_value(value));
+                    }
+                }
                 if opts.show_body {
                     let body_width = formatter
                         .available_width(BODY_INDENT)
@@ -158,10 +251,19 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
                 }
                 println!();
             } else {
-                let title = formatter.truncate(task.title(), COMPACT_FIXED_COLS);
+                let title = formatter.truncate(task.title(), compact_fixed_cols);
+                let title = if overdue { title.red() } else { title.normal() };
+                let columns: String = opts
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let value = field_value(&store, task, column);
+                        format!("{}\t", value.map(display_value).unwrap_or_default())
+                    })
+                    .collect();
                 println!(
-                    "{}\t{}\t{}\t{}",
-                    short_id, status_colored, priority_colored, title
+                    "{}\t{}\t{}\t{}{}",
+                    short_id, status_colored, priority_colored, columns, title
                 );
                 if opts.show_body {
                     let body_width = formatter
@@ -185,6 +287,67 @@ pub fn list(path: &Path, opts: ListOptions<'_>) -> Result<(), StoreError> {
     Ok(())
 }
 
+/// Fail unless `name` is a declared custom field or set on at least one of
+/// the tasks, so a typo isn't mistaken for a field nobody has set
+fn check_field_name(
+    store: &Store,
+    tasks: &[(PathBuf, Task)],
+    name: &str,
+) -> Result<(), StoreError> {
+    let key = Value::from(name);
+    if store.custom_field(name).is_some()
+        || tasks
+            .iter()
+            .any(|(_, task)| task.frontmatter.extra.contains_key(&key))
+    {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidArgument(format!(
+            "Unknown field '{}': not declared in config.yaml or set on any task",
+            name
+        )))
+    }
+}
+
+/// A task's value for a custom field, or the field's configured default if
+/// the task doesn't set it
+fn field_value<'a>(store: &'a Store, task: &'a Task, name: &str) -> Option<&'a Value> {
+    task.frontmatter
+        .extra
+        .get(name)
+        .or_else(|| store.custom_field(name)?.default.as_ref())
+}
+
+/// Sort tasks by a custom field, descending if `sort` starts with `-`.
+/// Tasks without the field go last either way.
+fn sort_tasks(store: &Store, tasks: &mut [(PathBuf, Task)], sort: &str) -> Result<(), StoreError> {
+    let (name, descending) = match sort.strip_prefix('-') {
+        Some(name) => (name, true),
+        None => (sort, false),
+    };
+    check_field_name(store, tasks, name)?;
+    let field = store.custom_field(name);
+    tasks.sort_by(|(_, a), (_, b)| {
+        match (field_value(store, a, name), field_value(store, b, name)) {
+            (Some(a), Some(b)) => {
+                let order = match field {
+                    Some(field) => field.compare(a, b),
+                    None => display_value(a).cmp(&display_value(b)),
+                };
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+    Ok(())
+}
+
 /// Get a truncated preview of the body (first meaningful line, truncated to max_len)
 fn get_body_preview(body: &str, max_len: usize) -> String {
     // Skip the log section and get the first non-empty line
@@ -201,3 +364,61 @@ fn get_body_preview(body: &str, max_len: usize) -> String {
         format!("{}...", &preview[..max_len - 3])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_store;
+    use std::fs;
+
+    fn titles(tasks: &[(PathBuf, Task)]) -> Vec<&str> {
+        tasks.iter().map(|(_, task)| task.title()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_field() {
+        let (root, store) = temp_store("list-sort");
+        fs::write(
+            store.tasks_dir().join("config.yaml"),
+            "fields:\n  - name: estimate\n    type: int\n    default: 2\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+        let mut tasks = vec![];
+        for (title, estimate, team) in [
+            ("Large", Some(5), None),
+            ("Default", None, Some("web")),
+            ("Small", Some(1), Some("api")),
+        ] {
+            let mut task = Task::new(title, None);
+            if let Some(estimate) = estimate {
+                task.frontmatter
+                    .extra
+                    .insert("estimate".into(), estimate.into());
+            }
+            if let Some(team) = team {
+                task.frontmatter.extra.insert("team".into(), team.into());
+            }
+            tasks.push((PathBuf::new(), task));
+        }
+
+        // Tasks without the field sort by its default
+        sort_tasks(&store, &mut tasks, "estimate").unwrap();
+        assert_eq!(titles(&tasks), ["Small", "Default", "Large"]);
+        sort_tasks(&store, &mut tasks, "-estimate").unwrap();
+        assert_eq!(titles(&tasks), ["Large", "Default", "Small"]);
+
+        // Undeclared fields still sort, with tasks lacking them last
+        sort_tasks(&store, &mut tasks, "-team").unwrap();
+        assert_eq!(titles(&tasks), ["Default", "Small", "Large"]);
+
+        for name in ["estimat", "due", "-priority"] {
+            assert!(matches!(
+                sort_tasks(&store, &mut tasks, name),
+                Err(StoreError::InvalidArgument(_))
+            ));
+        }
+        assert!(check_field_name(&store, &tasks, "team").is_ok());
+        assert!(check_field_name(&store, &tasks, "teams").is_err());
+    }
+}
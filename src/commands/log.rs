@@ -1,14 +1,59 @@
-use crate::store::{Store, StoreError};
+use crate::dates;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::WorkInterval;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn log(path: &Path, id: &str, message: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+/// Add a log entry to a task, recording `spent` (e.g. `45m`) as time worked
+/// up to now when given
+pub fn log(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    message: &str,
+    spent: Option<&str>,
+) -> Result<(), StoreError> {
+    let spent = spent
+        .map(dates::parse_duration)
+        .transpose()
+        .map_err(StoreError::InvalidArgument)?;
+
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
 
     let mut task = store.load(&task_path)?;
     let author = store.get_author();
-    task.add_log(message, author.as_deref());
+    match spent {
+        Some(spent) => {
+            let now = Utc::now();
+            let start = now.checked_sub_signed(spent).ok_or_else(|| {
+                StoreError::InvalidArgument(format!(
+                    "Time spent is too long: {}",
+                    dates::format_duration(spent)
+                ))
+            })?;
+            task.frontmatter.work.push(WorkInterval {
+                start,
+                end: Some(now),
+                author: author.clone(),
+            });
+            let spent = dates::format_duration(spent);
+            let entry = if message.is_empty() {
+                format!("Spent {}.", spent)
+            } else {
+                format!("Spent {}: {}", spent, message)
+            };
+            task.add_log_entry(
+                LogKind::TimeSpent,
+                &[("spent", &spent)],
+                &entry,
+                author.as_deref(),
+            );
+        }
+        None => task.add_log(message, author.as_deref()),
+    }
 
     store.save(&task, &task_path)?;
 
@@ -0,0 +1,40 @@
+use crate::git;
+use crate::merge::{self, Merged};
+use crate::store::StoreError;
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+/// Git merge driver for task files: merge `ours` and `theirs` (with common
+/// ancestor `base`) and write the result to `ours`, failing if conflicts remain
+pub fn merge_driver(base: &Path, ours: &Path, theirs: &Path) -> Result<(), StoreError> {
+    let base_content = fs::read_to_string(base)?;
+    let ours_content = fs::read_to_string(ours)?;
+    let theirs_content = fs::read_to_string(theirs)?;
+
+    let merged = match merge::merge(&base_content, &ours_content, &theirs_content) {
+        Ok(merged) => merged,
+        Err(message) => {
+            // Not a task file yatl can parse: merge it like git would
+            eprintln!(
+                "{} Cannot merge {} as a task ({}); merging lines instead",
+                "warning:".yellow(),
+                ours.display(),
+                message
+            );
+            let (content, conflicted) =
+                git::merge_file(&base_content, &ours_content, &theirs_content)
+                    .map_err(StoreError::Git)?;
+            Merged {
+                content,
+                conflicted,
+            }
+        }
+    };
+
+    fs::write(ours, &merged.content)?;
+    if merged.conflicted {
+        return Err(StoreError::MergeConflict(ours.to_path_buf()));
+    }
+    Ok(())
+}
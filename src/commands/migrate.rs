@@ -0,0 +1,154 @@
+use crate::migrate;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Task;
+use colored::*;
+use std::path::Path;
+
+/// Lines of unchanged context shown around each change in --dry-run diffs
+const CONTEXT: usize = 2;
+
+/// Upgrade every task file to the current format version
+pub fn migrate(path: &Path, options: &StoreOptions, dry_run: bool) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let mut tx = store.transaction();
+    let mut migrated = 0;
+    let mut failed = 0;
+
+    for task_path in store.task_paths()? {
+        let relative = task_path
+            .strip_prefix(store.tasks_dir())
+            .unwrap_or(&task_path)
+            .to_path_buf();
+        let content = store.read_raw(&task_path)?;
+
+        let result = migrate::migrate(&content).and_then(|m| match m {
+            // The upgraded file must parse with the current format
+            Some(m) => Task::parse(&m.content).map(|task| Some((m, task))),
+            None => Ok(None),
+        });
+        let (upgrade, task) = match result {
+            Ok(Some(upgrade)) => upgrade,
+            Ok(None) => continue,
+            Err(message) => {
+                eprintln!(
+                    "{} Cannot migrate {}: {}",
+                    "warning:".yellow(),
+                    relative.display(),
+                    message
+                );
+                failed += 1;
+                continue;
+            }
+        };
+
+        if dry_run {
+            println!("{}", format!("--- a/{}", relative.display()).bold());
+            println!("{}", format!("+++ b/{}", relative.display()).bold());
+            for description in &upgrade.applied {
+                println!("{}", format!("# {}", description).dimmed());
+            }
+            print_diff(&content, &upgrade.content);
+        } else {
+            tx.save_content(&task, &task_path, upgrade.content);
+            println!(
+                "{} Migrated {} (v{} -> v{})",
+                "info:".blue(),
+                relative.display(),
+                upgrade.from,
+                upgrade.to
+            );
+        }
+        migrated += 1;
+    }
+
+    tx.commit()?;
+
+    if migrated == 0 {
+        println!("{} All task files are up to date", "info:".blue());
+    } else if dry_run {
+        println!(
+            "{} {} file(s) would be migrated (dry run)",
+            "info:".blue(),
+            migrated
+        );
+    } else {
+        println!("{} Migrated {} file(s)", "info:".blue(), migrated);
+    }
+
+    if failed > 0 {
+        return Err(StoreError::ProblemsFound(failed));
+    }
+    Ok(())
+}
+
+/// A line in a line-based diff
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Print a unified-style diff between two versions of a file
+fn print_diff(old: &str, new: &str) {
+    let lines = diff_lines(old, new);
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, DiffLine::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut last_printed: Option<usize> = None;
+    for (i, line) in lines.iter().enumerate() {
+        let near_change = changed.iter().any(|&c| c.abs_diff(i) <= CONTEXT);
+        if !near_change {
+            continue;
+        }
+        if last_printed.is_none_or(|last| last + 1 != i) {
+            println!("{}", "@@".cyan());
+        }
+        match line {
+            DiffLine::Same(text) => println!(" {}", text),
+            DiffLine::Removed(text) => println!("{}", format!("-{}", text).red()),
+            DiffLine::Added(text) => println!("{}", format!("+{}", text).green()),
+        }
+        last_printed = Some(i);
+    }
+}
+
+/// Line diff based on the longest common subsequence (files are small)
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the LCS of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lines.push(DiffLine::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(a[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(a[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(b[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
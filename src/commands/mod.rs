@@ -1,39 +1,71 @@
 pub mod activity;
+pub mod adopt;
+pub mod agenda;
+pub mod archive;
+pub mod assign;
 pub mod block;
+pub mod check;
 pub mod close;
 pub mod context;
+pub mod diff;
+pub mod doctor;
 pub mod edit;
+pub mod history;
+pub mod hooks;
 pub mod import;
 pub mod init;
 pub mod list;
 pub mod log;
+pub mod merge_driver;
+pub mod migrate;
+pub mod move_task;
 pub mod new;
 pub mod next;
+pub mod orphan;
 pub mod ready;
 pub mod reopen;
+pub mod resolve;
 pub mod show;
 pub mod start;
 pub mod stop;
+pub mod timesheet;
 pub mod tree;
 pub mod unblock;
+pub mod undo;
 pub mod update;
 
 pub use activity::activity;
+pub use adopt::adopt;
+pub use agenda::agenda;
+pub use archive::archive;
+pub use assign::assign;
 pub use block::block;
+pub use check::check;
 pub use close::close;
 pub use context::context;
-pub use edit::edit;
+pub use diff::diff;
+pub use doctor::doctor;
+pub use edit::{edit, editor};
+pub use history::history;
+pub use hooks::{commit_msg, install_hooks, post_commit};
 pub use import::import;
 pub use init::init;
 pub use list::{list, ListOptions};
 pub use log::log;
-pub use new::new;
+pub use merge_driver::merge_driver;
+pub use migrate::migrate;
+pub use move_task::move_task;
+pub use new::{new, read_stdin_description, NewOptions};
 pub use next::next;
+pub use orphan::orphan;
 pub use ready::ready;
 pub use reopen::reopen;
+pub use resolve::resolve;
 pub use show::show;
 pub use start::start;
 pub use stop::stop;
+pub use timesheet::{timesheet, TimesheetFormat};
 pub use tree::tree;
 pub use unblock::unblock;
-pub use update::update;
+pub use undo::{redo, undo};
+pub use update::{update, UpdateOptions};
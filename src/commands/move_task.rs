@@ -0,0 +1,72 @@
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Status;
+use chrono::Utc;
+use colored::*;
+use std::path::Path;
+
+pub fn move_task(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    status: &str,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let task_path = store.find(id)?;
+    let target = store.parse_status(status)?;
+
+    let current_status = store
+        .status_from_path(&task_path)
+        .ok_or_else(|| StoreError::UnknownStatusDir(task_path.clone()))?;
+    if current_status == target {
+        println!("{} Task is already {}", "warning:".yellow(), target);
+        return Ok(());
+    }
+    store.check_transition(&current_status, &target)?;
+
+    let mut task = store.load(&task_path)?;
+    if store.is_terminal(&target) {
+        store.check_children_closed(&task)?;
+        store.check_checklist_done(&task)?;
+    }
+    let task_id = task.id().clone();
+
+    let author = store.get_author();
+    task.add_log_entry(
+        LogKind::StatusChange,
+        &[("from", current_status.name()), ("to", target.name())],
+        &format!("Moved from {} to {}.", current_status, target),
+        author.as_deref(),
+    );
+    // Time spent in progress is tracked as work intervals
+    if target == Status::InProgress {
+        task.start_work(author.as_deref(), Utc::now());
+    } else {
+        task.stop_work(Utc::now());
+    }
+
+    // Entering a terminal status resolves this task as a blocker
+    let mut tx = store.transaction();
+    let new_path = tx.save_to_status(&task, &task_path, target.clone())?;
+    let unblocked = if store.is_terminal(&target) && !store.is_terminal(&current_status) {
+        store.unblock_waiting_tasks(&mut tx, &task_id)?
+    } else {
+        vec![]
+    };
+    tx.commit()?;
+
+    println!("{} Moved {} to {}", "info:".blue(), task.id(), target);
+    println!("{} Moved to: {}", "info:".blue(), new_path.display());
+
+    for unblocked_path in unblocked {
+        let unblocked_task = store.load(&unblocked_path)?;
+        println!(
+            "{} Unblocked: {} ({})",
+            "info:".blue(),
+            unblocked_task.id(),
+            unblocked_task.title()
+        );
+    }
+
+    Ok(())
+}
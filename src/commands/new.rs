@@ -1,13 +1,15 @@
-use crate::store::{Store, StoreError};
+use crate::dates;
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::{Priority, Status, Task};
 use chrono::Utc;
 use colored::*;
 use std::io::{self, IsTerminal, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 /// Read description from stdin if it's not a TTY (i.e., piped input)
-fn read_stdin_description() -> Option<String> {
+pub fn read_stdin_description() -> Option<String> {
     if !io::stdin().is_terminal() {
         let mut buffer = String::new();
         if io::stdin().read_to_string(&mut buffer).is_ok() && !buffer.is_empty() {
@@ -17,21 +19,48 @@ fn read_stdin_description() -> Option<String> {
     None
 }
 
+/// Options for the new command
+pub struct NewOptions<'a> {
+    pub title: &'a str,
+    /// Body of the task (piped to stdin)
+    pub description: Option<String>,
+    pub priority: Option<Priority>,
+    pub tags: Option<Vec<String>>,
+    pub blocked_by: Option<Vec<String>>,
+    pub parent: Option<&'a str>,
+    /// Due date (absolute or relative, see `dates::parse_date`)
+    pub due: Option<&'a str>,
+    /// Hide from `ready` until this date
+    pub defer: Option<&'a str>,
+    /// `field=value` assignments for custom fields
+    pub set: &'a [String],
+    /// Editor to open the new task in, if any
+    pub edit: Option<String>,
+}
+
 pub fn new(
     path: &Path,
-    title: &str,
-    priority: Option<Priority>,
-    tags: Option<Vec<String>>,
-    blocked_by: Option<Vec<String>>,
-    edit: bool,
+    options: &StoreOptions,
+    opts: NewOptions<'_>,
 ) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+    let NewOptions {
+        title,
+        description,
+        priority,
+        tags,
+        blocked_by,
+        parent,
+        due,
+        defer,
+        set,
+        edit,
+    } = opts;
+    let store = Store::open_with(path, options)?;
     let author = store.get_author();
 
-    let mut task = Task::new(title, author);
+    let mut task = Task::new(title, author.clone());
 
-    // Read description from stdin if available
-    if let Some(description) = read_stdin_description() {
+    if let Some(description) = description {
         task.body = description;
     }
 
@@ -43,30 +72,76 @@ pub fn new(
         task.frontmatter.tags = t;
     }
 
+    let today = dates::today();
+    if let Some(due) = due {
+        task.frontmatter.due = Some(dates::parse_date(due, today).map_err(StoreError::InvalidArgument)?);
+    }
+    if let Some(defer) = defer {
+        task.frontmatter.defer_until =
+            Some(dates::parse_date(defer, today).map_err(StoreError::InvalidArgument)?);
+    }
+
+    store.apply_field_defaults(&mut task);
+    store.set_fields(&mut task, set)?;
+
+    // Existing tasks that get a reverse link (blocks, children) to the new
+    // one, saved in the same transaction
+    let mut linked: Vec<(PathBuf, Task)> = vec![];
+
     // Track whether we have any unresolved blockers
     let mut has_unresolved_blockers = false;
 
     if let Some(blockers) = blocked_by {
         for blocker_str in blockers {
             let blocker_path = store.find(&blocker_str)?;
-            let blocker = store.load(&blocker_path)?;
+            let blocker = load_linked(&store, &mut linked, blocker_path.clone())?;
             task.frontmatter.blocked_by.push(blocker.id().clone());
+            if !blocker.frontmatter.blocks.contains(task.id()) {
+                blocker.frontmatter.blocks.push(task.id().clone());
+            }
 
-            // Check if this blocker is unresolved (not closed or cancelled)
+            // Check if this blocker is unresolved (not in a terminal status)
             if let Some(status) = store.status_from_path(&blocker_path) {
-                if !matches!(status, Status::Closed | Status::Cancelled) {
+                if !store.is_terminal(&status) {
                     has_unresolved_blockers = true;
                 }
             }
         }
     }
 
-    let mut task_path = store.create(&task)?;
+    if let Some(parent_id) = parent {
+        let parent = load_linked(&store, &mut linked, store.find(parent_id)?)?;
+        task.frontmatter.parent = Some(parent.id().clone());
+        parent.frontmatter.children.push(task.id().clone());
+        parent.add_log_entry(
+            LogKind::SubtaskAdded,
+            &[("task", task.id().full())],
+            &format!("Added subtask: {}", task.id()),
+            author.as_deref(),
+        );
+    }
 
-    // If there are unresolved blockers, move to blocked/
-    if has_unresolved_blockers {
-        task_path = store.move_to_status(&task_path, Status::Blocked)?;
+    // If there are unresolved blockers, create directly in blocked/
+    let status = if has_unresolved_blockers {
+        Status::Blocked
+    } else {
+        Status::Open
+    };
+    // When editing, the creation is recorded together with the edit below
+    let originals: Vec<(PathBuf, Option<String>)> = linked
+        .iter()
+        .map(|(linked_path, _)| (linked_path.clone(), store.read_raw(linked_path).ok()))
+        .collect();
+    let mut tx = store.transaction();
+    let task_path = tx.create(&task, status);
+    for (linked_path, linked_task) in &linked {
+        tx.save(linked_task, linked_path);
     }
+    if edit.is_some() {
+        tx.skip_journal();
+        tx.skip_git_commit();
+    }
+    tx.commit()?;
 
     println!("{}", task.id());
     println!(
@@ -76,23 +151,135 @@ pub fn new(
     );
 
     // Open editor if requested
-    if edit {
-        let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    if let Some(editor) = edit {
+        let edited = match Command::new(&editor).arg(&task_path).status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(StoreError::EditorFailed(status)),
+            Err(e) => Err(StoreError::EditorLaunch(e)),
+        };
 
-        let status = Command::new(&editor)
-            .arg(&task_path)
-            .status()
-            .map_err(|e| StoreError::Parse(format!("Failed to launch editor: {}", e)))?;
+        // Journal and commit the new task and its linked tasks as they were
+        // before, whether or not the edit worked, so undo removes the links
+        let mut task = store.load(&task_path)?;
+        if edited.is_ok() {
+            task.frontmatter.updated = Utc::now();
+        }
+        let mut tx = store.transaction();
+        tx.record_original(&task_path, None);
+        tx.save(&task, &task_path);
+        for (linked_path, original) in originals {
+            let linked_task = store.load(&linked_path)?;
+            tx.record_original(&linked_path, original);
+            tx.save(&linked_task, &linked_path);
+        }
+        tx.commit()?;
+        edited?;
+    }
 
-        if !status.success() {
-            return Err(StoreError::Parse("Editor exited with error".to_string()));
+    Ok(())
+}
+
+/// The task at `path`, loading it into `linked` the first time so a task
+/// linked more than once is saved once with every change
+fn load_linked<'a>(
+    store: &Store,
+    linked: &'a mut Vec<(PathBuf, Task)>,
+    path: PathBuf,
+) -> Result<&'a mut Task, StoreError> {
+    let index = match linked.iter().position(|(p, _)| *p == path) {
+        Some(index) => index,
+        None => {
+            let task = store.load(&path)?;
+            linked.push((path, task));
+            linked.len() - 1
         }
+    };
+    Ok(&mut linked[index].1)
+}
 
-        // Update the 'updated' timestamp after editing
-        let mut task = store.load(&task_path)?;
-        task.frontmatter.updated = Utc::now();
-        store.save(&task, &task_path)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_blocks_consistent, temp_store};
+
+    #[test]
+    fn test_blocked_by_links_both_tasks() {
+        let (root, store) = temp_store("new-blocked");
+        let open = Task::new("Open blocker", None);
+        let closed = Task::new("Closed blocker", None);
+        let open_path = store.create(&open, Status::Open).unwrap();
+        let closed_path = store.create(&closed, Status::Closed).unwrap();
+
+        new(
+            &root,
+            &StoreOptions::default(),
+            NewOptions {
+                title: "Blocked",
+                description: None,
+                priority: None,
+                tags: None,
+                blocked_by: Some(vec![
+                    open.id().full().to_string(),
+                    closed.id().full().to_string(),
+                ]),
+                parent: None,
+                due: None,
+                defer: None,
+                set: &[],
+                edit: None,
+            },
+        )
+        .unwrap();
+
+        let store = Store::open(&root).unwrap();
+        assert_blocks_consistent(&store);
+        let blocks = store.load(&open_path).unwrap().frontmatter.blocks;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks, store.load(&closed_path).unwrap().frontmatter.blocks);
+        let task_path = store.find(blocks[0].full()).unwrap();
+        assert_eq!(store.status_from_path(&task_path), Some(Status::Blocked));
     }
 
-    Ok(())
+    #[test]
+    fn test_edit_then_undo_removes_links() {
+        let (root, store) = temp_store("new-edit");
+        let parent = Task::new("Parent", None);
+        let blocker = Task::new("Blocker", None);
+        let parent_path = store.create(&parent, Status::Open).unwrap();
+        let blocker_path = store.create(&blocker, Status::Open).unwrap();
+
+        let options = StoreOptions {
+            command: Some("new".to_string()),
+            ..Default::default()
+        };
+        new(
+            &root,
+            &options,
+            NewOptions {
+                title: "Child",
+                description: None,
+                priority: None,
+                tags: None,
+                blocked_by: Some(vec![blocker.id().full().to_string()]),
+                parent: Some(parent.id().full()),
+                due: None,
+                defer: None,
+                set: &[],
+                edit: Some("true".to_string()),
+            },
+        )
+        .unwrap();
+
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 3);
+        let parent = store.load(&parent_path).unwrap();
+        let blocker = store.load(&blocker_path).unwrap();
+        assert_eq!(parent.frontmatter.children.len(), 1);
+        assert_eq!(blocker.frontmatter.blocks, parent.frontmatter.children);
+
+        store.undo(1).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 2);
+        assert!(store.load(&parent_path).unwrap().frontmatter.children.is_empty());
+        assert!(store.load(&blocker_path).unwrap().frontmatter.blocks.is_empty());
+    }
 }
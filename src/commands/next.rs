@@ -1,12 +1,21 @@
 use crate::prefix::PrefixResolver;
-use crate::store::{Store, StoreError};
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Priority;
 use colored::*;
 use std::path::Path;
 
-pub fn next(path: &Path) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+/// Suggest the most important ready task, only among those assigned to
+/// `assignee` (or the current author, with `mine`) if given
+pub fn next(
+    path: &Path,
+    options: &StoreOptions,
+    mine: bool,
+    assignee: Option<&str>,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let mut tasks = store.list_ready()?;
+    store.retain_assigned(&mut tasks, mine, assignee)?;
+    store.warn_broken();
 
     if tasks.is_empty() {
         println!("{}", "No tasks ready to work on.".dimmed());
@@ -0,0 +1,69 @@
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Task;
+use colored::*;
+use std::path::{Path, PathBuf};
+
+/// Detach a subtask from its parent
+pub fn orphan(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let child_path = store.find(id)?;
+    let mut child = store.load(&child_path)?;
+
+    let Some(parent_id) = child.frontmatter.parent.clone() else {
+        return Err(StoreError::NotAllowed(format!(
+            "Task {} has no parent",
+            child.id()
+        )));
+    };
+    let former_parent = detach(&store, &child)?;
+
+    let author = store.get_author();
+    child.frontmatter.parent = None;
+    child.add_log_entry(
+        LogKind::ParentRemoved,
+        &[("task", parent_id.as_ref())],
+        &format!("Removed parent: {}", parent_id),
+        author.as_deref(),
+    );
+
+    let mut tx = store.transaction();
+    tx.save(&child, &child_path);
+    if let Some((parent_path, parent)) = &former_parent {
+        tx.save(parent, parent_path);
+    }
+    tx.commit()?;
+
+    println!(
+        "{} Task {} is no longer a subtask of {}",
+        "info:".blue(),
+        child.id(),
+        parent_id
+    );
+    Ok(())
+}
+
+/// The child's current parent with the child removed from its `children`
+/// and the removal logged, ready to be saved. None if the task has no
+/// parent or the parent no longer exists.
+pub(crate) fn detach(store: &Store, child: &Task) -> Result<Option<(PathBuf, Task)>, StoreError> {
+    let Some(parent_id) = &child.frontmatter.parent else {
+        return Ok(None);
+    };
+    let parent_path = match store.find(parent_id.full()) {
+        Ok(path) => path,
+        Err(StoreError::TaskNotFound(_)) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut parent = store.load(&parent_path)?;
+    parent.frontmatter.children.retain(|id| id != child.id());
+    let author = store.get_author();
+    parent.add_log_entry(
+        LogKind::SubtaskRemoved,
+        &[("task", child.id().full())],
+        &format!("Removed subtask: {}", child.id()),
+        author.as_deref(),
+    );
+    Ok(Some((parent_path, parent)))
+}
@@ -1,17 +1,26 @@
 use crate::prefix::PrefixResolver;
-use crate::store::{Store, StoreError};
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Priority;
 use crate::term::LineFormatter;
 use colored::*;
 use std::path::Path;
 
-pub fn ready(path: &Path) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
-    let tasks = store.list_ready()?;
+/// List ready tasks, only those assigned to `assignee` (or the current
+/// author, with `mine`) if given
+pub fn ready(
+    path: &Path,
+    options: &StoreOptions,
+    mine: bool,
+    assignee: Option<&str>,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    let mut tasks = store.list_ready()?;
+    store.retain_assigned(&mut tasks, mine, assignee)?;
 
     // Resolve shortest unique prefixes across ALL tasks (including closed/cancelled)
     // This ensures displayed prefixes work with `yatl edit`, which searches all directories
     let resolver = PrefixResolver::new(&store)?;
+    store.warn_broken();
 
     // Auto-detect terminal width for line truncation
     let formatter = LineFormatter::auto();
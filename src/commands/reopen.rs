@@ -1,18 +1,16 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
 use colored::*;
 use std::path::Path;
 
-pub fn reopen(path: &Path, id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn reopen(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
 
-    // Check if already in an active state
+    // Check if already in a non-terminal state
     let current_status = store.status_from_path(&task_path);
-    if matches!(
-        current_status,
-        Some(Status::Open) | Some(Status::InProgress) | Some(Status::Blocked)
-    ) {
+    if current_status.is_some_and(|status| !store.is_terminal(&status)) {
         println!("{} Task is already open", "warning:".yellow());
         return Ok(());
     }
@@ -20,10 +18,16 @@ pub fn reopen(path: &Path, id: &str) -> Result<(), StoreError> {
     let mut task = store.load(&task_path)?;
 
     let author = store.get_author();
-    task.add_log("Reopened.", author.as_deref());
+    task.add_log_entry(
+        LogKind::StatusChange,
+        &[("to", "open")],
+        "Reopened.",
+        author.as_deref(),
+    );
 
-    store.save(&task, &task_path)?;
-    let new_path = store.move_to_status(&task_path, Status::Open)?;
+    let mut tx = store.transaction();
+    let new_path = tx.save_to_status(&task, &task_path, Status::Open)?;
+    tx.commit()?;
 
     println!("{} Reopened: {}", "info:".blue(), task.id());
     println!("{} Moved to: {}", "info:".blue(), new_path.display());
@@ -0,0 +1,263 @@
+use crate::fields::format_value;
+use crate::log::LogKind;
+use crate::merge;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::{Task, TaskFrontmatter};
+use colored::*;
+use serde_yaml::{Mapping, Value};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// Which version of a conflicting field to keep
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Choice {
+    Ours,
+    Theirs,
+    Merge,
+}
+
+impl Choice {
+    /// How the choice is described in the repair log entry
+    fn summary(self) -> &'static str {
+        match self {
+            Choice::Ours => "from ours",
+            Choice::Theirs => "from theirs",
+            Choice::Merge => "merged",
+        }
+    }
+}
+
+/// Repair a task file damaged by a line-based git merge, asking which side
+/// to keep for every field the two versions disagree on
+pub fn resolve(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    resolve_with(&store, id, &mut io::stdin().lock().lines())
+}
+
+/// Resolve a task, reading the choices from `input` one line at a time
+fn resolve_with(
+    store: &Store,
+    id: &str,
+    input: &mut impl Iterator<Item = io::Result<String>>,
+) -> Result<(), StoreError> {
+    let task_path = store.find(id)?;
+    let content = store.read_raw(&task_path)?;
+
+    let Some(problem) = merge::damage(&content) else {
+        println!("{} Nothing to resolve in {}", "info:".blue(), id);
+        return Ok(());
+    };
+    println!(
+        "{} {}: {}",
+        "info:".blue(),
+        store.relative_path(&task_path),
+        problem
+    );
+
+    let invalid = |side: &str, message: String| StoreError::InvalidTask {
+        path: task_path.clone(),
+        message: format!("{} version: {}", side, message),
+    };
+    let (ours_text, theirs_text) = merge::split_sides(&content);
+    let ours = Task::parse(&ours_text).map_err(|m| invalid("our", m))?;
+    let theirs = Task::parse(&theirs_text).map_err(|m| invalid("their", m))?;
+
+    let ours_fields = ours.frontmatter.fields();
+    let theirs_fields = theirs.frontmatter.fields();
+    let mut keys: Vec<&Value> = ours_fields.keys().collect();
+    keys.extend(
+        theirs_fields
+            .keys()
+            .filter(|key| !ours_fields.contains_key(*key)),
+    );
+
+    let mut resolved = Mapping::new();
+    let mut choices = vec![];
+    for key in keys {
+        let name = key.as_str().unwrap_or_default();
+        let ours_value = ours_fields.get(key);
+        let theirs_value = theirs_fields.get(key);
+        // `updated` is set by the resolution's log entry below
+        let differs = ours_value != theirs_value && name != "updated";
+        let mergeable = matches!(
+            (ours_value, theirs_value),
+            (Some(Value::Sequence(_)), Some(Value::Sequence(_)))
+        );
+
+        let value = if !differs {
+            ours_value.cloned()
+        } else {
+            println!("\n{}", name.bold());
+            println!("  {} {}", "ours:  ".green(), format_value(ours_value));
+            println!("  {} {}", "theirs:".yellow(), format_value(theirs_value));
+            let choice = ask(input, name, mergeable)?;
+            choices.push(format!("{} {}", name, choice.summary()));
+            pick(choice, ours_value, theirs_value)
+        };
+        if let Some(value) = value {
+            resolved.insert(key.clone(), value);
+        }
+    }
+
+    let body = if ours.body == theirs.body {
+        ours.body.clone()
+    } else {
+        println!("\n{}", "body".bold());
+        println!("{}\n{}", "ours:".green(), ours.body);
+        println!("{}\n{}", "theirs:".yellow(), theirs.body);
+        let choice = ask(input, "body", true)?;
+        choices.push(format!("body {}", choice.summary()));
+        match choice {
+            Choice::Ours => ours.body.clone(),
+            Choice::Theirs => theirs.body.clone(),
+            Choice::Merge => format!("{}\n\n{}", ours.body, theirs.body),
+        }
+    };
+
+    let frontmatter: TaskFrontmatter = serde_yaml::from_value(Value::Mapping(resolved))
+        .map_err(|e| StoreError::Parse(format!("Resolved frontmatter is invalid: {}", e)))?;
+    let mut task = Task {
+        frontmatter,
+        body,
+        log: merge::merge_logs(&ours.log, &theirs.log),
+    };
+
+    let author = store.get_author();
+    let summary = if choices.is_empty() {
+        "both versions agreed".to_string()
+    } else {
+        choices.join(", ")
+    };
+    task.add_log_entry(
+        LogKind::Repair,
+        &[],
+        &format!("Resolved merge damage ({}): {}.", problem, summary),
+        author.as_deref(),
+    );
+    store.save(&task, &task_path)?;
+
+    println!("\n{} Resolved: {}", "info:".blue(), task.id());
+    Ok(())
+}
+
+/// The value to keep for a field: one side's, or for lists the entries of
+/// both without duplicates
+fn pick(choice: Choice, ours: Option<&Value>, theirs: Option<&Value>) -> Option<Value> {
+    match choice {
+        Choice::Ours => ours.cloned(),
+        Choice::Theirs => theirs.cloned(),
+        Choice::Merge => {
+            let mut items = vec![];
+            for item in [ours, theirs]
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_sequence)
+                .flatten()
+            {
+                if !items.contains(item) {
+                    items.push(item.clone());
+                }
+            }
+            Some(Value::Sequence(items))
+        }
+    }
+}
+
+/// Prompt until the user picks a side (or merge, if allowed)
+fn ask(
+    input: &mut impl Iterator<Item = io::Result<String>>,
+    name: &str,
+    mergeable: bool,
+) -> Result<Choice, StoreError> {
+    loop {
+        if mergeable {
+            print!("Keep [o]urs, [t]heirs or [m]erge both? ");
+        } else {
+            print!("Keep [o]urs or [t]heirs? ");
+        }
+        io::stdout().flush()?;
+
+        let Some(line) = input.next().transpose()? else {
+            return Err(StoreError::InvalidArgument(format!(
+                "No choice given for '{}'",
+                name
+            )));
+        };
+        match line.trim().to_lowercase().as_str() {
+            "o" | "ours" => return Ok(Choice::Ours),
+            "t" | "theirs" => return Ok(Choice::Theirs),
+            "m" | "merge" if mergeable => return Ok(Choice::Merge),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::{Priority, Status};
+    use crate::test_util::temp_store;
+    use std::fs;
+
+    #[test]
+    fn test_pick() {
+        let ours = Value::from(vec!["a", "b"]);
+        let theirs = Value::from(vec!["b", "c"]);
+        assert_eq!(
+            pick(Choice::Ours, Some(&ours), Some(&theirs)),
+            Some(ours.clone())
+        );
+        assert_eq!(pick(Choice::Theirs, Some(&ours), None), None);
+        assert_eq!(
+            pick(Choice::Merge, Some(&ours), Some(&theirs)),
+            Some(Value::from(vec!["a", "b", "c"]))
+        );
+    }
+
+    #[test]
+    fn test_resolve_asks_for_each_conflicting_field() {
+        let (_root, store) = temp_store("resolve");
+        let mut ours = Task::new("Our title", None);
+        ours.frontmatter.priority = Priority::High;
+        ours.frontmatter.tags = vec!["a".to_string(), "b".to_string()];
+        let task_path = store.create(&ours, Status::Open).unwrap();
+        let mut theirs = ours.clone();
+        theirs.frontmatter.title = "Their title".to_string();
+        theirs.frontmatter.priority = Priority::Low;
+        theirs.frontmatter.tags = vec!["b".to_string(), "c".to_string()];
+        let damaged = format!(
+            "<<<<<<< ours\n{}=======\n{}>>>>>>> theirs\n",
+            ours.to_markdown(),
+            theirs.to_markdown()
+        );
+        fs::write(&task_path, &damaged).unwrap();
+
+        // Merging isn't offered for the title, so it's asked again
+        let mut input = ["m", "t", "o", "m"]
+            .map(|line| Ok(line.to_string()))
+            .into_iter();
+        resolve_with(&store, ours.id().full(), &mut input).unwrap();
+        assert!(input.next().is_none());
+
+        let task = store.load(&task_path).unwrap();
+        assert_eq!(task.title(), "Their title");
+        assert_eq!(task.priority(), Priority::High);
+        assert_eq!(task.frontmatter.tags, ["a", "b", "c"]);
+        let entry = task.log.last().unwrap();
+        assert_eq!(entry.kind, LogKind::Repair);
+        assert_eq!(
+            entry.body,
+            "Resolved merge damage (unresolved merge conflict markers at line 1): \
+             title from theirs, priority from ours, tags merged."
+        );
+
+        // Running out of input leaves the file alone
+        fs::write(&task_path, &damaged).unwrap();
+        let mut input = ["t"].map(|line| Ok(line.to_string())).into_iter();
+        assert!(matches!(
+            resolve_with(&store, ours.id().full(), &mut input),
+            Err(StoreError::InvalidArgument(_))
+        ));
+        assert_eq!(fs::read_to_string(&task_path).unwrap(), damaged);
+    }
+}
@@ -1,6 +1,10 @@
-use crate::store::{Store, StoreError};
+use crate::checklist::ChecklistItem;
+use crate::dates;
+use crate::log::LogEntry;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::{Priority, Status};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use colored::*;
 use serde::Serialize;
 use std::path::Path;
 
@@ -13,18 +17,43 @@ struct TaskJson {
     priority: Priority,
     tags: Vec<String>,
     blocked_by: Vec<String>,
+    blocks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    defer_until: Option<NaiveDate>,
+    /// Finished subtasks, when the task has any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtasks_done: Option<usize>,
+    /// Checklist items in the body
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checklist: Vec<ChecklistItem>,
+    /// Minutes worked, including a running interval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minutes_spent: Option<i64>,
     created: DateTime<Utc>,
     updated: DateTime<Utc>,
     author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+    /// Frontmatter fields yatl doesn't know
+    #[serde(skip_serializing_if = "serde_yaml::Mapping::is_empty")]
+    fields: serde_yaml::Mapping,
     body: String,
-    log: String,
+    log: Vec<LogEntry>,
 }
 
-pub fn show(path: &Path, id: &str, json: bool) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn show(path: &Path, options: &StoreOptions, id: &str, json: bool) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
     let task = store.load(&task_path)?;
     let status = store.status_from_path(&task_path).unwrap_or(Status::Open);
+    let progress = store.subtask_progress(&task)?;
+    let spent = (!task.frontmatter.work.is_empty()).then(|| task.time_spent(Utc::now()));
 
     if json {
         let task_json = TaskJson {
@@ -34,15 +63,34 @@ pub fn show(path: &Path, id: &str, json: bool) -> Result<(), StoreError> {
             priority: task.priority(),
             tags: task.frontmatter.tags.clone(),
             blocked_by: task.frontmatter.blocked_by.iter().map(|id| id.full().to_string()).collect(),
+            blocks: task.frontmatter.blocks.iter().map(|id| id.full().to_string()).collect(),
+            parent: task.frontmatter.parent.as_ref().map(|id| id.full().to_string()),
+            children: task.frontmatter.children.iter().map(|id| id.full().to_string()).collect(),
+            due: task.frontmatter.due,
+            defer_until: task.frontmatter.defer_until,
+            subtasks_done: progress.map(|(done, _)| done),
+            checklist: task.checklist(),
+            minutes_spent: spent.map(|spent| spent.num_minutes()),
             created: task.frontmatter.created,
             updated: task.frontmatter.updated,
             author: task.frontmatter.author.clone(),
+            assignee: task.frontmatter.assignee.clone(),
+            fields: task.frontmatter.extra.clone(),
             body: task.body.clone(),
             log: task.log.clone(),
         };
         println!("{}", serde_json::to_string_pretty(&task_json).unwrap_or_default());
     } else {
         println!("{}", task.to_markdown());
+        if let Some((done, total)) = progress {
+            println!("{}", format!("{}/{} subtasks done", done, total).dimmed());
+        }
+        if let Some((done, total)) = task.checklist_progress() {
+            println!("{}", format!("{}/{} checklist items done", done, total).dimmed());
+        }
+        if let Some(spent) = spent {
+            println!("{}", format!("{} spent", dates::format_duration(spent)).dimmed());
+        }
     }
 
     Ok(())
@@ -1,32 +1,37 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn start(path: &Path, id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn start(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
 
-    let current_status = store.status_from_path(&task_path);
-
-    // Can only start tasks that are in open/
-    if !matches!(current_status, Some(Status::Open)) {
-        let status_str = current_status
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-        return Err(StoreError::Parse(format!(
-            "Cannot start task with status '{}'. Only 'open' tasks can be started.",
-            status_str
-        )));
-    }
+    let current_status = store
+        .status_from_path(&task_path)
+        .ok_or_else(|| StoreError::UnknownStatusDir(task_path.clone()))?;
+
+    // The workflow decides which statuses can be started (by default only open)
+    store.check_transition(&current_status, &Status::InProgress)?;
 
     let mut task = store.load(&task_path)?;
 
+    // Whoever starts a task owns it
     let author = store.get_author();
-    task.add_log("Started working.", author.as_deref());
-
-    store.save(&task, &task_path)?;
-    let new_path = store.move_to_status(&task_path, Status::InProgress)?;
+    if author.is_some() {
+        task.frontmatter.assignee = author.clone();
+    }
+    task.add_log_entry(
+        LogKind::StatusChange,
+        &[("to", "in-progress")],
+        "Started working.",
+        author.as_deref(),
+    );
+    task.start_work(author.as_deref(), Utc::now());
+
+    let new_path = store.save_to_status(&task, &task_path, Status::InProgress)?;
 
     println!("{} Started: {}", "info:".blue(), task.id());
     println!("{} Moved to: {}", "info:".blue(), new_path.display());
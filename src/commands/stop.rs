@@ -1,35 +1,73 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
+use chrono::Utc;
 use colored::*;
 use std::path::Path;
 
-pub fn stop(path: &Path, id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn stop(path: &Path, options: &StoreOptions, id: &str) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
 
-    let current_status = store.status_from_path(&task_path);
+    let current_status = store
+        .status_from_path(&task_path)
+        .ok_or_else(|| StoreError::UnknownStatusDir(task_path.clone()))?;
 
-    // Can only stop tasks that are in-progress
-    if !matches!(current_status, Some(Status::InProgress)) {
-        let status_str = current_status
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-        return Err(StoreError::Parse(format!(
-            "Cannot stop task with status '{}'. Only 'in-progress' tasks can be stopped.",
-            status_str
+    // Only tasks being worked on can be stopped; the workflow decides which
+    // of those may go back to open (by default only in-progress)
+    if current_status == Status::Open || store.is_terminal(&current_status) {
+        return Err(StoreError::NotAllowed(format!(
+            "Cannot stop task with status '{}'. It is not being worked on.",
+            current_status
         )));
     }
+    store.check_transition(&current_status, &Status::Open)?;
 
     let mut task = store.load(&task_path)?;
 
     let author = store.get_author();
-    task.add_log("Stopped working.", author.as_deref());
+    task.add_log_entry(
+        LogKind::StatusChange,
+        &[("to", "open")],
+        "Stopped working.",
+        author.as_deref(),
+    );
+    task.stop_work(Utc::now());
 
-    store.save(&task, &task_path)?;
-    let new_path = store.move_to_status(&task_path, Status::Open)?;
+    let new_path = store.save_to_status(&task, &task_path, Status::Open)?;
 
     println!("{} Stopped: {}", "info:".blue(), task.id());
     println!("{} Moved to: {}", "info:".blue(), new_path.display());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::start;
+    use crate::task::Task;
+    use crate::test_util::temp_store;
+
+    #[test]
+    fn test_start_and_stop_record_work() {
+        let (root, store) = temp_store("work");
+        let task = Task::new("Deploy", None);
+        store.create(&task, Status::Open).unwrap();
+
+        let options = StoreOptions::default();
+        start(&root, &options, task.id().full()).unwrap();
+        let store = Store::open(&root).unwrap();
+        let task_path = store.find(task.id().full()).unwrap();
+        let work = store.load(&task_path).unwrap().frontmatter.work;
+        assert_eq!(work.len(), 1);
+        assert!(work[0].end.is_none());
+
+        stop(&root, &options, task.id().full()).unwrap();
+        let store = Store::open(&root).unwrap();
+        let task_path = store.find(task.id().full()).unwrap();
+        let work = store.load(&task_path).unwrap().frontmatter.work;
+        assert_eq!(work.len(), 1);
+        assert!(work[0].end.is_some_and(|end| end >= work[0].start));
+    }
+}
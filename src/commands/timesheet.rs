@@ -0,0 +1,362 @@
+use crate::dates;
+use crate::id::TaskId;
+use crate::prefix::PrefixResolver;
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Task;
+use chrono::{DateTime, Days, Duration, NaiveDate, Utc};
+use colored::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How timesheet output is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimesheetFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+/// What time is totalled by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    Task,
+    Tag,
+    Author,
+}
+
+impl Grouping {
+    fn parse(name: &str) -> Result<Self, StoreError> {
+        match name {
+            "task" => Ok(Grouping::Task),
+            "tag" => Ok(Grouping::Tag),
+            "author" => Ok(Grouping::Author),
+            _ => Err(StoreError::InvalidArgument(format!(
+                "Invalid grouping '{}': expected task, tag or author",
+                name
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Grouping::Task => "task",
+            Grouping::Tag => "tag",
+            Grouping::Author => "author",
+        }
+    }
+}
+
+/// Time spent on one task, tag or author
+#[derive(Serialize)]
+struct Row {
+    /// Task ID, tag or author
+    name: String,
+    #[serde(skip)]
+    short_id: Option<String>,
+    /// Task title, when grouped by task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip)]
+    spent: Duration,
+    minutes: i64,
+}
+
+#[derive(Serialize)]
+struct Timesheet {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    by: &'static str,
+    rows: Vec<Row>,
+    total_minutes: i64,
+}
+
+/// Total the time worked between `from` and `to` (inclusive, either end
+/// open if None) per task, tag or author
+pub fn timesheet(
+    path: &Path,
+    options: &StoreOptions,
+    from: Option<&str>,
+    to: Option<&str>,
+    by: &str,
+    format: TimesheetFormat,
+) -> Result<(), StoreError> {
+    let grouping = Grouping::parse(by)?;
+    let today = dates::today();
+    let parse = |date: Option<&str>| {
+        date.map(|date| dates::parse_date(date, today))
+            .transpose()
+            .map_err(StoreError::InvalidArgument)
+    };
+    let (from, to) = (parse(from)?, parse(to)?);
+
+    let store = Store::open_with(path, options)?;
+    let tasks = store.list_all_with_archived()?;
+    let resolver = PrefixResolver::new(&store)?;
+    store.warn_broken();
+
+    let range_start = from.map(dates::start_of_day);
+    let range_end = to
+        .and_then(|to| to.checked_add_days(Days::new(1)))
+        .map(dates::start_of_day);
+    let (mut rows, total) = tally(&tasks, grouping, range_start, range_end, Utc::now());
+    if grouping == Grouping::Task {
+        for row in &mut rows {
+            let id = TaskId::from_string(&row.name);
+            row.short_id = Some(resolver.shortest_prefix(&id).to_string());
+        }
+    }
+
+    let timesheet = Timesheet {
+        from,
+        to,
+        by: grouping.name(),
+        rows,
+        total_minutes: total.num_minutes(),
+    };
+    match format {
+        TimesheetFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&timesheet).unwrap_or_default()
+        ),
+        TimesheetFormat::Csv => print!("{}", csv(&timesheet)),
+        TimesheetFormat::Text => print_text(&timesheet, total),
+    }
+    Ok(())
+}
+
+/// Total the time worked inside the range per task, tag or author, most
+/// time first. The overall total counts time on a task with several tags
+/// once.
+fn tally(
+    tasks: &[(PathBuf, Task)],
+    grouping: Grouping,
+    range_start: Option<DateTime<Utc>>,
+    range_end: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> (Vec<Row>, Duration) {
+    let mut totals: HashMap<String, Row> = HashMap::new();
+    let mut total = Duration::zero();
+    for (_, task) in tasks {
+        for work in &task.frontmatter.work {
+            let spent = clipped(work.start, work.end.unwrap_or(now), range_start, range_end);
+            if spent <= Duration::zero() {
+                continue;
+            }
+            total += spent;
+
+            let names = match grouping {
+                Grouping::Task => vec![task.id().full().to_string()],
+                Grouping::Tag if task.frontmatter.tags.is_empty() => {
+                    vec!["(untagged)".to_string()]
+                }
+                Grouping::Tag => task.frontmatter.tags.clone(),
+                Grouping::Author => {
+                    vec![work.author.clone().unwrap_or_else(|| "unknown".to_string())]
+                }
+            };
+            for name in names {
+                let row = totals.entry(name.clone()).or_insert_with(|| Row {
+                    name,
+                    short_id: None,
+                    title: (grouping == Grouping::Task).then(|| task.title().to_string()),
+                    spent: Duration::zero(),
+                    minutes: 0,
+                });
+                row.spent += spent;
+            }
+        }
+    }
+
+    // Most time first
+    let mut rows: Vec<Row> = totals.into_values().collect();
+    for row in &mut rows {
+        row.minutes = row.spent.num_minutes();
+    }
+    rows.sort_by(|a, b| b.spent.cmp(&a.spent).then_with(|| a.name.cmp(&b.name)));
+    (rows, total)
+}
+
+/// Length of `start..end` that falls inside the range
+fn clipped(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    range_start: Option<DateTime<Utc>>,
+    range_end: Option<DateTime<Utc>>,
+) -> Duration {
+    let start = range_start.map_or(start, |range_start| start.max(range_start));
+    let end = range_end.map_or(end, |range_end| end.min(range_end));
+    end - start
+}
+
+fn print_text(timesheet: &Timesheet, total: Duration) {
+    let range = match (timesheet.from, timesheet.to) {
+        (Some(from), Some(to)) => format!("{} to {}", from, to),
+        (Some(from), None) => format!("since {}", from),
+        (None, Some(to)) => format!("until {}", to),
+        (None, None) => "all time".to_string(),
+    };
+    println!("Time spent, {}, by {}", range.cyan(), timesheet.by);
+
+    if timesheet.rows.is_empty() {
+        println!("{}", "No time recorded.".dimmed());
+        return;
+    }
+
+    println!();
+    let width = timesheet
+        .rows
+        .iter()
+        .map(|row| row.short_id.as_deref().unwrap_or(&row.name).chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Total".len());
+    for row in &timesheet.rows {
+        let spent = format!("{:>7}", dates::format_duration(row.spent));
+        match (&row.short_id, &row.title) {
+            (Some(short_id), Some(title)) => {
+                println!("  {:<width$}  {}  {}", short_id.cyan(), spent, title)
+            }
+            _ => println!("  {:<width$}  {}", row.name, spent),
+        }
+    }
+    println!(
+        "\n  {:<width$}  {}",
+        "Total".bold(),
+        format!("{:>7}", dates::format_duration(total)).bold()
+    );
+}
+
+fn csv(timesheet: &Timesheet) -> String {
+    let mut csv = if timesheet.by == "task" {
+        "task,title,minutes\n".to_string()
+    } else {
+        format!("{},minutes\n", timesheet.by)
+    };
+    for row in &timesheet.rows {
+        let line = match &row.title {
+            Some(title) => format!(
+                "{},{},{}\n",
+                csv_field(&row.name),
+                csv_field(title),
+                row.minutes
+            ),
+            None => format!("{},{}\n", csv_field(&row.name), row.minutes),
+        };
+        csv.push_str(&line);
+    }
+    csv
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::WorkInterval;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    /// Work on 2025-01-15 between two times of day
+    fn work(start: &str, end: Option<&str>, author: Option<&str>) -> WorkInterval {
+        let on_day = |time: &str| at(&format!("2025-01-15T{}:00Z", time));
+        WorkInterval {
+            start: on_day(start),
+            end: end.map(on_day),
+            author: author.map(str::to_string),
+        }
+    }
+
+    fn minutes(rows: &[Row]) -> Vec<(&str, i64)> {
+        rows.iter()
+            .map(|row| (row.name.as_str(), row.spent.num_minutes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_clipped() {
+        let (start, end) = (at("2025-01-15T10:00:00Z"), at("2025-01-15T12:00:00Z"));
+        assert_eq!(clipped(start, end, None, None), Duration::hours(2));
+        let from = Some(at("2025-01-15T11:00:00Z"));
+        assert_eq!(clipped(start, end, from, None), Duration::hours(1));
+        let to = Some(at("2025-01-15T10:30:00Z"));
+        assert_eq!(clipped(start, end, None, to), Duration::minutes(30));
+        assert_eq!(clipped(start, end, to, from), Duration::minutes(30));
+        // Work entirely outside the range doesn't count
+        let from = Some(at("2025-01-16T00:00:00Z"));
+        assert!(clipped(start, end, from, None) <= Duration::zero());
+    }
+
+    #[test]
+    fn test_tally_by_tag_and_author() {
+        let mut backend = Task::new("Backend", None);
+        backend.frontmatter.tags = vec!["api".into(), "db".into()];
+        backend.frontmatter.work = vec![
+            work("10:00", Some("11:00"), Some("alice")),
+            work("11:00", Some("11:30"), Some("bob")),
+        ];
+        let mut docs = Task::new("Docs", None);
+        docs.frontmatter.work = vec![
+            work("09:00", Some("09:45"), Some("alice")),
+            work("12:00", None, None),
+        ];
+        let tasks = vec![(PathBuf::new(), backend), (PathBuf::new(), docs)];
+        let now = at("2025-01-15T12:15:00Z");
+
+        // Time on a task with two tags counts for both, but once in the total
+        let (rows, total) = tally(&tasks, Grouping::Tag, None, None, now);
+        assert_eq!(
+            minutes(&rows),
+            [("api", 90), ("db", 90), ("(untagged)", 60)]
+        );
+        assert_eq!(total, Duration::minutes(150));
+
+        let (rows, total) = tally(&tasks, Grouping::Author, None, None, now);
+        assert_eq!(
+            minutes(&rows),
+            [("alice", 105), ("bob", 30), ("unknown", 15)]
+        );
+        assert_eq!(total, Duration::minutes(150));
+
+        // Work is clipped to the range
+        let end = Some(at("2025-01-15T10:30:00Z"));
+        let (rows, total) = tally(&tasks, Grouping::Author, None, end, now);
+        assert_eq!(minutes(&rows), [("alice", 75)]);
+        assert_eq!(total, Duration::minutes(75));
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+
+        let timesheet = Timesheet {
+            from: None,
+            to: None,
+            by: "task",
+            rows: vec![Row {
+                name: "abc123".into(),
+                short_id: Some("a".into()),
+                title: Some("Fix, then \"ship\"".into()),
+                spent: Duration::minutes(30),
+                minutes: 30,
+            }],
+            total_minutes: 30,
+        };
+        assert_eq!(
+            csv(&timesheet),
+            "task,title,minutes\nabc123,\"Fix, then \"\"ship\"\"\",30\n"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::id::TaskId;
 use crate::prefix::PrefixResolver;
-use crate::store::{Store, StoreError};
+use crate::store::{Store, StoreError, StoreOptions};
+use crate::task::Task;
 use crate::term::LineFormatter;
 use colored::*;
 use std::collections::{HashMap, HashSet};
@@ -14,12 +15,22 @@ struct TreeContext<'a> {
     formatter: &'a LineFormatter,
 }
 
-/// Display a DAG of task dependencies
-pub fn tree(path: &Path) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+/// Display a DAG of task dependencies, or the parent/child hierarchy.
+/// Archived tasks are never active, so only the hierarchy can show them.
+pub fn tree(
+    path: &Path,
+    options: &StoreOptions,
+    children: bool,
+    archived: bool,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    if children {
+        return hierarchy(&store, archived);
+    }
 
     // Load all active tasks (open, in-progress, blocked)
     let active_tasks = store.list_active()?;
+    store.warn_broken();
 
     if active_tasks.is_empty() {
         println!("No active tasks.");
@@ -227,3 +238,137 @@ fn print_task_tree(
         );
     }
 }
+
+/// A task in the parent/child hierarchy
+struct Node<'a> {
+    task: &'a Task,
+    short_id: &'a str,
+    active: bool,
+}
+
+/// Display active tasks as a parent/child hierarchy with subtask progress
+fn hierarchy(store: &Store, archived: bool) -> Result<(), StoreError> {
+    let tasks = if archived {
+        store.list_all_with_archived()?
+    } else {
+        store.list_all()?
+    };
+    store.warn_broken();
+    let resolver = PrefixResolver::new(store)?;
+
+    let nodes: HashMap<&TaskId, Node<'_>> = tasks
+        .iter()
+        .map(|(path, task)| {
+            let active = store
+                .status_from_path(path)
+                .is_some_and(|status| store.is_active(&status));
+            let node = Node {
+                task,
+                short_id: resolver.shortest_prefix(task.id()),
+                active,
+            };
+            (task.id(), node)
+        })
+        .collect();
+
+    // Active tasks whose parent isn't shown start their own tree
+    let mut roots: Vec<&Node<'_>> = nodes
+        .values()
+        .filter(|node| {
+            node.active
+                && node
+                    .task
+                    .frontmatter
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| nodes.get(parent))
+                    .is_none_or(|parent| !parent.active)
+        })
+        .collect();
+    if roots.is_empty() {
+        println!("No active tasks.");
+        return Ok(());
+    }
+    roots.sort_by(|a, b| a.task.title().cmp(b.task.title()));
+
+    let formatter = LineFormatter::auto();
+    let mut printed = HashSet::new();
+    for root in roots {
+        print_node(store, root, &nodes, &formatter, &mut printed, "", None)?;
+    }
+    Ok(())
+}
+
+/// Print a task and its subtasks. `is_last` is None for roots.
+fn print_node(
+    store: &Store,
+    node: &Node<'_>,
+    nodes: &HashMap<&TaskId, Node<'_>>,
+    formatter: &LineFormatter,
+    printed: &mut HashSet<TaskId>,
+    prefix: &str,
+    is_last: Option<bool>,
+) -> Result<(), StoreError> {
+    // Guards against parent cycles in hand-edited files
+    if !printed.insert(node.task.id().clone()) {
+        return Ok(());
+    }
+
+    let connector = match is_last {
+        None => "",
+        Some(true) => "└── ",
+        Some(false) => "├── ",
+    };
+    let progress = match store.subtask_progress(node.task)? {
+        Some((done, total)) => format!("  ({}/{} done)", done, total),
+        None => String::new(),
+    };
+    // Box-drawing characters are 1 column wide each
+    let width = prefix.chars().count() + connector.chars().count() + node.short_id.len() + 2;
+    let title = formatter.truncate(node.task.title(), width + progress.len());
+    if node.active {
+        println!(
+            "{}{}{}  {}{}",
+            prefix,
+            connector,
+            node.short_id.green(),
+            title,
+            progress.dimmed()
+        );
+    } else {
+        println!(
+            "{}{}{}  {}{}",
+            prefix,
+            connector,
+            node.short_id.dimmed(),
+            title.dimmed(),
+            progress.dimmed()
+        );
+    }
+
+    let children: Vec<&Node<'_>> = node
+        .task
+        .frontmatter
+        .children
+        .iter()
+        .filter_map(|id| nodes.get(id))
+        .collect();
+    let child_prefix = match is_last {
+        None => prefix.to_string(),
+        Some(true) => format!("{}    ", prefix),
+        Some(false) => format!("{}│   ", prefix),
+    };
+    for (i, child) in children.iter().enumerate() {
+        let is_last_child = i == children.len() - 1;
+        print_node(
+            store,
+            child,
+            nodes,
+            formatter,
+            printed,
+            &child_prefix,
+            Some(is_last_child),
+        )?;
+    }
+    Ok(())
+}
@@ -1,16 +1,28 @@
-use crate::store::{Store, StoreError};
+use crate::log::LogKind;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Status;
 use colored::*;
 use std::path::Path;
 
-pub fn unblock(path: &Path, id: &str, blocker_id: &str) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+pub fn unblock(
+    path: &Path,
+    options: &StoreOptions,
+    id: &str,
+    blocker_id: &str,
+) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
-    let mut task = store.load(&task_path)?;
 
     // Find the blocker to get its full ID
     let blocker_path = store.find(blocker_id)?;
-    let blocker = store.load(&blocker_path)?;
+    if task_path == blocker_path {
+        return Err(StoreError::NotAllowed(format!(
+            "Task {} cannot be blocked by itself",
+            id
+        )));
+    }
+    let mut task = store.load(&task_path)?;
+    let mut blocker = store.load(&blocker_path)?;
     let blocker_full_id = blocker.id().clone();
 
     // Check if the blocker is actually in blocked_by
@@ -18,21 +30,37 @@ pub fn unblock(path: &Path, id: &str, blocker_id: &str) -> Result<(), StoreError
     task.frontmatter.blocked_by.retain(|b| b != &blocker_full_id);
 
     if task.frontmatter.blocked_by.len() == original_len {
-        return Err(StoreError::Parse(format!(
+        return Err(StoreError::NotAllowed(format!(
             "Task {} is not blocked by {}",
             task.id(),
             blocker_full_id
         )));
     }
 
+    blocker.frontmatter.blocks.retain(|b| b != task.id());
+
     // Add log entry
     let author = store.get_author();
-    task.add_log(
+    task.add_log_entry(
+        LogKind::BlockerRemoved,
+        &[("task", blocker_full_id.as_ref())],
         &format!("Removed blocker: {}", blocker_full_id),
         author.as_deref(),
     );
 
-    store.save(&task, &task_path)?;
+    // If no more blockers and task is in blocked/, move it back to open/
+    let current_status = store.status_from_path(&task_path);
+    let mut tx = store.transaction();
+    let moved_to = if task.frontmatter.blocked_by.is_empty()
+        && matches!(current_status, Some(Status::Blocked))
+    {
+        Some(tx.save_to_status(&task, &task_path, Status::Open)?)
+    } else {
+        tx.save(&task, &task_path);
+        None
+    };
+    tx.save(&blocker, &blocker_path);
+    tx.commit()?;
 
     println!(
         "{} Removed blocker {} from {}",
@@ -41,18 +69,68 @@ pub fn unblock(path: &Path, id: &str, blocker_id: &str) -> Result<(), StoreError
         task.id()
     );
 
-    // If no more blockers and task is in blocked/, move it back to open/
-    if task.frontmatter.blocked_by.is_empty() {
-        let current_status = store.status_from_path(&task_path);
-        if matches!(current_status, Some(Status::Blocked)) {
-            let new_path = store.move_to_status(&task_path, Status::Open)?;
-            println!(
-                "{} All blockers removed, moved to: {}",
-                "info:".blue(),
-                new_path.display()
-            );
-        }
+    if let Some(new_path) = moved_to {
+        println!(
+            "{} All blockers removed, moved to: {}",
+            "info:".blue(),
+            new_path.display()
+        );
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::block;
+    use crate::task::Task;
+    use crate::test_util::{assert_blocks_consistent, temp_store};
+
+    #[test]
+    fn test_unblock_removes_both_links() {
+        let (root, store) = temp_store("unblock");
+        let task = Task::new("Deploy", None);
+        let first = Task::new("Fix tests", None);
+        let second = Task::new("Update docs", None);
+        store.create(&task, Status::Open).unwrap();
+        let first_path = store.create(&first, Status::Open).unwrap();
+        let second_path = store.create(&second, Status::Open).unwrap();
+
+        let options = StoreOptions::default();
+        block(&root, &options, task.id().full(), first.id().full()).unwrap();
+        block(&root, &options, task.id().full(), second.id().full()).unwrap();
+        unblock(&root, &options, task.id().full(), first.id().full()).unwrap();
+
+        let store = Store::open(&root).unwrap();
+        assert_blocks_consistent(&store);
+        let task_path = store.find(task.id().full()).unwrap();
+        assert_eq!(store.status_from_path(&task_path), Some(Status::Blocked));
+        let blocks = store.load(&first_path).unwrap().frontmatter.blocks;
+        assert!(blocks.is_empty());
+
+        unblock(&root, &options, task.id().full(), second.id().full()).unwrap();
+        let store = Store::open(&root).unwrap();
+        assert_blocks_consistent(&store);
+        let task_path = store.find(task.id().full()).unwrap();
+        assert_eq!(store.status_from_path(&task_path), Some(Status::Open));
+        let blocks = store.load(&second_path).unwrap().frontmatter.blocks;
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn test_task_cannot_be_unblocked_from_itself() {
+        let (root, store) = temp_store("unblock-self");
+        let task = Task::new("Deploy", None);
+        let path = store.create(&task, Status::Blocked).unwrap();
+
+        let options = StoreOptions::default();
+        let id = task.id().full();
+        let result = unblock(&root, &options, id, id);
+        assert!(matches!(result, Err(StoreError::NotAllowed(_))));
+
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 1);
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::journal::{FileChange, JournalEntry};
+use crate::store::{Store, StoreError, StoreOptions};
+use colored::*;
+use std::path::Path;
+
+/// Reverse the last `count` operations recorded in the journal
+pub fn undo(path: &Path, options: &StoreOptions, count: usize) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    for entry in store.undo(count)? {
+        print_entry("Undid", &entry, |change| &change.before);
+    }
+    Ok(())
+}
+
+/// Re-apply the last `count` undone operations
+pub fn redo(path: &Path, options: &StoreOptions, count: usize) -> Result<(), StoreError> {
+    let store = Store::open_with(path, options)?;
+    for entry in store.redo(count)? {
+        print_entry("Redid", &entry, |change| &change.after);
+    }
+    Ok(())
+}
+
+fn print_entry(action: &str, entry: &JournalEntry, state: impl Fn(&FileChange) -> &Option<String>) {
+    println!(
+        "{} {} '{}' from {}",
+        "info:".blue(),
+        action,
+        entry.command,
+        entry.timestamp.format("%Y-%m-%d %H:%M")
+    );
+    for change in &entry.files {
+        match state(change) {
+            Some(_) => println!("  {} {}", "restored:".green(), change.path),
+            None => println!("  {} {}", "removed:".yellow(), change.path),
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::store::{Store, StoreError};
+use crate::dates;
+use crate::store::{Store, StoreError, StoreOptions};
 use crate::task::Priority;
 use colored::*;
 use std::io::{self, IsTerminal, Read};
@@ -15,18 +16,42 @@ fn read_stdin_body() -> Option<String> {
     None
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Fields to change with the update command (None leaves a field alone)
+#[derive(Default)]
+pub struct UpdateOptions<'a> {
+    pub title: Option<&'a str>,
+    pub priority: Option<Priority>,
+    pub tags: Option<Vec<String>>,
+    pub add_tag: Option<&'a str>,
+    pub remove_tag: Option<&'a str>,
+    /// New body, or "-" to read it from stdin
+    pub body: Option<&'a str>,
+    /// Due date, or "none" to clear it
+    pub due: Option<&'a str>,
+    /// Deferral date, or "none" to clear it
+    pub defer: Option<&'a str>,
+    /// `field=value` assignments for custom fields (an empty value clears)
+    pub set: &'a [String],
+}
+
 pub fn update(
     path: &Path,
+    options: &StoreOptions,
     id: &str,
-    title: Option<&str>,
-    priority: Option<Priority>,
-    tags: Option<Vec<String>>,
-    add_tag: Option<&str>,
-    remove_tag: Option<&str>,
-    body: Option<&str>,
+    opts: UpdateOptions<'_>,
 ) -> Result<(), StoreError> {
-    let store = Store::open(path)?;
+    let UpdateOptions {
+        title,
+        priority,
+        tags,
+        add_tag,
+        remove_tag,
+        body,
+        due,
+        defer,
+        set,
+    } = opts;
+    let store = Store::open_with(path, options)?;
     let task_path = store.find(id)?;
     let mut task = store.load(&task_path)?;
 
@@ -67,6 +92,27 @@ pub fn update(
         }
     }
 
+    // Update dates
+    let today = dates::today();
+    if let Some(due) = due {
+        let due = dates::parse_optional_date(due, today).map_err(StoreError::InvalidArgument)?;
+        if task.frontmatter.due != due {
+            task.frontmatter.due = due;
+            changed = true;
+        }
+    }
+    if let Some(defer) = defer {
+        let defer = dates::parse_optional_date(defer, today).map_err(StoreError::InvalidArgument)?;
+        if task.frontmatter.defer_until != defer {
+            task.frontmatter.defer_until = defer;
+            changed = true;
+        }
+    }
+
+    if store.set_fields(&mut task, set)? {
+        changed = true;
+    }
+
     // Update body - check for stdin first, then explicit value
     if let Some(body_value) = body {
         if body_value == "-" {
@@ -1,4 +1,6 @@
+use crate::fields::FieldConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -7,20 +9,81 @@ use std::path::Path;
 pub struct Config {
     #[serde(default)]
     pub default_author: Option<String>,
+
+    /// Commit the task files each command changes to git
+    #[serde(default)]
+    pub auto_commit: bool,
+
+    /// Refuse to close a task while any of its subtasks are still unfinished
+    #[serde(default)]
+    pub require_children_closed: bool,
+
+    /// What closing a task with unchecked checklist items does
+    #[serde(default, skip_serializing_if = "UncheckedOnClose::is_allow")]
+    pub unchecked_on_close: UncheckedOnClose,
+
+    /// Extra status directories beyond the built-in ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<StatusConfig>,
+
+    /// Allowed status transitions: status name -> statuses it may move to.
+    /// When empty, the built-in workflow is used.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub transitions: BTreeMap<String, Vec<String>>,
+
+    /// Typed custom frontmatter fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldConfig>,
+}
+
+/// Rule for closing a task whose checklist isn't done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UncheckedOnClose {
+    #[default]
+    Allow,
+    /// Close anyway, with a warning
+    Warn,
+    /// Refuse to close
+    Refuse,
+}
+
+impl UncheckedOnClose {
+    fn is_allow(&self) -> bool {
+        *self == UncheckedOnClose::Allow
+    }
+}
+
+/// A custom status declared in config.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusConfig {
+    /// Directory name, also used on the command line
+    pub name: String,
+
+    /// Terminal statuses resolve blockers and are treated like closed
+    #[serde(default)]
+    pub terminal: bool,
+
+    /// Whether tasks in this status show up in the default listing
+    /// (defaults to true for non-terminal statuses)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+}
+
+impl StatusConfig {
+    /// Whether this status counts as active work
+    pub fn is_active(&self) -> bool {
+        self.active.unwrap_or(!self.terminal)
+    }
 }
 
 impl Config {
-    /// Load config from the .tasks directory
-    pub fn load(tasks_dir: &Path) -> Self {
-        let config_path = tasks_dir.join("config.yaml");
-        if config_path.exists() {
-            fs::read_to_string(&config_path)
-                .ok()
-                .and_then(|content| serde_yaml::from_str(&content).ok())
-                .unwrap_or_default()
-        } else {
-            Config::default()
+    /// Parse config.yaml content. An empty file means the defaults.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        if serde_yaml::from_str::<serde_yaml::Value>(content).is_ok_and(|value| value.is_null()) {
+            return Ok(Config::default());
         }
+        serde_yaml::from_str(content).map_err(|e| e.to_string())
     }
 
     /// Save config to the .tasks directory
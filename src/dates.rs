@@ -0,0 +1,187 @@
+//! Calendar dates and durations given on the command line.
+//!
+//! Dates (for `due`, `defer_until` and report ranges) are given either
+//! absolutely (`2025-03-14`) or relative to today: `today`, `tomorrow`, an
+//! offset such as `+3d`, `+2w`, `+1m` or `-1w`, or a weekday name meaning the
+//! next such day (`friday`, `fri`). They are stored in task files as plain
+//! `YYYY-MM-DD`. Durations of work are written like `45m`, `2h` or `1h30m`.
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, Local, Months, NaiveDate, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
+
+/// Today's date in the local time zone
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Parse a date argument relative to `today`
+pub fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.trim().to_lowercase();
+    let invalid = || {
+        format!(
+            "Invalid date '{}': expected YYYY-MM-DD, today, tomorrow, +3d, -2w, +1m or a weekday",
+            input
+        )
+    };
+
+    match input.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return today.checked_add_days(Days::new(1)).ok_or_else(invalid),
+        _ => {}
+    }
+
+    let offset = input
+        .strip_prefix('+')
+        .map(|offset| (offset, true))
+        .or_else(|| input.strip_prefix('-').map(|offset| (offset, false)));
+    if let Some((offset, forward)) = offset {
+        let (split, _) = offset.char_indices().last().ok_or_else(invalid)?;
+        let (count, unit) = offset.split_at(split);
+        let count: u32 = count.parse().map_err(|_| invalid())?;
+        let weeks = u64::from(count) * 7;
+        let date = match (unit, forward) {
+            ("d", true) => today.checked_add_days(Days::new(count.into())),
+            ("d", false) => today.checked_sub_days(Days::new(count.into())),
+            ("w", true) => today.checked_add_days(Days::new(weeks)),
+            ("w", false) => today.checked_sub_days(Days::new(weeks)),
+            ("m", true) => today.checked_add_months(Months::new(count)),
+            ("m", false) => today.checked_sub_months(Months::new(count)),
+            _ => None,
+        };
+        return date.ok_or_else(invalid);
+    }
+
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        // The next such day, a week away if it is today
+        let ahead = (weekday.num_days_from_monday() + 7
+            - today.weekday().num_days_from_monday())
+            % 7;
+        let ahead = if ahead == 0 { 7 } else { ahead };
+        return today
+            .checked_add_days(Days::new(ahead.into()))
+            .ok_or_else(invalid);
+    }
+
+    NaiveDate::parse_from_str(&input, "%Y-%m-%d").map_err(|_| invalid())
+}
+
+/// Parse a date argument that may also be `none` to clear the date
+pub fn parse_optional_date(input: &str, today: NaiveDate) -> Result<Option<NaiveDate>, String> {
+    if input.trim().eq_ignore_ascii_case("none") {
+        Ok(None)
+    } else {
+        parse_date(input, today).map(Some)
+    }
+}
+
+/// The moment a local calendar day begins
+pub fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+}
+
+/// Parse a duration of work such as `45m`, `2h` or `1h30m`
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "Invalid duration '{}': expected e.g. 45m, 2h or 1h30m",
+            input
+        )
+    };
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in input.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let count: i64 = number.parse().map_err(|_| invalid())?;
+        let part = match c.to_ascii_lowercase() {
+            'h' => Duration::try_hours(count),
+            'm' => Duration::try_minutes(count),
+            _ => return Err(invalid()),
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Format a duration as hours and minutes, e.g. `1h 30m` or `45m`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_date() {
+        // A Wednesday
+        let today = date("2025-01-15");
+
+        assert_eq!(parse_date("2025-03-14", today), Ok(date("2025-03-14")));
+        assert_eq!(parse_date("today", today), Ok(today));
+        assert_eq!(parse_date("Tomorrow", today), Ok(date("2025-01-16")));
+        assert_eq!(parse_date("+3d", today), Ok(date("2025-01-18")));
+        assert_eq!(parse_date("+2w", today), Ok(date("2025-01-29")));
+        assert_eq!(parse_date("+1m", today), Ok(date("2025-02-15")));
+        assert_eq!(parse_date("-1w", today), Ok(date("2025-01-08")));
+        assert_eq!(parse_date("-1m", today), Ok(date("2024-12-15")));
+        assert_eq!(parse_date("friday", today), Ok(date("2025-01-17")));
+        assert_eq!(parse_date("mon", today), Ok(date("2025-01-20")));
+        assert_eq!(parse_date("wednesday", today), Ok(date("2025-01-22")));
+
+        assert!(parse_date("+3x", today).is_err());
+        assert!(parse_date("+d", today).is_err());
+        assert!(parse_date("someday", today).is_err());
+        assert!(parse_date("2025-02-30", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_optional_date() {
+        let today = date("2025-01-15");
+        assert_eq!(parse_optional_date("none", today), Ok(None));
+        assert_eq!(
+            parse_optional_date("+1d", today),
+            Ok(Some(date("2025-01-16")))
+        );
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("45m"), Ok(Duration::minutes(45)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert!(parse_duration("45").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("1x").is_err());
+        assert!(parse_duration("9999999999999999h").is_err());
+        assert!(parse_duration("99999999999999999999m").is_err());
+
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(120)), "2h");
+        assert_eq!(format_duration(Duration::minutes(95)), "1h 35m");
+    }
+}
@@ -0,0 +1,237 @@
+//! Custom frontmatter fields declared in config.yaml.
+//!
+//! ```yaml
+//! fields:
+//!   - name: estimate
+//!     type: int
+//!     default: 1
+//!   - name: component
+//!     type: enum
+//!     values: [auth, billing, ui]
+//!   - name: epic
+//!     type: task-ref
+//! ```
+//!
+//! Values are stored in the task's frontmatter after the built-in fields,
+//! like any other key yatl doesn't know. Declaring a field gives it a type:
+//! `--set` values are checked against it, `yatl list` sorts by it and
+//! `yatl doctor` reports stored values that don't match it.
+
+use crate::dates;
+use crate::task::BUILTIN_FIELDS;
+use chrono::NaiveDate;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The type of a custom field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldType {
+    String,
+    Int,
+    /// One of the field's declared `values`
+    Enum,
+    /// A calendar date, stored as `YYYY-MM-DD`
+    Date,
+    /// The full ID of another task
+    TaskRef,
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::String => write!(f, "string"),
+            FieldType::Int => write!(f, "int"),
+            FieldType::Enum => write!(f, "enum"),
+            FieldType::Date => write!(f, "date"),
+            FieldType::TaskRef => write!(f, "task-ref"),
+        }
+    }
+}
+
+/// A custom field declared in config.yaml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldConfig {
+    /// Frontmatter key, also used with `--set`
+    pub name: String,
+
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+
+    /// Allowed values, for enum fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+
+    /// Value given to new tasks that don't set the field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+impl FieldConfig {
+    /// Check the declaration itself: a usable name, values for enums and a
+    /// default of the right type
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.contains(['=', ',', ':', ' ']) {
+            return Err(format!("invalid name '{}'", self.name));
+        }
+        if BUILTIN_FIELDS.contains(&self.name.as_str()) {
+            return Err(format!("'{}' is a built-in field", self.name));
+        }
+        if self.field_type == FieldType::Enum && self.values.is_empty() {
+            return Err(format!("enum field '{}' needs values", self.name));
+        }
+        if let Some(default) = &self.default {
+            self.check(default)
+                .map_err(|e| format!("default for '{}': {}", self.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Convert a command-line value to the value stored in frontmatter.
+    /// Task references must already be resolved to a full ID.
+    pub fn parse(&self, input: &str, today: NaiveDate) -> Result<Value, String> {
+        let value = match self.field_type {
+            FieldType::String | FieldType::Enum | FieldType::TaskRef => {
+                Value::String(input.to_string())
+            }
+            FieldType::Int => input
+                .trim()
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("expected an integer, got '{}'", input))?,
+            FieldType::Date => Value::String(
+                dates::parse_date(input, today)?
+                    .format("%Y-%m-%d")
+                    .to_string(),
+            ),
+        };
+        self.check(&value)?;
+        Ok(value)
+    }
+
+    /// Check a stored value against the declared type
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let ok = match (self.field_type, value) {
+            (FieldType::String | FieldType::TaskRef, Value::String(_)) => true,
+            (FieldType::Int, Value::Number(n)) => n.is_i64(),
+            (FieldType::Enum, Value::String(s)) => {
+                if !self.values.contains(s) {
+                    return Err(format!("'{}' is not one of: {}", s, self.values.join(", ")));
+                }
+                true
+            }
+            (FieldType::Date, Value::String(s)) => NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok(),
+            _ => false,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {}, got {}",
+                self.field_type,
+                describe(value)
+            ))
+        }
+    }
+
+    /// Order two stored values: integers numerically, enums in declared
+    /// order, everything else as text
+    pub fn compare(&self, a: &Value, b: &Value) -> Ordering {
+        match self.field_type {
+            FieldType::Int => a.as_i64().cmp(&b.as_i64()),
+            FieldType::Enum => {
+                let position = |value: &Value| {
+                    value
+                        .as_str()
+                        .and_then(|s| self.values.iter().position(|v| v == s))
+                };
+                position(a).cmp(&position(b))
+            }
+            _ => a.as_str().cmp(&b.as_str()),
+        }
+    }
+}
+
+/// A stored value as shown in a list column
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => String::new(),
+        _ => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// Any frontmatter value on one line, for showing how it changed
+pub fn format_value(value: Option<&Value>) -> String {
+    match value {
+        None => "(not set)".dimmed().to_string(),
+        Some(Value::Sequence(items)) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(display_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Some(value) => display_value(value),
+    }
+}
+
+/// A short description of a value's kind, for error messages
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "nothing".to_string(),
+        Value::Bool(b) => format!("boolean {}", b),
+        Value::Number(n) => format!("number {}", n),
+        Value::String(s) => format!("'{}'", s),
+        Value::Sequence(_) => "a list".to_string(),
+        Value::Mapping(_) => "a mapping".to_string(),
+        Value::Tagged(_) => "a tagged value".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(yaml: &str) -> FieldConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_check_values() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+
+        let estimate = field("name: estimate\ntype: int\ndefault: 1");
+        assert!(estimate.validate().is_ok());
+        assert_eq!(estimate.parse("3", today), Ok(Value::from(3)));
+        assert!(estimate.parse("three", today).is_err());
+        assert!(estimate.check(&Value::from("3")).is_err());
+
+        let component = field("name: component\ntype: enum\nvalues: [auth, ui]");
+        assert_eq!(component.parse("ui", today), Ok(Value::from("ui")));
+        assert!(component.parse("billing", today).is_err());
+        assert_eq!(
+            component.compare(&Value::from("ui"), &Value::from("auth")),
+            Ordering::Greater
+        );
+
+        let review = field("name: review\ntype: date");
+        assert_eq!(review.parse("+1w", today), Ok(Value::from("2025-01-22")));
+        assert!(review.check(&Value::from("next week")).is_err());
+
+        assert!(field("name: kind\ntype: enum").validate().is_err());
+        assert!(field("name: title\ntype: string").validate().is_err());
+        assert!(field("name: size\ntype: int\ndefault: big")
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Git integration: automatic commits, the task merge driver, file history,
+//! reading tasks at past revisions and the task trailers used by the commit
+//! hooks.
+//!
+//! Commits follow the convention in SPEC.md: `yatl({command}): {title}`.
+//! Only the task files a command touched are committed, so anything else the
+//! user has staged is left alone.
+
+use crate::id::TaskId;
+use chrono::{DateTime, Utc};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// For `run` calls that take no path arguments
+const NO_PATHS: &[&str] = &[];
+
+/// Name of the merge driver in git config and .gitattributes
+pub const MERGE_DRIVER: &str = "yatl";
+
+/// Whether `dir` is inside a git work tree (false if git isn't installed)
+pub fn is_work_tree(dir: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .is_ok_and(|output| output.status.success() && output.stdout.starts_with(b"true"))
+}
+
+/// Build a commit message from the command name and the tasks it touched
+/// (id, title), the main task first
+pub fn commit_message(command: Option<&str>, tasks: &[(String, String)]) -> String {
+    let prefix = match command {
+        Some(command) => format!("yatl({})", command),
+        None => "yatl".to_string(),
+    };
+    let Some((_, title)) = tasks.first() else {
+        return prefix;
+    };
+    if tasks.len() == 1 {
+        return format!("{}: {}", prefix, title);
+    }
+
+    let mut message = format!("{}: {} (+{} more)\n", prefix, title, tasks.len() - 1);
+    for (id, title) in tasks {
+        message.push_str(&format!("\n- {} {}", id, title));
+    }
+    message
+}
+
+/// Stage exactly `paths` (including deletions) and commit them with
+/// `message`, leaving anything else in the index untouched
+pub fn commit_paths(dir: &Path, paths: &[PathBuf], message: &str) -> Result<(), String> {
+    // Files that no longer exist can only be staged if git knows them
+    let paths: Vec<&PathBuf> = paths
+        .iter()
+        .filter(|path| path.exists() || is_tracked(dir, path))
+        .collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    run(dir, &["add", "-A", "--"], &paths)?;
+    run(
+        dir,
+        &["commit", "-q", "-m", message, "--only", "--"],
+        &paths,
+    )?;
+    Ok(())
+}
+
+/// Register `yatl merge-driver` as the `yatl` merge driver in the
+/// repository's git config
+pub fn register_merge_driver(dir: &Path) -> Result<(), String> {
+    let section = format!("merge.{}", MERGE_DRIVER);
+    run(
+        dir,
+        &["config", &format!("{}.name", section), "yatl task merge"],
+        NO_PATHS,
+    )?;
+    run(
+        dir,
+        &[
+            "config",
+            &format!("{}.driver", section),
+            "yatl merge-driver %O %A %B",
+        ],
+        NO_PATHS,
+    )?;
+    Ok(())
+}
+
+/// Line-based three-way merge with `git merge-file`.
+/// Returns the merged text and whether it contains conflict markers.
+pub fn merge_file(base: &str, ours: &str, theirs: &str) -> Result<(String, bool), String> {
+    let dir = std::env::temp_dir();
+    let name = |side: &str| dir.join(format!("yatl-merge-{}-{}", std::process::id(), side));
+    let files = [name("ours"), name("base"), name("theirs")];
+    let result = (|| {
+        for (file, content) in files.iter().zip([ours, base, theirs]) {
+            fs::write(file, content).map_err(|e| e.to_string())?;
+        }
+        Command::new("git")
+            .args([
+                "merge-file",
+                "-p",
+                "-L",
+                "ours",
+                "-L",
+                "base",
+                "-L",
+                "theirs",
+            ])
+            .args(&files)
+            .output()
+            .map_err(|e| format!("failed to run git: {}", e))
+    })();
+    for file in &files {
+        let _ = fs::remove_file(file);
+    }
+
+    // Exit status is the number of conflicts, or negative on error
+    let output = result?;
+    match output.status.code() {
+        Some(code @ 0..=127) => Ok((
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            code > 0,
+        )),
+        _ => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// One committed version of a file
+#[derive(Debug)]
+pub struct Revision {
+    /// Abbreviated commit hash
+    pub commit: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    /// Path of the file in this commit, relative to the repository root
+    pub path: String,
+    /// File content in this commit (None if the commit deleted it)
+    pub content: Option<String>,
+}
+
+/// Commit message trailers that reference tasks. `Closes` also closes the
+/// task when the commit is made.
+pub const TASK_TRAILERS: &[&str] = &["Task", "Refs", "Closes"];
+
+/// Parse a `Task: <id>` style trailer line (several IDs may be separated by
+/// commas or spaces). Returns the canonical trailer name and the IDs, or
+/// None if the value isn't made of task IDs (e.g. `Refs: #123`).
+pub fn parse_trailer(line: &str) -> Option<(&'static str, Vec<&str>)> {
+    let (key, value) = line.split_once(':')?;
+    let trailer = TASK_TRAILERS
+        .iter()
+        .find(|trailer| trailer.eq_ignore_ascii_case(key.trim_end()))?;
+    let ids: Vec<&str> = value
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect();
+    let all_ids = ids.iter().all(|id| TaskId::is_valid_prefix(id));
+    (!ids.is_empty() && all_ids).then_some((*trailer, ids))
+}
+
+/// Indices of the lines (as split by `split_inclusive('\n')`) in a commit
+/// message's trailer block. Like git, that's the last paragraph, if it
+/// isn't the only one and every line in it is a `Key: value` trailer or a
+/// continuation of one. Comment lines and anything below a scissors line
+/// are ignored, as git strips them.
+pub fn trailer_block(message: &str) -> Range<usize> {
+    let lines: Vec<&str> = message.split_inclusive('\n').collect();
+    let end = lines
+        .iter()
+        .position(|line| line.starts_with("# ") && line.contains(" >8 "))
+        .unwrap_or(lines.len());
+    let content: Vec<usize> = (0..end).filter(|&i| !lines[i].starts_with('#')).collect();
+    let blank = |i: &usize| lines[*i].trim().is_empty();
+
+    // Last paragraph of non-comment lines
+    let Some(last) = content.iter().rposition(|i| !blank(i)) else {
+        return 0..0;
+    };
+    let first = content[..last]
+        .iter()
+        .rposition(blank)
+        .map_or(0, |blank_line| blank_line + 1);
+    let has_body_before = content[..first].iter().any(|i| !blank(i));
+    let all_trailers = content[first..=last].iter().all(|&i| {
+        let line = lines[i];
+        line.starts_with([' ', '\t'])
+            || line.split_once(':').is_some_and(|(key, _)| {
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+    });
+    if has_body_before && all_trailers {
+        content[first]..content[last] + 1
+    } else {
+        0..0
+    }
+}
+
+/// Every task reference in a commit message's trailer block, as
+/// (trailer, id) pairs
+pub fn task_refs(message: &str) -> Vec<(&'static str, String)> {
+    let block = trailer_block(message);
+    message
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|(i, line)| block.contains(i) && !line.starts_with('#'))
+        .filter_map(|(_, line)| parse_trailer(line.trim_end()))
+        .flat_map(|(trailer, ids)| ids.into_iter().map(move |id| (trailer, id.to_string())))
+        .collect()
+}
+
+/// The most recent commit: (abbreviated hash, subject, full message)
+pub fn head_commit(dir: &Path) -> Result<(String, String, String), String> {
+    let output = run(dir, &["log", "-1", "--format=%h%x1f%s%x1f%B"], NO_PATHS)?;
+    let mut parts = output.splitn(3, '\x1f');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(hash), Some(subject), Some(message)) => {
+            Ok((hash.to_string(), subject.to_string(), message.to_string()))
+        }
+        _ => Err("unexpected output from git log".to_string()),
+    }
+}
+
+/// The repository's hooks directory
+pub fn hooks_dir(dir: &Path) -> Result<PathBuf, String> {
+    let path = run(dir, &["rev-parse", "--git-path", "hooks"], NO_PATHS)?;
+    Ok(dir.join(path.trim()))
+}
+
+/// Path of `dir` relative to the top of its work tree ("" at the top)
+pub fn work_tree_prefix(dir: &Path) -> Result<String, String> {
+    Ok(run(dir, &["rev-parse", "--show-prefix"], NO_PATHS)?
+        .trim()
+        .to_string())
+}
+
+/// Where the last commit has a file named `file_name` somewhere below `dir`
+/// (a task may have moved to another status directory since)
+pub fn committed_path(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let files = run(dir, &["ls-tree", "-r", "--name-only", "HEAD"], NO_PATHS).ok()?;
+    files
+        .lines()
+        .find(|line| {
+            Path::new(line)
+                .file_name()
+                .is_some_and(|name| name == file_name)
+        })
+        .map(|line| dir.join(line))
+}
+
+/// Every commit that changed `path`, following renames, oldest first
+pub fn file_history(dir: &Path, path: &Path) -> Result<Vec<Revision>, String> {
+    let log = run(
+        dir,
+        &[
+            "log",
+            "--follow",
+            "--format=%x1e%h%x1f%an%x1f%aI",
+            "--name-status",
+            "--",
+        ],
+        &[path],
+    )?;
+
+    let mut revisions = vec![];
+    for record in log.split('\x1e').skip(1) {
+        let mut lines = record.lines();
+        let header: Vec<&str> = lines.next().unwrap_or_default().split('\x1f').collect();
+        let [commit, author, date] = header[..] else {
+            continue;
+        };
+        // "M\tpath", "A\tpath", "D\tpath" or "R095\told\tnew"
+        let Some(change) = lines.find(|line| !line.is_empty()) else {
+            continue;
+        };
+        let fields: Vec<&str> = change.split('\t').collect();
+        let file = fields.last().copied().unwrap_or_default().to_string();
+        let content = if change.starts_with('D') {
+            None
+        } else {
+            Some(run(
+                dir,
+                &["show", &format!("{}:{}", commit, file)],
+                NO_PATHS,
+            )?)
+        };
+
+        revisions.push(Revision {
+            commit: commit.to_string(),
+            author: author.to_string(),
+            date: DateTime::parse_from_rfc3339(date)
+                .map_err(|e| format!("unexpected date from git log: {}", e))?
+                .with_timezone(&Utc),
+            path: file,
+            content,
+        });
+    }
+    revisions.reverse();
+    Ok(revisions)
+}
+
+/// Fail unless `rev` names a commit
+fn verify_commit(dir: &Path, rev: &str) -> Result<(), String> {
+    run(
+        dir,
+        &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)],
+        NO_PATHS,
+    )
+    .map(|_| ())
+    .map_err(|_| format!("unknown revision '{}'", rev))
+}
+
+/// Whether `dir` existed at revision `rev`
+pub fn dir_exists_at(dir: &Path, rev: &str) -> Result<bool, String> {
+    verify_commit(dir, rev)?;
+    let prefix = work_tree_prefix(dir)?;
+    let tree = format!("{}:{}", rev, prefix.trim_end_matches('/'));
+    Ok(run(dir, &["cat-file", "-e", &tree], NO_PATHS).is_ok())
+}
+
+/// Every file below `dir` as it was at revision `rev`: paths relative to
+/// `dir` and their contents
+pub fn read_tree(dir: &Path, rev: &str) -> Result<Vec<(String, String)>, String> {
+    verify_commit(dir, rev)?;
+
+    let prefix = work_tree_prefix(dir)?;
+    let tree = format!("{}:{}", rev, prefix.trim_end_matches('/'));
+    let listing = run(dir, &["ls-tree", "-r", "-z", "--full-tree", &tree], NO_PATHS)
+        .map_err(|_| format!("{} does not exist at revision '{}'", dir.display(), rev))?;
+
+    // "<mode> blob <hash>\t<path>"
+    let mut blobs = vec![];
+    for entry in listing.split('\0').filter(|entry| !entry.is_empty()) {
+        let Some((meta, path)) = entry.split_once('\t') else {
+            continue;
+        };
+        if let [_, "blob", hash] = meta.split(' ').collect::<Vec<_>>()[..] {
+            blobs.push((path.to_string(), hash.to_string()));
+        }
+    }
+    if blobs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Read every blob with one `git cat-file --batch` rather than a process
+    // per file
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    let input: String = blobs.iter().map(|(_, hash)| format!("{}\n", hash)).collect();
+    let mut stdin = child.stdin.take().ok_or("failed to write to git")?;
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    // "<hash> blob <size>\n<content>\n" per object
+    let mut files = vec![];
+    let mut rest = &output.stdout[..];
+    for (path, _) in blobs {
+        let header_end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or("unexpected output from git cat-file")?;
+        let header = String::from_utf8_lossy(&rest[..header_end]);
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or("unexpected output from git cat-file")?;
+        let start = header_end + 1;
+        let content = rest
+            .get(start..start + size)
+            .ok_or("unexpected output from git cat-file")?;
+        files.push((path, String::from_utf8_lossy(content).into_owned()));
+        rest = rest.get(start + size + 1..).unwrap_or_default();
+    }
+    Ok(files)
+}
+
+/// Whether git tracks `path` in the index
+fn is_tracked(dir: &Path, path: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(path)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Run git in `dir` with `args` followed by `paths`, returning its output
+fn run<P: AsRef<OsStr>>(dir: &Path, args: &[&str], paths: &[P]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .args(paths)
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_refs() {
+        let message = "Fix login\n\n\
+                       Longer description: not a trailer\n\
+                       Refs: the design doc\n\
+                       \n\
+                       Task: a1b2\n\
+                       # Task: commented out\n\
+                       refs: c3d4, e5f6\n\
+                       Refs: #123\n\
+                       Closes: a1b2\n\
+                       # Please enter the commit message\n";
+        assert_eq!(
+            task_refs(message),
+            vec![
+                ("Task", "a1b2".to_string()),
+                ("Refs", "c3d4".to_string()),
+                ("Refs", "e5f6".to_string()),
+                ("Closes", "a1b2".to_string()),
+            ]
+        );
+        assert_eq!(parse_trailer("Task:"), None);
+        assert_eq!(parse_trailer("Refs: the design doc"), None);
+
+        // The last paragraph only counts if it's all trailers
+        assert!(task_refs("Fix login\n\nTask: a1b2\nSee the docs\n").is_empty());
+        assert!(task_refs("Task: a1b2\n").is_empty());
+    }
+
+    #[test]
+    fn test_commit_message() {
+        let fix = ("a1b2c3d4".to_string(), "Fix login bug".to_string());
+        let tests = ("e5f6g7h8".to_string(), "Write tests".to_string());
+
+        assert_eq!(
+            commit_message(Some("close"), std::slice::from_ref(&fix)),
+            "yatl(close): Fix login bug"
+        );
+        assert_eq!(
+            commit_message(None, std::slice::from_ref(&fix)),
+            "yatl: Fix login bug"
+        );
+        assert_eq!(
+            commit_message(Some("close"), &[fix, tests]),
+            "yatl(close): Fix login bug (+1 more)\n\n\
+             - a1b2c3d4 Fix login bug\n\
+             - e5f6g7h8 Write tests"
+        );
+    }
+}
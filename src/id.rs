@@ -48,6 +48,7 @@ impl TaskId {
     }
 
     /// Get the shortest prefix that uniquely identifies this ID among others
+    #[allow(dead_code)]
     pub fn shortest_unique_prefix<'a>(&'a self, others: &[&TaskId]) -> &'a str {
         for len in 1..=self.0.len() {
             let prefix = &self.0[..len];
@@ -65,6 +66,14 @@ impl TaskId {
         &self.0
     }
 
+    /// Whether `s` could be a task ID or a prefix of one
+    pub fn is_valid_prefix(s: &str) -> bool {
+        !s.is_empty()
+            && s.len() <= 8
+            && s.bytes()
+                .all(|b| CROCKFORD.contains(&b.to_ascii_lowercase()))
+    }
+
     /// Check if this ID matches a prefix (case-insensitive)
     pub fn matches_prefix(&self, prefix: &str) -> bool {
         self.0.to_lowercase().starts_with(&prefix.to_lowercase())
@@ -122,6 +131,11 @@ mod tests {
         assert!(id.matches_prefix("a1"));
         assert!(id.matches_prefix("a"));
         assert!(!id.matches_prefix("b2"));
+
+        assert!(TaskId::is_valid_prefix("A1B2"));
+        assert!(!TaskId::is_valid_prefix("#123"));
+        assert!(!TaskId::is_valid_prefix("design"));
+        assert!(!TaskId::is_valid_prefix("a1b2c3d4e"));
     }
 
     #[test]
@@ -0,0 +1,244 @@
+//! Persistent on-disk cache of parsed task files.
+//!
+//! Parsing every markdown file on every command gets slow once a project has
+//! thousands of closed tasks. The index stores the parsed frontmatter, body
+//! and log of each task file keyed by its path relative to `.tasks/`, along
+//! with the file's modification time and size. Entries are reused while the
+//! file is unchanged and refreshed incrementally when it isn't. Each status
+//! directory's modification time and archive subdirectories are recorded
+//! too, so listing a directory whose entries haven't changed needs no
+//! `read_dir`.
+//!
+//! A file changed in the same clock tick as the index was written could
+//! keep its recorded stamp, so, as git does for its own index, entries whose
+//! modification time is not older than the index file are treated as dirty
+//! and read again.
+//!
+//! The index is purely a cache: if it is missing, unreadable or written by a
+//! different format version, it is discarded and rebuilt by a full rescan.
+
+use crate::log;
+use crate::task::{Task, TaskFrontmatter};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, Metadata};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Index file name (inside .tasks)
+pub const INDEX_FILE: &str = ".index.json";
+
+/// Bump when the cached representation changes to force a rebuild
+const INDEX_VERSION: u32 = 6;
+
+/// Modification time and size of a file, used to detect changes cheaply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    mtime_ns: u64,
+    size: u64,
+}
+
+impl FileStamp {
+    /// Build a stamp from file metadata
+    pub fn from_metadata(meta: &Metadata) -> Self {
+        let mtime_ns = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        FileStamp {
+            mtime_ns,
+            size: meta.len(),
+        }
+    }
+
+    /// Stamp the file (or directory) at `path`
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        fs::metadata(path).map(|meta| Self::from_metadata(&meta))
+    }
+}
+
+/// A cached task file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    stamp: FileStamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    task: Option<CachedTask>,
+    /// Parse error, for files that could not be loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Modified no earlier than the index was written, so not trusted
+    #[serde(skip)]
+    racy: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedTask {
+    frontmatter: TaskFrontmatter,
+    body: String,
+    log: String,
+}
+
+/// A directory as it was last fully listed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirEntry {
+    stamp: FileStamp,
+    /// Archive subdirectory names
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    subdirs: Vec<String>,
+    #[serde(skip)]
+    racy: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    /// Directory (relative to .tasks) -> state when it was last fully listed
+    dirs: BTreeMap<String, DirEntry>,
+    /// Task file (relative to .tasks) -> cached contents
+    entries: BTreeMap<String, IndexEntry>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Index {
+    /// Load the index, or start an empty one if it is missing or stale
+    pub fn load(tasks_dir: &Path) -> Self {
+        let path = tasks_dir.join(INDEX_FILE);
+        let mut index = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Index>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_else(|| Index {
+                version: INDEX_VERSION,
+                ..Index::default()
+            });
+        if let Ok(written) = FileStamp::of(&path) {
+            index.mark_racy(written.mtime_ns);
+        }
+        index
+    }
+
+    /// Distrust entries that may have changed after they were recorded
+    /// without changing their stamp: those whose modification time is not
+    /// before the index was written
+    fn mark_racy(&mut self, written_ns: u64) {
+        for entry in self.entries.values_mut() {
+            entry.racy = entry.stamp.mtime_ns >= written_ns;
+        }
+        for dir in self.dirs.values_mut() {
+            dir.racy = dir.stamp.mtime_ns >= written_ns;
+        }
+    }
+
+    /// Serialize the index if anything changed since it was loaded
+    pub fn to_json_if_dirty(&self) -> Option<String> {
+        if !self.dirty {
+            return None;
+        }
+        serde_json::to_string(self).ok()
+    }
+
+    /// File and subdirectory names recorded for a directory, if the
+    /// directory is unchanged since it was last fully listed
+    pub fn dir_listing(&self, dir: &str, stamp: FileStamp) -> Option<(Vec<String>, Vec<String>)> {
+        let entry = self.dirs.get(dir).filter(|d| d.stamp == stamp && !d.racy)?;
+        let prefix = format!("{}/", dir);
+        let files = self
+            .entries
+            .keys()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter(|name| !name.contains('/'))
+            .map(|name| name.to_string())
+            .collect();
+        Some((files, entry.subdirs.clone()))
+    }
+
+    /// Record the full listing of a directory, dropping entries for files
+    /// (and subdirectories) that no longer exist
+    pub fn set_dir_listing(
+        &mut self,
+        dir: &str,
+        stamp: FileStamp,
+        files: &[String],
+        subdirs: &[String],
+    ) {
+        let prefix = format!("{}/", dir);
+        let before = self.entries.len();
+        self.entries
+            .retain(|key, _| match key.strip_prefix(&prefix) {
+                Some(name) => match name.split_once('/') {
+                    None => files.iter().any(|f| f == name),
+                    Some((subdir, _)) => subdirs.iter().any(|s| s == subdir),
+                },
+                None => true,
+            });
+
+        let unchanged = self
+            .dirs
+            .get(dir)
+            .is_some_and(|d| d.stamp == stamp && d.subdirs == subdirs && !d.racy);
+        if self.entries.len() != before || !unchanged {
+            self.dirs.insert(
+                dir.to_string(),
+                DirEntry {
+                    stamp,
+                    subdirs: subdirs.to_vec(),
+                    racy: false,
+                },
+            );
+            self.dirty = true;
+        }
+    }
+
+    /// Look up a cached file if it hasn't changed since it was cached.
+    /// Returns the parsed task, or the parse error for a broken file.
+    pub fn get(&self, key: &str, stamp: FileStamp) -> Option<Result<Task, String>> {
+        let entry = self
+            .entries
+            .get(key)
+            .filter(|e| e.stamp == stamp && !e.racy)?;
+        match (&entry.task, &entry.error) {
+            (Some(cached), _) => Some(Ok(Task {
+                frontmatter: cached.frontmatter.clone(),
+                body: cached.body.clone(),
+                log: log::parse(&cached.log),
+            })),
+            (None, Some(error)) => Some(Err(error.clone())),
+            (None, None) => None,
+        }
+    }
+
+    /// Cache a successfully parsed task
+    pub fn insert_task(&mut self, key: &str, stamp: FileStamp, task: &Task) {
+        self.entries.insert(
+            key.to_string(),
+            IndexEntry {
+                stamp,
+                task: Some(CachedTask {
+                    frontmatter: task.frontmatter.clone(),
+                    body: task.body.clone(),
+                    log: log::render(&task.log),
+                }),
+                error: None,
+                racy: false,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Cache a parse failure so the file is still reported on the next run
+    pub fn insert_error(&mut self, key: &str, stamp: FileStamp, error: &str) {
+        self.entries.insert(
+            key.to_string(),
+            IndexEntry {
+                stamp,
+                task: None,
+                error: Some(error.to_string()),
+                racy: false,
+            },
+        );
+        self.dirty = true;
+    }
+}
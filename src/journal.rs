@@ -0,0 +1,77 @@
+//! Operation journal backing `yatl undo` and `yatl redo`.
+//!
+//! Every committed transaction records the before and after content of each
+//! file it touched. Undoing an entry puts the "before" content back and moves
+//! the entry to the redo stack; redoing does the reverse. Entries on the undo
+//! stack describe files currently in their "after" state, entries on the redo
+//! stack files currently in their "before" state. Running any other
+//! mutating command clears the redo stack. Only the most recent entries are
+//! kept.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Journal file name (inside .tasks)
+pub const JOURNAL_FILE: &str = ".journal";
+
+/// Number of undoable operations kept
+const JOURNAL_LIMIT: usize = 100;
+
+/// State of one file before and after an operation (None = absent)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path relative to .tasks
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// One recorded operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Command that made the change (e.g. "close")
+    pub command: String,
+    pub files: Vec<FileChange>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    /// Operations that can be undone, oldest first
+    #[serde(default)]
+    pub done: Vec<JournalEntry>,
+    /// Undone operations that can be redone, oldest first
+    #[serde(default)]
+    pub undone: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Load the journal, starting empty if there is none yet. A journal
+    /// that can't be read or parsed is an error, so it isn't overwritten
+    /// and the undo history lost.
+    pub fn load(tasks_dir: &Path) -> Result<Self, String> {
+        let path = tasks_dir.join(JOURNAL_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Journal::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Serialize the journal for writing
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Record a new operation, discarding anything that could be redone
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.undone.clear();
+        self.done.push(entry);
+        if self.done.len() > JOURNAL_LIMIT {
+            self.done.drain(..self.done.len() - JOURNAL_LIMIT);
+        }
+    }
+}
@@ -1,13 +1,27 @@
+pub mod backend;
+pub mod checklist;
 pub mod commands;
 pub mod config;
+pub mod dates;
+pub mod fields;
 pub mod id;
+pub mod git;
+pub mod index;
+pub mod journal;
+pub mod log;
+pub mod merge;
+pub mod migrate;
 pub mod prefix;
 pub mod store;
 pub mod task;
 pub mod term;
+#[cfg(test)]
+mod test_util;
+pub mod transaction;
 
 pub use config::Config;
 pub use id::TaskId;
 pub use prefix::PrefixResolver;
-pub use store::Store;
+pub use store::{Store, StoreOptions};
 pub use task::{Priority, Status, Task};
+pub use transaction::Transaction;
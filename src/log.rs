@@ -0,0 +1,414 @@
+//! A task's log section, parsed into entries.
+//!
+//! ```text
+//! ---
+//! # Log: 2025-01-15T10:00:00Z Alice
+//!
+//! Moved from open to review.
+//! ```
+//!
+//! Commands record each entry with an explicit kind and metadata. Most
+//! entries are still stored as just a timestamp, author and message: the
+//! message a command writes is enough to read its kind back, which is also
+//! how entries from older versions of yatl get one. When the message alone
+//! would be read as something else (a comment saying "Closed: later", say),
+//! the kind and metadata are written on a line after the header:
+//!
+//! ```text
+//! ---
+//! # Log: 2025-01-15T10:00:00Z Alice
+//! <!-- yatl {"kind":"comment"} -->
+//!
+//! Closed: later
+//! ```
+//!
+//! Entries that don't look like one yatl would write are kept verbatim.
+
+use crate::dates;
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a log entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogKind {
+    Created,
+    /// Free text from `yatl log`, or anything not written by a command
+    Comment,
+    /// Metadata: `to`, and `from` or `reason` when known
+    StatusChange,
+    /// Metadata: `field`, and `value` unless it was cleared
+    FieldChange,
+    /// Metadata: `task`
+    BlockerAdded,
+    BlockerRemoved,
+    SubtaskAdded,
+    SubtaskRemoved,
+    ParentSet,
+    ParentRemoved,
+    /// A checklist item in the body was ticked off. Metadata: `item`
+    ItemChecked,
+    ItemUnchecked,
+    /// Metadata: `spent`, e.g. `1h 30m`
+    TimeSpent,
+    /// Metadata: `commit`
+    Commit,
+    /// Undone or redone by `yatl undo`/`yatl redo`. Metadata: `command`
+    Undo,
+    /// Repaired by `yatl resolve`
+    Repair,
+}
+
+/// One entry of a task's log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub author: String,
+    pub kind: LogKind,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    pub body: String,
+
+    /// The entry as found in the file, when it isn't what `render` gives
+    #[serde(skip)]
+    raw: Option<String>,
+}
+
+/// Kind and metadata as written after an entry's header
+#[derive(Serialize, Deserialize)]
+struct Marker {
+    kind: LogKind,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+}
+
+const MARKER_START: &str = "<!-- yatl ";
+const MARKER_END: &str = " -->";
+
+impl LogEntry {
+    /// An entry of the given kind, timestamped to the second like the file
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        author: impl Into<String>,
+        kind: LogKind,
+        metadata: &[(&str, &str)],
+        body: impl Into<String>,
+    ) -> Self {
+        LogEntry {
+            timestamp: timestamp.with_nanosecond(0).unwrap_or(timestamp),
+            author: author.into(),
+            kind,
+            metadata: metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            body: body.into(),
+            raw: None,
+        }
+    }
+
+    /// The first line of the message
+    pub fn summary(&self) -> &str {
+        self.body.lines().next().unwrap_or_default()
+    }
+
+    /// The entry in the file format, starting at its `---` separator
+    pub fn render(&self) -> String {
+        if let Some(raw) = &self.raw {
+            return raw.clone();
+        }
+        let mut header = format!(
+            "# Log: {} {}",
+            self.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+            self.author
+        );
+        if classify(&self.body) != (self.kind, self.metadata.clone()) {
+            let marker = Marker {
+                kind: self.kind,
+                metadata: self.metadata.clone(),
+            };
+            let json = serde_json::to_string(&marker).unwrap_or_default();
+            header.push_str(&format!("\n{}{}{}", MARKER_START, json, MARKER_END));
+        }
+        format!("---\n{}\n\n{}\n", header, self.body)
+    }
+
+    /// Parse one entry, `---\n# Log: ...` through the end of its message.
+    /// Returns None if the header has no usable timestamp.
+    fn parse(text: &str) -> Option<Self> {
+        let rest = text.strip_prefix("---\n# Log:")?;
+        let (header, mut message) = rest.split_once('\n').unwrap_or((rest, ""));
+        let header = header.trim();
+        let (timestamp, author) = header.split_once(' ').unwrap_or((header, ""));
+        let timestamp = DateTime::parse_from_rfc3339(timestamp).ok()?;
+
+        let marker = message.split_once('\n').and_then(|(line, rest)| {
+            let json = line.strip_prefix(MARKER_START)?.strip_suffix(MARKER_END)?;
+            let marker: Marker = serde_json::from_str(json).ok()?;
+            message = rest;
+            Some(marker)
+        });
+        let body = message.trim();
+        let (kind, metadata) = match marker {
+            Some(marker) => (marker.kind, marker.metadata),
+            // Legacy or plain entries: read the kind from the message
+            None => classify(body),
+        };
+
+        let mut entry = LogEntry {
+            timestamp: timestamp.with_timezone(&Utc),
+            author: author.trim().to_string(),
+            kind,
+            metadata,
+            body: body.to_string(),
+            raw: None,
+        };
+        if entry.render() != text {
+            entry.raw = Some(text.to_string());
+        }
+        Some(entry)
+    }
+}
+
+/// Parse a log section, as found after a task's body
+pub fn parse(log: &str) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = vec![];
+    if log.is_empty() {
+        return entries;
+    }
+
+    for (i, chunk) in log.split("\n---\n# Log:").enumerate() {
+        let text = if i == 0 {
+            chunk.to_string()
+        } else {
+            format!("---\n# Log:{}", chunk)
+        };
+        match (LogEntry::parse(&text), entries.last_mut()) {
+            (Some(entry), _) => entries.push(entry),
+            // A header yatl can't read stays part of the entry before it
+            (None, Some(previous)) => {
+                let raw = format!("{}\n{}", previous.render(), text);
+                previous.body = format!("{}\n\n{}", previous.body, text.trim());
+                previous.raw = Some(raw);
+            }
+            (None, None) => entries.push(LogEntry {
+                timestamp: DateTime::UNIX_EPOCH,
+                author: String::new(),
+                kind: LogKind::Comment,
+                metadata: BTreeMap::new(),
+                body: text.trim().to_string(),
+                raw: Some(text),
+            }),
+        }
+    }
+    entries
+}
+
+/// The log section for a task file
+pub fn render(entries: &[LogEntry]) -> String {
+    entries
+        .iter()
+        .map(LogEntry::render)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The kind and metadata of an entry stored without a marker, read from
+/// the message the command that wrote it uses
+fn classify(body: &str) -> (LogKind, BTreeMap<String, String>) {
+    let first = body.lines().next().unwrap_or_default();
+    let meta = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    };
+    let task_ref = |prefix: &str| {
+        first
+            .strip_prefix(prefix)
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && !id.contains(' '))
+    };
+
+    let status = |to: &str| (LogKind::StatusChange, meta(&[("to", to)]));
+    match first {
+        "Created task." => return (LogKind::Created, BTreeMap::new()),
+        "Started working." => return status("in-progress"),
+        "Stopped working." | "Reopened." => return status("open"),
+        "Closed." => return status("closed"),
+        "Unassigned." => return (LogKind::FieldChange, meta(&[("field", "assignee")])),
+        _ => {}
+    }
+
+    if let Some(reason) = body.strip_prefix("Closed: ") {
+        return (
+            LogKind::StatusChange,
+            meta(&[("to", "closed"), ("reason", reason.trim())]),
+        );
+    }
+    if let Some((from, to)) = first
+        .strip_prefix("Moved from ")
+        .and_then(|rest| rest.strip_suffix('.'))
+        .and_then(|rest| rest.split_once(" to "))
+    {
+        return (LogKind::StatusChange, meta(&[("from", from), ("to", to)]));
+    }
+    if let Some((to, _)) = first
+        .strip_prefix("Doctor: moved to ")
+        .and_then(|rest| rest.split_once(' '))
+    {
+        return status(to);
+    }
+    if let Some(assignee) = first
+        .strip_prefix("Assigned to ")
+        .and_then(|rest| rest.strip_suffix('.'))
+    {
+        return (
+            LogKind::FieldChange,
+            meta(&[("field", "assignee"), ("value", assignee)]),
+        );
+    }
+
+    let refs = [
+        ("Added blocker: ", LogKind::BlockerAdded),
+        ("Removed blocker: ", LogKind::BlockerRemoved),
+        ("Added subtask: ", LogKind::SubtaskAdded),
+        ("Removed subtask: ", LogKind::SubtaskRemoved),
+        ("Parent set to: ", LogKind::ParentSet),
+        ("Removed parent: ", LogKind::ParentRemoved),
+    ];
+    for (prefix, kind) in refs {
+        if let Some(id) = task_ref(prefix) {
+            return (kind, meta(&[("task", id)]));
+        }
+    }
+
+    if let Some(item) = first.strip_prefix("Checked: ") {
+        return (LogKind::ItemChecked, meta(&[("item", item)]));
+    }
+    if let Some(item) = first.strip_prefix("Unchecked: ") {
+        return (LogKind::ItemUnchecked, meta(&[("item", item)]));
+    }
+    if let Some(spent) = first.strip_prefix("Spent ").and_then(|rest| {
+        rest.split_once(':')
+            .map(|(spent, _)| spent)
+            .or_else(|| rest.strip_suffix('.'))
+    }) {
+        if dates::parse_duration(&spent.replace(' ', "")).is_ok() {
+            return (LogKind::TimeSpent, meta(&[("spent", spent)]));
+        }
+    }
+    if let Some((hash, _)) = first
+        .strip_prefix("Commit ")
+        .and_then(|rest| rest.split_once(": "))
+        .filter(|(hash, _)| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return (LogKind::Commit, meta(&[("commit", hash)]));
+    }
+    if let Some((command, _)) = first
+        .strip_prefix("Undid '")
+        .or_else(|| first.strip_prefix("Redid '"))
+        .and_then(|rest| rest.rsplit_once("' from "))
+    {
+        return (LogKind::Undo, meta(&[("command", command)]));
+    }
+    if first.starts_with("Resolved merge damage (") {
+        return (LogKind::Repair, BTreeMap::new());
+    }
+
+    (LogKind::Comment, BTreeMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let log = "---\n# Log: 2025-01-15T10:00:00Z Alice Smith\n\nCreated task.\n\n\
+            ---\n# Log: 2025-01-15T11:00:00Z Alice Smith\n\nMoved from open to review.\n\n\
+            ---\n# Log: 2025-01-15T11:30:00.5+01:00 Bob\nNo blank line.\n\n\n\
+            ---\n# Log: yesterday Bob\n\nUnreadable header.\n\n\
+            ---\n# Log: 2025-01-16T09:00:00Z Bob\n\nFirst line\n\nSecond paragraph.\n";
+        let entries = parse(log);
+        assert_eq!(render(&entries), log);
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].kind, LogKind::Created);
+        assert_eq!(entries[0].author, "Alice Smith");
+        assert_eq!(entries[1].kind, LogKind::StatusChange);
+        assert_eq!(entries[1].metadata["from"], "open");
+        assert_eq!(entries[1].metadata["to"], "review");
+        assert_eq!(
+            entries[2].body,
+            "No blank line.\n\n---\n# Log: yesterday Bob\n\nUnreadable header."
+        );
+        assert_eq!(entries[3].summary(), "First line");
+        assert_eq!(entries[3].kind, LogKind::Comment);
+
+        let mut entries = entries;
+        entries.push(LogEntry::new(
+            Utc::now(),
+            "Bob",
+            LogKind::StatusChange,
+            &[("to", "closed"), ("reason", "done")],
+            "Closed: done",
+        ));
+        assert_eq!(parse(&render(&entries)), entries);
+        assert!(!render(&entries).contains("<!--"));
+    }
+
+    #[test]
+    fn test_explicit_kind_survives_round_trip() {
+        let now = Utc::now();
+        let comment = LogEntry::new(now, "Bob", LogKind::Comment, &[], "Closed: later maybe");
+        let moved = LogEntry::new(
+            now,
+            "Bob",
+            LogKind::Comment,
+            &[],
+            "Moved from Berlin to Paris.",
+        );
+        let checked = LogEntry::new(
+            now,
+            "Bob",
+            LogKind::ItemChecked,
+            &[("item", "Tests: all green")],
+            "Checked: Tests: all green",
+        );
+        let entries = vec![comment, moved, checked];
+
+        let text = render(&entries);
+        assert!(text.contains("<!-- yatl {\"kind\":\"comment\"} -->\n\nClosed: later maybe\n"));
+        let parsed = parse(&text);
+        assert_eq!(parsed, entries);
+        assert_eq!(parsed[1].kind, LogKind::Comment);
+        assert!(parsed[1].metadata.is_empty());
+    }
+
+    #[test]
+    fn test_classify_messages() {
+        let kind = |body: &str| classify(body);
+
+        let (k, m) = kind("Closed: duplicate of abcd");
+        assert_eq!(k, LogKind::StatusChange);
+        assert_eq!(m["reason"], "duplicate of abcd");
+        assert_eq!(
+            kind("Doctor: moved to blocked to match its blockers.").1["to"],
+            "blocked"
+        );
+        assert_eq!(kind("Added blocker: 0123abcd").0, LogKind::BlockerAdded);
+        assert_eq!(kind("Added blocker: a sentence").0, LogKind::Comment);
+        assert_eq!(kind("Assigned to alice.").1["value"], "alice");
+        assert_eq!(kind("Unchecked: Tests pass").1["item"], "Tests pass");
+        assert_eq!(kind("Spent 1h 30m: pairing").1["spent"], "1h 30m");
+        assert_eq!(kind("Spent the day on it.").0, LogKind::Comment);
+        assert_eq!(kind("Commit 1a2b3c4: Fix it").1["commit"], "1a2b3c4");
+        assert_eq!(
+            kind("Undid 'yatl close x' from 2025-01-15T10:00:00Z.").1["command"],
+            "yatl close x"
+        );
+        assert_eq!(kind("Looked into it.").0, LogKind::Comment);
+    }
+}
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::*;
 use std::path::{Path, PathBuf};
 use std::process;
@@ -6,14 +6,28 @@ use std::process;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 
+mod backend;
+mod checklist;
 mod commands;
 mod config;
+mod dates;
+mod fields;
 mod id;
+mod git;
+mod index;
+mod journal;
+mod log;
+mod merge;
+mod migrate;
 mod prefix;
 mod store;
 mod task;
 mod term;
+#[cfg(test)]
+mod test_util;
+mod transaction;
 
+use store::{StoreError, StoreOptions};
 use task::Priority;
 
 #[derive(Parser)]
@@ -21,6 +35,18 @@ use task::Priority;
 #[command(about = "Yet Another Task List - a minimal, file-based task tracker")]
 #[command(version)]
 struct Cli {
+    /// Treat unparseable task files as errors instead of skipping them
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Commit the task files changed by this command to git
+    #[arg(long, global = true)]
+    commit: bool,
+
+    /// Read tasks as they were at a git revision (read-only)
+    #[arg(long, global = true, value_name = "REV")]
+    at: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -28,7 +54,12 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize task tracking in the current directory
-    Init,
+    Init {
+        /// Merge task files with `yatl merge-driver` instead of the union
+        /// strategy (also works on an existing tracker)
+        #[arg(long)]
+        merge_driver: bool,
+    },
 
     /// Create a new task
     New {
@@ -47,6 +78,22 @@ enum Commands {
         #[arg(short, long)]
         blocked_by: Option<String>,
 
+        /// Create the task as a subtask of this task
+        #[arg(long)]
+        parent: Option<String>,
+
+        /// Due date (YYYY-MM-DD, today, tomorrow, +3d, +2w, +1m or a weekday)
+        #[arg(long)]
+        due: Option<String>,
+
+        /// Hide from ready/next until this date (same formats as --due)
+        #[arg(long)]
+        defer: Option<String>,
+
+        /// Set a custom field declared in config.yaml (repeatable)
+        #[arg(long = "set", value_name = "FIELD=VALUE")]
+        set: Vec<String>,
+
         /// Open task in editor after creation
         #[arg(short, long)]
         edit: bool,
@@ -59,6 +106,10 @@ enum Commands {
         #[arg(short, long)]
         all: bool,
 
+        /// Include archived tasks (implies --all)
+        #[arg(long)]
+        archived: bool,
+
         /// Long format output
         #[arg(short, long)]
         long: bool,
@@ -75,6 +126,26 @@ enum Commands {
         #[arg(short, long)]
         tag: Option<String>,
 
+        /// Only tasks assigned to you
+        #[arg(long, conflicts_with = "assignee")]
+        mine: bool,
+
+        /// Only tasks assigned to this person
+        #[arg(long)]
+        assignee: Option<String>,
+
+        /// Filter by a frontmatter field, e.g. component=auth (repeatable)
+        #[arg(long = "field", value_name = "KEY=VALUE")]
+        fields: Vec<String>,
+
+        /// Show custom fields as columns (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        columns: Vec<String>,
+
+        /// Sort by a custom field (prefix with - for descending)
+        #[arg(long, allow_hyphen_values = true)]
+        sort: Option<String>,
+
         /// Search in title and body
         #[arg(long)]
         search: Option<String>,
@@ -142,11 +213,36 @@ enum Commands {
         ids: Vec<String>,
     },
 
+    /// Move a task to another status, following the configured workflow
+    Move {
+        /// Task ID or prefix
+        id: String,
+
+        /// Target status (e.g. review, closed)
+        status: String,
+    },
+
     /// List tasks ready to work on (no blockers)
-    Ready,
+    Ready {
+        /// Only tasks assigned to you
+        #[arg(long, conflicts_with = "assignee")]
+        mine: bool,
+
+        /// Only tasks assigned to this person
+        #[arg(long)]
+        assignee: Option<String>,
+    },
 
     /// Suggest the highest priority ready task
-    Next,
+    Next {
+        /// Only tasks assigned to you
+        #[arg(long, conflicts_with = "assignee")]
+        mine: bool,
+
+        /// Only tasks assigned to this person
+        #[arg(long)]
+        assignee: Option<String>,
+    },
 
     /// Show recent activity across all tasks
     Activity {
@@ -157,10 +253,52 @@ enum Commands {
         /// Include closed/cancelled tasks
         #[arg(short, long)]
         all: bool,
+
+        /// Include archived tasks (implies --all)
+        #[arg(long)]
+        archived: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show dependency tree of active tasks
-    Tree,
+    Tree {
+        /// Show the parent/child hierarchy instead of dependencies
+        #[arg(long)]
+        children: bool,
+
+        /// Include archived subtasks in the hierarchy
+        #[arg(long, requires = "children")]
+        archived: bool,
+    },
+
+    /// Show active tasks that are overdue, due today or due this week
+    Agenda,
+
+    /// Total the time worked per task, tag or author
+    Timesheet {
+        /// First day to include (e.g. 2025-01-06 or -1w)
+        #[arg(long, allow_hyphen_values = true)]
+        from: Option<String>,
+
+        /// Last day to include
+        #[arg(long, allow_hyphen_values = true)]
+        to: Option<String>,
+
+        /// Group by task, tag or author
+        #[arg(long, default_value = "task")]
+        by: String,
+
+        /// Output as CSV
+        #[arg(long, conflicts_with = "json")]
+        csv: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Add a log entry to a task
     Log {
@@ -169,6 +307,10 @@ enum Commands {
 
         /// Log message
         message: Vec<String>,
+
+        /// Record time worked, e.g. 45m, 2h or 1h30m
+        #[arg(long)]
+        spent: Option<String>,
     },
 
     /// Mark a task as blocked by another
@@ -189,6 +331,48 @@ enum Commands {
         blocker: String,
     },
 
+    /// Assign a task to someone (yourself by default, `none` to unassign)
+    Assign {
+        /// Task ID or prefix
+        id: String,
+
+        /// Who to assign the task to
+        who: Option<String>,
+    },
+
+    /// Tick off a checklist item in a task's body
+    Check {
+        /// Task ID or prefix
+        id: String,
+
+        /// Item number (from 1) or text
+        item: String,
+    },
+
+    /// Clear a checked checklist item in a task's body
+    Uncheck {
+        /// Task ID or prefix
+        id: String,
+
+        /// Item number (from 1) or text
+        item: String,
+    },
+
+    /// Make a task a subtask of another
+    Adopt {
+        /// Task ID to make a subtask
+        child: String,
+
+        /// Task ID of the new parent
+        parent: String,
+    },
+
+    /// Detach a subtask from its parent
+    Orphan {
+        /// Subtask ID
+        id: String,
+    },
+
     /// Import tasks from a YAML file
     Import {
         /// Path to YAML file with task definitions
@@ -223,6 +407,20 @@ enum Commands {
         /// New body/description (use "-" to read from stdin)
         #[arg(long)]
         body: Option<String>,
+
+        /// Due date (YYYY-MM-DD, today, tomorrow, +3d, +2w, +1m, a weekday
+        /// or "none" to clear)
+        #[arg(long)]
+        due: Option<String>,
+
+        /// Hide from ready/next until this date (same formats as --due)
+        #[arg(long)]
+        defer: Option<String>,
+
+        /// Set a custom field declared in config.yaml, or clear it with
+        /// FIELD= (repeatable)
+        #[arg(long = "set", value_name = "FIELD=VALUE")]
+        set: Vec<String>,
     },
 
     /// Set or replace the task body/description
@@ -233,6 +431,108 @@ enum Commands {
         /// Description text (use "-" to read from stdin)
         description: Vec<String>,
     },
+
+    /// Check task files for integrity problems (exits non-zero if any are found)
+    Doctor {
+        /// Repair the problems that can be fixed safely
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Move old closed and cancelled tasks into dated archive directories
+    Archive {
+        /// Archive tasks not updated within this age (e.g. 90d, 12w)
+        #[arg(long, default_value = "90d")]
+        older_than: String,
+
+        /// Show what would be archived without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Upgrade task files to the current format version
+    Migrate {
+        /// Show the changes as diffs without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show a task's status and field changes over time (from git)
+    History {
+        /// Task ID or prefix
+        id: String,
+    },
+
+    /// Show tasks created, closed, reopened, reprioritised, newly blocked or
+    /// logged between two git revisions
+    Diff {
+        /// Revision to compare from
+        from: String,
+
+        /// Revision to compare to (default: the working tree)
+        to: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Git hooks linking commits to tasks via Task:/Refs:/Closes: trailers
+    Hooks {
+        #[command(subcommand)]
+        action: HooksCommand,
+    },
+
+    /// Repair a task file damaged by a git merge, choosing a side per field
+    Resolve {
+        /// Task ID or prefix
+        id: String,
+    },
+
+    /// Merge two versions of a task file (git merge driver: %O %A %B)
+    MergeDriver {
+        /// Common ancestor version (%O)
+        base: PathBuf,
+
+        /// Our version, overwritten with the result (%A)
+        ours: PathBuf,
+
+        /// Their version (%B)
+        theirs: PathBuf,
+    },
+
+    /// Undo the last change(s) made by yatl
+    Undo {
+        /// Number of operations to undo
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+
+    /// Redo change(s) reverted by undo
+    Redo {
+        /// Number of operations to redo
+        #[arg(default_value_t = 1)]
+        count: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommand {
+    /// Install the commit-msg and post-commit hooks in this repository
+    Install {
+        /// Replace existing hooks that weren't installed by yatl
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Expand task references in a commit message file (run by git)
+    CommitMsg {
+        /// Commit message file
+        file: PathBuf,
+    },
+
+    /// Log the last commit on the tasks it references (run by git)
+    PostCommit,
 }
 
 /// VCS directory markers that indicate a repository boundary
@@ -289,13 +589,42 @@ fn find_tasks_root(start: &Path) -> Result<PathBuf, String> {
     }
 }
 
+/// Run a command on each of several tasks, reporting each failure and
+/// failing overall if any of them failed
+fn for_each_id(
+    ids: &[String],
+    mut run: impl FnMut(&str) -> Result<(), StoreError>,
+) -> Result<(), StoreError> {
+    let mut failed = 0;
+    for id in ids {
+        if let Err(e) = run(id) {
+            eprintln!("{} {}: {}", "error:".red(), id, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(StoreError::SomeFailed {
+            failed,
+            total: ids.len(),
+        });
+    }
+    Ok(())
+}
+
 fn main() {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let command_name = matches.subcommand_name().map(str::to_string);
     let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
 
     // Init uses cwd directly; all other commands find .tasks by walking up
     let result = match cli.command {
-        Commands::Init => commands::init(&cwd),
+        Commands::Init { merge_driver } => commands::init(&cwd, merge_driver),
+
+        // Run by git from the repository root on temporary files
+        Commands::MergeDriver { base, ours, theirs } => {
+            commands::merge_driver(&base, &ours, &theirs)
+        }
 
         _ => {
             // Find .tasks root by walking up the directory tree
@@ -307,14 +636,25 @@ fn main() {
                 }
             };
 
+            let store_options = StoreOptions {
+                strict: cli.strict,
+                command: command_name,
+                commit: cli.commit,
+                at: cli.at,
+            };
+
             match cli.command {
-                Commands::Init => unreachable!(),
+                Commands::Init { .. } | Commands::MergeDriver { .. } => unreachable!(),
 
                 Commands::New {
                     title,
                     priority,
                     tags,
                     blocked_by,
+                    parent,
+                    due,
+                    defer,
+                    set,
                     edit,
                 } => {
                     let priority = priority.and_then(|p| p.parse::<Priority>().ok());
@@ -323,91 +663,159 @@ fn main() {
                     let blocked_by =
                         blocked_by.map(|b| b.split(',').map(|s| s.trim().to_string()).collect());
 
-                    commands::new(&root, &title, priority, tags, blocked_by, edit)
+                    commands::new(
+                        &root,
+                        &store_options,
+                        commands::NewOptions {
+                            title: &title,
+                            description: commands::read_stdin_description(),
+                            priority,
+                            tags,
+                            blocked_by,
+                            parent: parent.as_deref(),
+                            due: due.as_deref(),
+                            defer: defer.as_deref(),
+                            set: &set,
+                            edit: edit.then(commands::editor),
+                        },
+                    )
                 }
 
                 Commands::List {
                     all,
+                    archived,
                     long,
                     status,
                     priority,
                     tag,
+                    mine,
+                    assignee,
+                    fields,
+                    columns,
+                    sort,
                     search,
                     limit,
                     json,
                     body,
-                } => commands::list(&root, commands::ListOptions {
+                } => commands::list(&root, &store_options, commands::ListOptions {
                     all,
+                    archived,
                     long,
                     status_filter: status.as_deref(),
                     priority_filter: priority.as_deref(),
                     tag_filter: tag.as_deref(),
+                    mine,
+                    assignee_filter: assignee.as_deref(),
+                    field_filters: &fields,
+                    columns: &columns,
+                    sort: sort.as_deref(),
                     search_query: search.as_deref(),
                     limit,
                     json,
                     show_body: body,
                 }),
 
-                Commands::Show { id, json } => commands::show(&root, &id, json),
+                Commands::Show { id, json } => commands::show(&root, &store_options, &id, json),
 
-                Commands::Context { id } => commands::context(&root, &id),
+                Commands::Context { id } => commands::context(&root, &store_options, &id),
 
-                Commands::Edit { id } => commands::edit(&root, &id),
+                Commands::Edit { id } => commands::edit(&root, &store_options, &id),
 
-                Commands::Close { ids, reason } => {
-                    for id in &ids {
-                        if let Err(e) = commands::close(&root, id, reason.as_deref()) {
-                            eprintln!("{} {}: {}", "error:".red(), id, e);
-                        }
-                    }
-                    Ok(())
-                }
+                Commands::Close { ids, reason } => for_each_id(&ids, |id| {
+                    commands::close(&root, &store_options, id, reason.as_deref())
+                }),
 
                 Commands::Reopen { ids } => {
-                    for id in &ids {
-                        if let Err(e) = commands::reopen(&root, id) {
-                            eprintln!("{} {}: {}", "error:".red(), id, e);
-                        }
-                    }
-                    Ok(())
+                    for_each_id(&ids, |id| commands::reopen(&root, &store_options, id))
                 }
 
                 Commands::Start { ids } => {
-                    for id in &ids {
-                        if let Err(e) = commands::start(&root, id) {
-                            eprintln!("{} {}: {}", "error:".red(), id, e);
-                        }
-                    }
-                    Ok(())
+                    for_each_id(&ids, |id| commands::start(&root, &store_options, id))
                 }
 
                 Commands::Stop { ids } => {
-                    for id in &ids {
-                        if let Err(e) = commands::stop(&root, id) {
-                            eprintln!("{} {}: {}", "error:".red(), id, e);
-                        }
-                    }
-                    Ok(())
+                    for_each_id(&ids, |id| commands::stop(&root, &store_options, id))
+                }
+
+                Commands::Move { id, status } => {
+                    commands::move_task(&root, &store_options, &id, &status)
+                }
+
+                Commands::Ready { mine, assignee } => {
+                    commands::ready(&root, &store_options, mine, assignee.as_deref())
+                }
+
+                Commands::Next { mine, assignee } => {
+                    commands::next(&root, &store_options, mine, assignee.as_deref())
                 }
 
-                Commands::Ready => commands::ready(&root),
+                Commands::Activity {
+                    limit,
+                    all,
+                    archived,
+                    json,
+                } => commands::activity(&root, &store_options, limit, all, archived, json),
 
-                Commands::Next => commands::next(&root),
+                Commands::Tree { children, archived } => {
+                    commands::tree(&root, &store_options, children, archived)
+                }
 
-                Commands::Activity { limit, all } => commands::activity(&root, limit, all),
+                Commands::Agenda => commands::agenda(&root, &store_options),
 
-                Commands::Tree => commands::tree(&root),
+                Commands::Timesheet {
+                    from,
+                    to,
+                    by,
+                    csv,
+                    json,
+                } => {
+                    let format = if json {
+                        commands::TimesheetFormat::Json
+                    } else if csv {
+                        commands::TimesheetFormat::Csv
+                    } else {
+                        commands::TimesheetFormat::Text
+                    };
+                    commands::timesheet(
+                        &root,
+                        &store_options,
+                        from.as_deref(),
+                        to.as_deref(),
+                        &by,
+                        format,
+                    )
+                }
 
-                Commands::Log { id, message } => {
+                Commands::Log { id, message, spent } => {
                     let message = message.join(" ");
-                    commands::log(&root, &id, &message)
+                    commands::log(&root, &store_options, &id, &message, spent.as_deref())
                 }
 
-                Commands::Block { id, by } => commands::block(&root, &id, &by),
+                Commands::Block { id, by } => commands::block(&root, &store_options, &id, &by),
 
-                Commands::Unblock { id, blocker } => commands::unblock(&root, &id, &blocker),
+                Commands::Unblock { id, blocker } => {
+                    commands::unblock(&root, &store_options, &id, &blocker)
+                }
+
+                Commands::Assign { id, who } => {
+                    commands::assign(&root, &store_options, &id, who.as_deref())
+                }
 
-                Commands::Import { file } => commands::import(&root, &file),
+                Commands::Check { id, item } => {
+                    commands::check(&root, &store_options, &id, &item, true)
+                }
+
+                Commands::Uncheck { id, item } => {
+                    commands::check(&root, &store_options, &id, &item, false)
+                }
+
+                Commands::Adopt { child, parent } => {
+                    commands::adopt(&root, &store_options, &child, &parent)
+                }
+
+                Commands::Orphan { id } => commands::orphan(&root, &store_options, &id),
+
+                Commands::Import { file } => commands::import(&root, &store_options, &file),
 
                 Commands::Update {
                     id,
@@ -417,6 +825,9 @@ fn main() {
                     add_tag,
                     remove_tag,
                     body,
+                    due,
+                    defer,
+                    set,
                 } => {
                     let priority = priority.and_then(|p| p.parse::<Priority>().ok());
                     let tags =
@@ -424,13 +835,19 @@ fn main() {
 
                     commands::update(
                         &root,
+                        &store_options,
                         &id,
-                        title.as_deref(),
-                        priority,
-                        tags,
-                        add_tag.as_deref(),
-                        remove_tag.as_deref(),
-                        body.as_deref(),
+                        commands::UpdateOptions {
+                            title: title.as_deref(),
+                            priority,
+                            tags,
+                            add_tag: add_tag.as_deref(),
+                            remove_tag: remove_tag.as_deref(),
+                            body: body.as_deref(),
+                            due: due.as_deref(),
+                            defer: defer.as_deref(),
+                            set: &set,
+                        },
                     )
                 }
 
@@ -442,15 +859,45 @@ fn main() {
                     };
                     commands::update(
                         &root,
+                        &store_options,
                         &id,
-                        None,
-                        None,
-                        None,
-                        None,
-                        None,
-                        Some(body.as_str()),
+                        commands::UpdateOptions {
+                            body: Some(body.as_str()),
+                            ..Default::default()
+                        },
                     )
                 }
+
+                Commands::Doctor { fix } => commands::doctor(&root, &store_options, fix),
+
+                Commands::Archive {
+                    older_than,
+                    dry_run,
+                } => commands::archive(&root, &store_options, &older_than, dry_run),
+
+                Commands::Migrate { dry_run } => commands::migrate(&root, &store_options, dry_run),
+
+                Commands::Diff { from, to, json } => {
+                    commands::diff(&root, &store_options, &from, to.as_deref(), json)
+                }
+
+                Commands::History { id } => commands::history(&root, &store_options, &id),
+
+                Commands::Hooks { action } => match action {
+                    HooksCommand::Install { force } => {
+                        commands::install_hooks(&root, &store_options, force)
+                    }
+                    HooksCommand::CommitMsg { file } => {
+                        commands::commit_msg(&root, &store_options, &file)
+                    }
+                    HooksCommand::PostCommit => commands::post_commit(&root, &store_options),
+                },
+
+                Commands::Resolve { id } => commands::resolve(&root, &store_options, &id),
+
+                Commands::Undo { count } => commands::undo(&root, &store_options, count),
+
+                Commands::Redo { count } => commands::redo(&root, &store_options, count),
             }
         }
     };
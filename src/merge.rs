@@ -0,0 +1,567 @@
+//! Three-way merge of task files, used by `yatl merge-driver`.
+//!
+//! Instead of merging lines, the frontmatter is merged field by field: a
+//! field changed on one side takes that side's value, and when both sides
+//! changed it differently the side updated most recently wins. List fields
+//! combine the entries both sides added and drop the ones either side
+//! removed. Log entries from both sides are merged into one log sorted by
+//! timestamp, with duplicates removed. Only conflicting bodies fall back to
+//! a line-based merge with conflict markers.
+//!
+//! Files merged line by line instead (git's default, or `merge=union`) can
+//! end up with conflict markers, repeated frontmatter fields or repeated log
+//! entries. `damage` detects these and `split_sides` recovers the two
+//! versions for `yatl resolve`.
+
+use crate::git;
+use crate::log::LogEntry;
+use crate::task::{Task, WorkInterval};
+use chrono::{DateTime, Utc};
+use serde_yaml::Mapping;
+
+/// Result of merging two versions of a task file
+#[derive(Debug)]
+pub struct Merged {
+    pub content: String,
+    /// Whether the result contains conflict markers
+    pub conflicted: bool,
+}
+
+/// Merge `ours` and `theirs`, which both derive from `base` (empty if the
+/// file was added on both sides). Fails if either side doesn't parse.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Result<Merged, String> {
+    let base = Task::parse(base).ok();
+    let ours = Task::parse(ours).map_err(|e| format!("our version: {}", e))?;
+    let theirs = Task::parse(theirs).map_err(|e| format!("their version: {}", e))?;
+
+    let prefer_theirs = theirs.frontmatter.updated > ours.frontmatter.updated;
+    let b = base.as_ref().map(|task| &task.frontmatter);
+    let (o, t) = (&ours.frontmatter, &theirs.frontmatter);
+
+    let mut merged = ours.clone();
+    let fm = &mut merged.frontmatter;
+    fm.yatl_version = o.yatl_version.max(t.yatl_version);
+    fm.title = pick(b.map(|b| &b.title), &o.title, &t.title, prefer_theirs);
+    fm.created = pick(b.map(|b| &b.created), &o.created, &t.created, prefer_theirs);
+    fm.updated = o.updated.max(t.updated);
+    fm.author = pick(b.map(|b| &b.author), &o.author, &t.author, prefer_theirs);
+    fm.assignee = pick(
+        b.map(|b| &b.assignee),
+        &o.assignee,
+        &t.assignee,
+        prefer_theirs,
+    );
+    fm.priority = pick(
+        b.map(|b| &b.priority),
+        &o.priority,
+        &t.priority,
+        prefer_theirs,
+    );
+    fm.parent = pick(b.map(|b| &b.parent), &o.parent, &t.parent, prefer_theirs);
+    fm.due = pick(b.map(|b| &b.due), &o.due, &t.due, prefer_theirs);
+    fm.defer_until = pick(
+        b.map(|b| &b.defer_until),
+        &o.defer_until,
+        &t.defer_until,
+        prefer_theirs,
+    );
+    fm.tags = merge_list(b.map(|b| &b.tags[..]), &o.tags, &t.tags);
+    fm.blocked_by = merge_list(b.map(|b| &b.blocked_by[..]), &o.blocked_by, &t.blocked_by);
+    fm.blocks = merge_list(b.map(|b| &b.blocks[..]), &o.blocks, &t.blocks);
+    fm.children = merge_list(b.map(|b| &b.children[..]), &o.children, &t.children);
+    fm.work = merge_work(b.map(|b| &b.work[..]), &o.work, &t.work);
+    fm.extra = merge_extra(b.map(|b| &b.extra), &o.extra, &t.extra, prefer_theirs);
+
+    let base_body = base.as_ref().map(|task| task.body.as_str());
+    let mut conflicted = false;
+    merged.body = if ours.body == theirs.body || base_body == Some(theirs.body.as_str()) {
+        ours.body.clone()
+    } else if base_body == Some(ours.body.as_str()) {
+        theirs.body.clone()
+    } else {
+        let (body, conflicts) = git::merge_file(
+            &with_newline(base_body.unwrap_or("")),
+            &with_newline(&ours.body),
+            &with_newline(&theirs.body),
+        )?;
+        conflicted = conflicts;
+        body.trim().to_string()
+    };
+
+    merged.log = merge_logs(&ours.log, &theirs.log);
+
+    Ok(Merged {
+        content: merged.to_markdown(),
+        conflicted,
+    })
+}
+
+/// Three-way merge of a single value
+fn pick<T: PartialEq + Clone>(base: Option<&T>, ours: &T, theirs: &T, prefer_theirs: bool) -> T {
+    if ours == theirs || base == Some(theirs) {
+        ours.clone()
+    } else if base == Some(ours) || prefer_theirs {
+        theirs.clone()
+    } else {
+        ours.clone()
+    }
+}
+
+/// Three-way merge of a list: everything either side has, except entries
+/// that were in the base and one side removed
+fn merge_list<T: PartialEq + Clone>(base: Option<&[T]>, ours: &[T], theirs: &[T]) -> Vec<T> {
+    let base = base.unwrap_or(&[]);
+    let mut merged: Vec<T> = vec![];
+    for item in ours.iter().chain(theirs) {
+        let removed = base.contains(item) && !(ours.contains(item) && theirs.contains(item));
+        if !removed && !merged.contains(item) {
+            merged.push(item.clone());
+        }
+    }
+    merged
+}
+
+/// Three-way merge of work intervals, where an interval one side ended
+/// replaces the same interval still running on the other side
+fn merge_work(
+    base: Option<&[WorkInterval]>,
+    ours: &[WorkInterval],
+    theirs: &[WorkInterval],
+) -> Vec<WorkInterval> {
+    let mut merged = merge_list(base, ours, theirs);
+    let ended: Vec<(DateTime<Utc>, Option<String>)> = merged
+        .iter()
+        .filter(|work| work.end.is_some())
+        .map(|work| (work.start, work.author.clone()))
+        .collect();
+    merged.retain(|work| work.end.is_some() || !ended.contains(&(work.start, work.author.clone())));
+    merged.sort_by_key(|work| work.start);
+    merged
+}
+
+/// Three-way merge of the keys yatl doesn't know, key by key; a key removed
+/// on one side and left alone on the other is dropped
+fn merge_extra(
+    base: Option<&Mapping>,
+    ours: &Mapping,
+    theirs: &Mapping,
+    prefer_theirs: bool,
+) -> Mapping {
+    let mut merged = Mapping::new();
+    for key in ours.keys().chain(theirs.keys()) {
+        if merged.contains_key(key) {
+            continue;
+        }
+        let value = pick(
+            base.map(|base| base.get(key).cloned()).as_ref(),
+            &ours.get(key).cloned(),
+            &theirs.get(key).cloned(),
+            prefer_theirs,
+        );
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+    merged
+}
+
+/// Union of both sides' log entries, deduplicated and sorted by timestamp
+/// (entries with the same timestamp keep their order)
+pub fn merge_logs(ours: &[LogEntry], theirs: &[LogEntry]) -> Vec<LogEntry> {
+    let mut entries: Vec<LogEntry> = vec![];
+    for entry in ours.iter().chain(theirs) {
+        if !entries.contains(entry) {
+            entries.push(entry.clone());
+        }
+    }
+    entries.sort_by_key(|entry| entry.timestamp);
+    entries
+}
+
+/// Split a log section into its entries, each normalized to start with
+/// `---\n# Log:` and without trailing whitespace. Entries are found by their
+/// `# Log:` header, since a line merge can drop the `---` separator.
+pub fn log_entries(log: &str) -> Vec<String> {
+    let mut entries: Vec<String> = vec![];
+    let mut current = String::new();
+    for line in log.lines() {
+        if line.starts_with("# Log:") {
+            entries.push(std::mem::take(&mut current));
+            current = format!("---\n{}\n", line);
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    entries.push(current);
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            // The separator before the next entry belongs to that entry
+            let entry = entry.trim_end();
+            let entry = entry.strip_suffix("\n---").unwrap_or(entry);
+            entry.trim_end().to_string()
+        })
+        .filter(|entry| !entry.is_empty() && entry != "---")
+        .collect()
+}
+
+/// Describe the damage a line-based merge left in a task file, if any
+pub fn damage(content: &str) -> Option<String> {
+    if let Some(line) = conflict_block(content) {
+        return Some(format!(
+            "unresolved merge conflict markers at line {}",
+            line + 1
+        ));
+    }
+
+    let (frontmatter, rest) = split_frontmatter(content)?;
+    let keys = top_level_keys(frontmatter);
+    for (i, key) in keys.iter().enumerate() {
+        if keys[..i].contains(key) {
+            return Some(format!("duplicate frontmatter field '{}'", key));
+        }
+    }
+
+    let log = rest
+        .find("\n---\n# Log:")
+        .map_or("", |start| &rest[start + 1..]);
+    let entries = log_entries(log);
+    for (i, entry) in entries.iter().enumerate() {
+        if entries[..i].contains(entry) {
+            let header = entry.lines().nth(1).unwrap_or_default();
+            return Some(format!("duplicate log entry '{}'", header));
+        }
+    }
+    None
+}
+
+/// Recover our and their version of a file damaged by a line-based merge.
+/// Conflict blocks are split into their two sides, a repeated frontmatter
+/// field counts as ours the first time and theirs the last time, and
+/// repeated log entries are dropped.
+pub fn split_sides(content: &str) -> (String, String) {
+    #[derive(PartialEq)]
+    enum Side {
+        Both,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut ours = String::new();
+    let mut theirs = String::new();
+    let mut side = Side::Both;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("<<<<<<<") {
+            side = Side::Ours;
+        } else if line.starts_with("|||||||") && side == Side::Ours {
+            side = Side::Base;
+        } else if line.trim_end() == "=======" && matches!(side, Side::Ours | Side::Base) {
+            side = Side::Theirs;
+        } else if line.starts_with(">>>>>>>") {
+            side = Side::Both;
+        } else {
+            match side {
+                Side::Both => {
+                    ours.push_str(line);
+                    theirs.push_str(line);
+                }
+                Side::Ours => ours.push_str(line),
+                Side::Theirs => theirs.push_str(line),
+                Side::Base => {}
+            }
+        }
+    }
+
+    (repair(&ours, true), repair(&theirs, false))
+}
+
+/// Drop repeated frontmatter fields (keeping the first or last occurrence)
+/// and repeated log entries
+fn repair(content: &str, keep_first: bool) -> String {
+    let Some((frontmatter, rest)) = split_frontmatter(content) else {
+        return content.to_string();
+    };
+
+    // Each top-level field with its continuation lines (list items etc.)
+    let mut fields: Vec<(Option<String>, String)> = vec![];
+    for line in frontmatter.split_inclusive('\n') {
+        match top_level_key(line) {
+            Some(key) => fields.push((Some(key.to_string()), line.to_string())),
+            None => match fields.last_mut() {
+                Some((_, text)) => text.push_str(line),
+                None => fields.push((None, line.to_string())),
+            },
+        }
+    }
+    let mut kept: Vec<(Option<String>, String)> = vec![];
+    for (key, text) in fields {
+        match kept.iter().position(|(k, _)| key.is_some() && *k == key) {
+            Some(i) if !keep_first => kept[i] = (key, text),
+            Some(_) => {}
+            None => kept.push((key, text)),
+        }
+    }
+    let frontmatter: String = kept.into_iter().map(|(_, text)| text).collect();
+
+    let (body, log) = match rest.find("\n---\n# Log:") {
+        Some(start) => (&rest[..start + 1], &rest[start + 1..]),
+        None => (rest, ""),
+    };
+    let mut entries: Vec<String> = vec![];
+    for entry in log_entries(log) {
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+    let log: Vec<String> = entries.iter().map(|entry| format!("{}\n", entry)).collect();
+
+    format!("---{}---{}{}", frontmatter, body, log.join("\n"))
+}
+
+/// Split a task file into its frontmatter and everything after it, the
+/// same way `Task::parse` does
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let mut parts = content.splitn(3, "---");
+    parts.next()?;
+    Some((parts.next()?, parts.next()?))
+}
+
+/// Names of the top-level fields in a YAML frontmatter block, in order
+fn top_level_keys(frontmatter: &str) -> Vec<&str> {
+    frontmatter.lines().filter_map(top_level_key).collect()
+}
+
+fn top_level_key(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t', '-', '#']) {
+        return None;
+    }
+    line.split_once(':').map(|(key, _)| key.trim())
+}
+
+/// Whether a line is a git conflict marker (`=======` alone is too common in
+/// markdown to count)
+/// Find the first complete conflict block (`<<<<<<<`, `=======`,
+/// `>>>>>>>`) outside fenced code, returning the index of its first line.
+/// Markers quoted in a task's body on their own are not damage.
+fn conflict_block(content: &str) -> Option<usize> {
+    let mut fenced = false;
+    let mut start = None;
+    let mut separated = false;
+    for (i, line) in content.lines().enumerate() {
+        if is_marker(line, "<<<<<<<") && !fenced {
+            start = Some(i);
+            separated = false;
+        } else if start.is_none() {
+            if line.starts_with("```") || line.starts_with("~~~") {
+                fenced = !fenced;
+            }
+        } else if line == "=======" {
+            separated = true;
+        } else if separated && is_marker(line, ">>>>>>>") {
+            return start;
+        }
+    }
+    None
+}
+
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::TaskId;
+    use crate::log;
+    use crate::task::Priority;
+    use chrono::Duration;
+
+    #[test]
+    fn test_merges_frontmatter_fields() {
+        let mut base = Task::new("Task", Some("brian".into()));
+        base.frontmatter.tags = vec!["a".into(), "b".into()];
+        base.frontmatter.blocked_by = vec![TaskId::from_string("blocker1")];
+        base.frontmatter
+            .extra
+            .insert("jira".into(), "PROJ-1".into());
+        base.frontmatter.extra.insert("estimate".into(), 3.into());
+
+        let mut ours = base.clone();
+        ours.frontmatter.priority = Priority::High;
+        ours.frontmatter.tags.push("ours".into());
+        ours.frontmatter.blocked_by.clear();
+        ours.frontmatter.extra.insert("estimate".into(), 5.into());
+        ours.add_log("Reprioritized.", None);
+
+        let mut theirs = base.clone();
+        theirs.frontmatter.title = "Renamed task".into();
+        theirs.frontmatter.tags.retain(|tag| tag != "a");
+        theirs.frontmatter.tags.push("theirs".into());
+        theirs.frontmatter.extra.remove("jira");
+        theirs
+            .frontmatter
+            .extra
+            .insert("component".into(), "auth".into());
+        theirs.frontmatter.updated = ours.frontmatter.updated + Duration::seconds(60);
+
+        let merged = merge(
+            &base.to_markdown(),
+            &ours.to_markdown(),
+            &theirs.to_markdown(),
+        )
+        .unwrap();
+        assert!(!merged.conflicted);
+
+        let task = Task::parse(&merged.content).unwrap();
+        assert_eq!(task.title(), "Renamed task");
+        assert_eq!(task.priority(), Priority::High);
+        assert_eq!(task.frontmatter.tags, vec!["b", "ours", "theirs"]);
+        assert!(task.frontmatter.blocked_by.is_empty());
+        let extra: Vec<String> = task
+            .frontmatter
+            .extra
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}={}",
+                    key.as_str().unwrap(),
+                    serde_yaml::to_string(value).unwrap().trim()
+                )
+            })
+            .collect();
+        assert_eq!(extra, vec!["estimate=5", "component=auth"]);
+        assert_eq!(task.frontmatter.updated, theirs.frontmatter.updated);
+        assert_eq!(task.log.len(), 2);
+    }
+
+    #[test]
+    fn test_merges_work_intervals() {
+        let mut base = Task::new("Task", Some("brian".into()));
+        let started = base.frontmatter.created;
+        base.start_work(Some("brian"), started);
+
+        // One side stops work, the other logs time by hand
+        let mut ours = base.clone();
+        ours.stop_work(started + Duration::minutes(30));
+        let mut theirs = base.clone();
+        theirs.frontmatter.work.push(WorkInterval {
+            start: started - Duration::hours(2),
+            end: Some(started - Duration::hours(1)),
+            author: Some("alice".into()),
+        });
+
+        let merged = merge(
+            &base.to_markdown(),
+            &ours.to_markdown(),
+            &theirs.to_markdown(),
+        )
+        .unwrap();
+        let task = Task::parse(&merged.content).unwrap();
+        assert_eq!(task.frontmatter.work.len(), 2);
+        assert_eq!(task.frontmatter.work[0].author.as_deref(), Some("alice"));
+        assert_eq!(
+            task.frontmatter.work[1].end,
+            Some(started + Duration::minutes(30))
+        );
+    }
+
+    #[test]
+    fn test_detects_and_splits_merge_damage() {
+        let content = "---\n\
+                       title: Task\n\
+                       priority: high\n\
+                       priority: low\n\
+                       tags:\n\
+                       - a\n\
+                       tags:\n\
+                       - b\n\
+                       ---\n\
+                       \n\
+                       <<<<<<< HEAD\n\
+                       Our body\n\
+                       =======\n\
+                       Their body\n\
+                       >>>>>>> other\n\
+                       \n\
+                       ---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n\n\
+                       ---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n";
+        assert_eq!(
+            damage(content).as_deref(),
+            Some("unresolved merge conflict markers at line 11")
+        );
+
+        let (ours, theirs) = split_sides(content);
+        assert_eq!(
+            ours,
+            "---\ntitle: Task\npriority: high\ntags:\n- a\n---\n\nOur body\n\n\
+             ---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n"
+        );
+        assert!(theirs.contains("priority: low\ntags:\n- b\n---\n\nTheir body\n"));
+        assert_eq!(damage(&ours), None);
+        assert_eq!(damage(&theirs), None);
+
+        let union = content
+            .replace("<<<<<<< HEAD\n", "")
+            .replace(">>>>>>> other\n", "");
+        assert_eq!(
+            damage(&union).as_deref(),
+            Some("duplicate frontmatter field 'priority'")
+        );
+        let (ours, _) = split_sides(&union);
+        let duplicated_log = format!("{}\n{}", ours, &ours[ours.find("---\n# Log:").unwrap()..]);
+        assert_eq!(
+            damage(&duplicated_log).as_deref(),
+            Some("duplicate log entry '# Log: 2025-01-01T10:00:00Z a'")
+        );
+    }
+
+    #[test]
+    fn test_markers_in_body_are_not_damage() {
+        let task = |body: &str| {
+            let mut task = Task::new("Task", None);
+            task.body = body.to_string();
+            task.to_markdown()
+        };
+
+        // Stray markers, as when describing a conflict in prose
+        let content = task("Git left\n<<<<<<< HEAD\nin the file, and\n>>>>>>> main\nat the end.");
+        assert_eq!(damage(&content), None);
+        assert!(Task::parse(&content).is_ok());
+
+        // A whole conflict block quoted in fenced code
+        let content = task("```\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> main\n```");
+        assert_eq!(damage(&content), None);
+        assert!(Task::parse(&content).is_ok());
+
+        let content = task("<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> main");
+        assert!(damage(&content)
+            .unwrap()
+            .starts_with("unresolved merge conflict markers at line"));
+    }
+
+    #[test]
+    fn test_merges_logs_by_timestamp() {
+        let ours = "---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n\n\
+                    ---\n# Log: 2025-01-03T10:00:00Z a\n\nOurs.\n";
+        let theirs = "---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n\n\
+                      ---\n# Log: 2025-01-02T10:00:00Z b\n\nTheirs.\n";
+        assert_eq!(
+            log::render(&merge_logs(&log::parse(ours), &log::parse(theirs))),
+            "---\n# Log: 2025-01-01T10:00:00Z a\n\nCreated task.\n\n\
+             ---\n# Log: 2025-01-02T10:00:00Z b\n\nTheirs.\n\n\
+             ---\n# Log: 2025-01-03T10:00:00Z a\n\nOurs.\n"
+        );
+    }
+}
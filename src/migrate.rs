@@ -0,0 +1,217 @@
+//! Task file format migrations.
+//!
+//! Every task file records the format version it was written with in
+//! `yatl_version` (files without it are version 0). Each migration upgrades
+//! the raw file content by exactly one version, so a file is brought up to
+//! `TASK_FORMAT_VERSION` by applying the registered migrations in order.
+//! Migrations work on text rather than parsed tasks because older formats
+//! don't necessarily parse with the current `Task::parse`.
+
+use crate::task::TASK_FORMAT_VERSION;
+
+/// A single format upgrade from `from` to `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    apply: fn(&str) -> String,
+}
+
+/// All known migrations, ordered by the version they upgrade from
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "convert '### {timestamp} {author}' log headers and drop the '## Log' header",
+    apply: v0_log_entries,
+}];
+
+/// Result of migrating one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    pub from: u32,
+    pub to: u32,
+    /// Descriptions of the migrations that were applied, in order
+    pub applied: Vec<&'static str>,
+    pub content: String,
+}
+
+/// Read the format version from a task file's frontmatter (0 if absent)
+pub fn file_version(content: &str) -> Result<u32, String> {
+    let parts: Vec<&str> = content.splitn(3, "---").collect();
+    if parts.len() < 3 {
+        return Err("Invalid task format: missing YAML frontmatter".into());
+    }
+
+    let yaml: serde_yaml::Value = serde_yaml::from_str(parts[1])
+        .map_err(|e| format!("Failed to parse frontmatter: {}", e))?;
+    match yaml.get("yatl_version") {
+        None => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid yatl_version: {:?}", value)),
+    }
+}
+
+/// Upgrade a task file to the current format.
+/// Returns None if the file is already current.
+pub fn migrate(content: &str) -> Result<Option<Migrated>, String> {
+    let from = file_version(content)?;
+    if from > TASK_FORMAT_VERSION {
+        return Err(format!(
+            "format version {} is newer than this yatl supports ({})",
+            from, TASK_FORMAT_VERSION
+        ));
+    }
+    if from == TASK_FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let mut version = from;
+    let mut content = content.to_string();
+    let mut applied = vec![];
+    while version < TASK_FORMAT_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| format!("no migration from format version {}", version))?;
+        content = set_version(&(migration.apply)(&content), version + 1);
+        applied.push(migration.description);
+        version += 1;
+    }
+
+    Ok(Some(Migrated {
+        from,
+        to: version,
+        applied,
+        content,
+    }))
+}
+
+/// Set `yatl_version` in the frontmatter, inserting it as the first field
+/// if it isn't there yet
+fn set_version(content: &str, version: u32) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let close = lines.iter().skip(1).position(|l| l == "---").map(|i| i + 1);
+    let field = format!("yatl_version: {}", version);
+
+    let existing = close.and_then(|close| {
+        lines[1..close]
+            .iter()
+            .position(|l| l.starts_with("yatl_version:"))
+            .map(|i| i + 1)
+    });
+    match existing {
+        Some(i) => lines[i] = field,
+        None => lines.insert(1, field),
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// v0 -> v1: log entries used to start with `### {timestamp} {author}` under
+/// a `## Log` section header. They now start with `---\n# Log: ...` and the
+/// section header is gone.
+fn v0_log_entries(content: &str) -> String {
+    let mut converted = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        match line.strip_prefix("### ") {
+            Some(rest) if is_log_header(rest) => {
+                converted.push_str("---\n# Log: ");
+                converted.push_str(rest);
+            }
+            _ => converted.push_str(line),
+        }
+    }
+
+    // "---\n## Log\n" followed by blank lines and the first entry's "---"
+    // collapses into that single "---"
+    const HEADER: &str = "---\n## Log\n";
+    let mut result = String::with_capacity(converted.len());
+    let mut rest = converted.as_str();
+    while let Some(start) = rest.find(HEADER) {
+        let after = rest[start + HEADER.len()..].trim_start_matches('\n');
+        if after.starts_with("---") {
+            result.push_str(&rest[..start]);
+            rest = after;
+        } else {
+            result.push_str(&rest[..start + HEADER.len()]);
+            rest = &rest[start + HEADER.len()..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Whether text starts with `YYYY-MM-DDTHH:MM:SSZ ` (an old log header)
+fn is_log_header(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    if bytes.len() < 21 {
+        return false;
+    }
+    bytes[..21].iter().enumerate().all(|(i, b)| match i {
+        4 | 7 => *b == b'-',
+        10 => *b == b'T',
+        13 | 16 => *b == b':',
+        19 => *b == b'Z',
+        20 => *b == b' ',
+        _ => b.is_ascii_digit(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Task;
+
+    const V0_TASK: &str = "---
+title: Old task
+id: a1b2c3d4
+created: 2025-11-27T03:33:45Z
+updated: 2025-11-27T03:33:45Z
+---
+Body text
+
+### Not a log header
+
+---
+## Log
+
+### 2025-11-27T03:33:45Z brian
+
+Created task.
+
+### 2025-11-28T10:00:00Z brian
+
+Closed.
+";
+
+    #[test]
+    fn test_migrates_v0_to_current() {
+        let migrated = migrate(V0_TASK).unwrap().unwrap();
+        assert_eq!(migrated.from, 0);
+        assert_eq!(migrated.to, TASK_FORMAT_VERSION);
+        assert!(migrated.content.starts_with("---\nyatl_version: 1\ntitle:"));
+        assert!(migrated.content.contains("### Not a log header"));
+        assert!(!migrated.content.contains("## Log\n"));
+
+        let task = Task::parse(&migrated.content).unwrap();
+        assert_eq!(task.body, "Body text\n\n### Not a log header");
+        assert_eq!(task.log.len(), 2);
+
+        // Already current: nothing to do
+        assert_eq!(migrate(&migrated.content).unwrap(), None);
+    }
+
+    #[test]
+    fn test_refuses_newer_versions() {
+        let newer = V0_TASK.replacen(
+            "---\n",
+            &format!("---\nyatl_version: {}\n", TASK_FORMAT_VERSION + 1),
+            1,
+        );
+        assert!(migrate(&newer).is_err());
+    }
+}
@@ -2,25 +2,79 @@ use crate::id::TaskId;
 use crate::store::{Result, Store};
 
 /// Resolves shortest unique prefixes for task IDs.
-/// Caches all task IDs for efficient batch operations.
+/// Caches all task IDs (sorted) for efficient batch operations.
 pub struct PrefixResolver {
-    all_ids: Vec<TaskId>,
+    /// Lowercased IDs, sorted and deduplicated
+    sorted_ids: Vec<String>,
 }
 
 impl PrefixResolver {
-    /// Create a new resolver by loading all task IDs from the store
+    /// Create a new resolver from the IDs of every task in the store,
+    /// including archived ones (taken from file names, as `Store::find` does)
     pub fn new(store: &Store) -> Result<Self> {
-        let all_tasks = store.list_all()?;
-        let all_ids = all_tasks
-            .into_iter()
-            .map(|(_, t)| t.id().clone())
+        let ids: Vec<TaskId> = store
+            .task_paths()?
+            .iter()
+            .filter_map(|path| path.file_stem())
+            .map(|stem| TaskId::from_string(stem.to_string_lossy()))
             .collect();
-        Ok(Self { all_ids })
+        Ok(Self::from_ids(&ids))
+    }
+
+    /// Create a resolver from a set of IDs
+    pub fn from_ids<'a>(ids: impl IntoIterator<Item = &'a TaskId>) -> Self {
+        let mut sorted_ids: Vec<String> =
+            ids.into_iter().map(|id| id.full().to_lowercase()).collect();
+        sorted_ids.sort();
+        sorted_ids.dedup();
+        Self { sorted_ids }
     }
 
     /// Get the shortest unique prefix for a task ID
     pub fn shortest_prefix<'a>(&self, id: &'a TaskId) -> &'a str {
-        let refs: Vec<&TaskId> = self.all_ids.iter().collect();
-        id.shortest_unique_prefix(&refs)
+        // In sorted order, the IDs sharing the longest prefix with this one
+        // are its immediate neighbours
+        let lower = id.full().to_lowercase();
+        let pos = self.sorted_ids.partition_point(|other| *other < lower);
+        let next = self.sorted_ids[pos..].iter().find(|other| **other != lower);
+        let prev = self.sorted_ids[..pos].last();
+
+        let shared = [prev, next]
+            .into_iter()
+            .flatten()
+            .map(|other| common_prefix_len(&lower, other))
+            .max()
+            .unwrap_or(0);
+
+        let full = id.full();
+        let len = (shared + 1).min(full.len());
+        full.get(..len).unwrap_or(full)
+    }
+}
+
+/// Number of leading bytes two strings have in common
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_per_id_computation() {
+        let ids: Vec<TaskId> = ["a1b2c3d4", "a1b2xxxx", "xxxxxxxx", "a1zzzzzz", "b0000000"]
+            .iter()
+            .map(|s| TaskId::from_string(*s))
+            .collect();
+        let resolver = PrefixResolver::from_ids(&ids);
+        let refs: Vec<&TaskId> = ids.iter().collect();
+
+        for id in &ids {
+            assert_eq!(
+                resolver.shortest_prefix(id),
+                id.shortest_unique_prefix(&refs)
+            );
+        }
     }
 }
@@ -1,12 +1,37 @@
-use crate::config::Config;
+use crate::backend::{Backend, GitRevision, WorkTree};
+use crate::config::{Config, StatusConfig, UncheckedOnClose};
+use crate::dates;
+use crate::fields::{FieldConfig, FieldType};
+use crate::git;
 use crate::id::TaskId;
-use crate::task::{Status, Task};
-use std::fs;
+use crate::index::{FileStamp, Index, INDEX_FILE};
+use crate::journal::{FileChange, Journal, JournalEntry, JOURNAL_FILE};
+use crate::log::LogKind;
+use crate::task::{Status, Task, TASK_FORMAT_VERSION};
+use crate::transaction::Transaction;
+use colored::*;
+use serde_yaml::Value;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 const TASKS_DIR: &str = ".tasks";
 
+/// Config file name (inside .tasks)
+const CONFIG_FILE: &str = "config.yaml";
+
+/// Directory (inside .tasks) holding per-task advisory lock files
+const LOCKS_DIR: &str = ".locks";
+
+/// Default .gitattributes written by `yatl init`
+const UNION_ATTRIBUTES: &str = "# Use union merge for task files - concatenates both sides\n\
+                                # This helps with the append-only log section\n\
+                                *.md merge=union\n";
+
 #[derive(Error, Debug)]
 pub enum StoreError {
     #[error("Not a yatl-enabled directory. Run 'yatl init' first.")]
@@ -26,56 +51,295 @@ pub enum StoreError {
 
     #[error("Parse error: {0}")]
     Parse(String),
+
+    #[error("Failed to parse task file {}: {message}", path.display())]
+    InvalidTask { path: PathBuf, message: String },
+
+    #[error("Refusing to write {}, which could not be read back: {message}", path.display())]
+    Unreadable { path: PathBuf, message: String },
+
+    #[error("Task file was changed on disk since it was loaded: {0}")]
+    Conflict(PathBuf),
+
+    #[error("Transaction failed: {0}")]
+    Rollback(String),
+
+    #[error("{0} problem(s) found")]
+    ProblemsFound(usize),
+
+    #[error("{failed} of {total} task(s) failed")]
+    SomeFailed { failed: usize, total: usize },
+
+    #[error("{0} task reference(s) in the commit message match no single task (commit with --no-verify to skip the check)")]
+    UnresolvedRefs(usize),
+
+    #[error(
+        "Task file {} uses format version {version}, newer than this yatl supports ({}); upgrade yatl to modify it",
+        path.display(),
+        TASK_FORMAT_VERSION
+    )]
+    UnsupportedVersion { path: PathBuf, version: u32 },
+
+    #[error("Tasks read from revision '{0}' can't be modified")]
+    ReadOnly(String),
+
+    #[error("Merge left conflicts in {}", .0.display())]
+    MergeConflict(PathBuf),
+
+    #[error("Cannot close {id}: {count} subtask(s) not finished (require_children_closed is set)")]
+    UnfinishedChildren { id: TaskId, count: usize },
+
+    #[error("Cannot close {id}: {count} checklist item(s) unchecked (unchecked_on_close is refuse)")]
+    UncheckedItems { id: TaskId, count: usize },
+
+    #[error("Can't tell who you are: set default_author in .tasks/config.yaml or git config user.name")]
+    UnknownAuthor,
+
+    #[error("Unknown status '{0}'")]
+    UnknownStatus(String),
+
+    #[error("Cannot move task from '{from}' to '{to}' (allowed: {allowed})")]
+    InvalidTransition {
+        from: Status,
+        to: Status,
+        allowed: String,
+    },
+
+    #[error("Task is in an unknown status directory: {}", .0.display())]
+    UnknownStatusDir(PathBuf),
+
+    #[error("Invalid config.yaml: {0}")]
+    InvalidConfig(String),
+
+    #[error("{0}")]
+    InvalidField(String),
+
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    #[error("{0}")]
+    NotAllowed(String),
+
+    #[error("Can't read the undo journal {0}; move it aside to start a new one")]
+    BadJournal(String),
+
+    #[error("Nothing to undo")]
+    NothingToUndo,
+
+    #[error("Nothing to redo")]
+    NothingToRedo,
+
+    #[error("Cannot {verb} '{command}': {path} was changed since")]
+    UndoConflict {
+        verb: &'static str,
+        command: String,
+        path: String,
+    },
+
+    #[error("Not in a git work tree")]
+    NotGitWorkTree,
+
+    #[error("Git error: {0}")]
+    Git(String),
+
+    #[error("Failed to launch editor: {0}")]
+    EditorLaunch(std::io::Error),
+
+    #[error("Editor exited with {0}")]
+    EditorFailed(std::process::ExitStatus),
 }
 
 pub type Result<T> = std::result::Result<T, StoreError>;
 
+/// Options controlling how a store is opened
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    /// Fail on unparseable task files instead of skipping them
+    pub strict: bool,
+    /// Name of the command being run, recorded in the journal and used in
+    /// commit messages
+    pub command: Option<String>,
+    /// Commit changed task files to git (as if `auto_commit` were set)
+    pub commit: bool,
+    /// Read tasks from this git revision instead of the working tree
+    /// (read-only)
+    pub at: Option<String>,
+}
+
+/// What a task file looked like when it was loaded
+#[derive(Debug, Clone)]
+pub(crate) enum Fingerprint {
+    /// Full content, when the file was read from disk
+    Content(String),
+    /// Modification time and size, when the task came from the index
+    Stamp(FileStamp),
+}
+
 /// File-based task store with directory-based status
 pub struct Store {
     tasks_dir: PathBuf,
+    /// Where task files are read from
+    backend: Box<dyn Backend>,
     config: Config,
+    options: StoreOptions,
+    /// File state as last loaded, used to detect concurrent modification
+    pub(crate) loaded: RefCell<HashMap<PathBuf, Fingerprint>>,
+    /// Cache of parsed task files, loaded on first use
+    index: RefCell<Option<Index>>,
+    /// Task files skipped while listing because they failed to parse
+    broken: RefCell<Vec<(PathBuf, String)>>,
+}
+
+/// Contents of a task directory
+#[derive(Debug, Default)]
+struct DirListing {
+    exists: bool,
+    /// None if the backend can't stamp directories
+    stamp: Option<FileStamp>,
+    files: Vec<PathBuf>,
+    /// Archive subdirectories (named by year)
+    subdirs: Vec<PathBuf>,
+}
+
+/// Advisory lock on a single task, released when dropped
+pub struct TaskLock {
+    _file: File,
 }
 
 impl Store {
     /// Get the directory for a given status
-    fn status_dir(&self, status: Status) -> PathBuf {
-        let dir_name = match status {
-            Status::Open => "open",
-            Status::InProgress => "in-progress",
-            Status::Blocked => "blocked",
-            Status::Closed => "closed",
-            Status::Cancelled => "cancelled",
-        };
-        self.tasks_dir.join(dir_name)
+    pub(crate) fn status_dir(&self, status: &Status) -> PathBuf {
+        self.tasks_dir.join(status.name())
     }
 
     /// Get all status directories
     fn all_status_dirs(&self) -> Vec<PathBuf> {
-        vec![
-            self.status_dir(Status::Open),
-            self.status_dir(Status::InProgress),
-            self.status_dir(Status::Blocked),
-            self.status_dir(Status::Closed),
-            self.status_dir(Status::Cancelled),
-        ]
+        self.statuses()
+            .iter()
+            .map(|status| self.status_dir(status))
+            .collect()
+    }
+
+    /// Get the archive directory for a terminal status and year
+    pub(crate) fn archive_dir(&self, status: &Status, year: i32) -> PathBuf {
+        self.status_dir(status).join(format!("{:04}", year))
     }
 
     /// Derive status from a file path
     pub fn status_from_path(&self, path: &Path) -> Option<Status> {
-        let parent = path.parent()?;
+        let mut parent = path.parent()?;
+        // Archived tasks live in {status}/YYYY/
+        if is_archive_dir_name(parent.file_name()?) {
+            parent = parent.parent()?;
+        }
         let dir_name = parent.file_name()?.to_str()?;
-        match dir_name {
-            "open" => Some(Status::Open),
-            "in-progress" => Some(Status::InProgress),
-            "blocked" => Some(Status::Blocked),
-            "closed" => Some(Status::Closed),
-            "cancelled" => Some(Status::Cancelled),
-            _ => None,
+        match Status::from_name(dir_name) {
+            Status::Custom(name) => self
+                .custom_status(&name)
+                .map(|_| Status::Custom(name)),
+            status => Some(status),
+        }
+    }
+
+    /// All known statuses: the built-in ones followed by those from config
+    pub fn statuses(&self) -> Vec<Status> {
+        let mut statuses = Status::BUILTIN.to_vec();
+        statuses.extend(
+            self.config
+                .statuses
+                .iter()
+                .map(|s| Status::Custom(s.name.clone())),
+        );
+        statuses
+    }
+
+    /// Look up a status by name, failing for statuses that aren't configured
+    pub fn parse_status(&self, name: &str) -> Result<Status> {
+        let status = Status::from_name(&name.to_lowercase());
+        if self.statuses().contains(&status) {
+            Ok(status)
+        } else {
+            Err(StoreError::UnknownStatus(name.to_string()))
+        }
+    }
+
+    fn custom_status(&self, name: &str) -> Option<&StatusConfig> {
+        self.config.statuses.iter().find(|s| s.name == name)
+    }
+
+    /// Whether a status is final (resolves blockers, like closed)
+    pub fn is_terminal(&self, status: &Status) -> bool {
+        match status {
+            Status::Closed | Status::Cancelled => true,
+            Status::Custom(name) => self.custom_status(name).is_some_and(|s| s.terminal),
+            _ => false,
+        }
+    }
+
+    /// Whether tasks in a status count as active work
+    pub fn is_active(&self, status: &Status) -> bool {
+        match status {
+            Status::Open | Status::InProgress | Status::Blocked => true,
+            Status::Closed | Status::Cancelled => false,
+            Status::Custom(name) => self.custom_status(name).is_some_and(|s| s.is_active()),
         }
     }
 
+    /// Statuses a task may be moved to from `from`.
+    ///
+    /// Statuses listed under `transitions` in config.yaml use that list.
+    /// Other built-in statuses follow the default workflow, and custom
+    /// statuses without an entry may move anywhere.
+    pub fn allowed_transitions(&self, from: &Status) -> Vec<Status> {
+        if let Some(targets) = self.config.transitions.get(from.name()) {
+            return targets
+                .iter()
+                .filter_map(|name| self.parse_status(name).ok())
+                .collect();
+        }
+
+        let names: &[&str] = match from {
+            Status::Open => &["in-progress", "blocked", "closed", "cancelled"],
+            Status::InProgress => &["open", "blocked", "closed", "cancelled"],
+            Status::Blocked => &["closed", "cancelled"],
+            Status::Closed | Status::Cancelled => &["open"],
+            Status::Custom(_) => {
+                return self.statuses().into_iter().filter(|s| s != from).collect();
+            }
+        };
+        names.iter().map(|name| Status::from_name(name)).collect()
+    }
+
+    /// Fail unless the workflow allows moving from `from` to `to`
+    pub fn check_transition(&self, from: &Status, to: &Status) -> Result<()> {
+        let allowed = self.allowed_transitions(from);
+        if allowed.contains(to) {
+            return Ok(());
+        }
+
+        let allowed = if allowed.is_empty() {
+            "none".to_string()
+        } else {
+            allowed
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        Err(StoreError::InvalidTransition {
+            from: from.clone(),
+            to: to.clone(),
+            allowed,
+        })
+    }
+
     /// Open an existing store
     pub fn open(root: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(root, &StoreOptions::default())
+    }
+
+    /// Open an existing store with the given options
+    pub fn open_with(root: impl AsRef<Path>, options: &StoreOptions) -> Result<Self> {
         let root = root.as_ref().to_path_buf();
         let tasks_dir = root.join(TASKS_DIR);
 
@@ -83,9 +347,55 @@ impl Store {
             return Err(StoreError::NotInitialized);
         }
 
-        let config = Config::load(&tasks_dir);
+        let backend: Box<dyn Backend> = match &options.at {
+            Some(revision) => Box::new(
+                GitRevision::load(&tasks_dir, revision).map_err(StoreError::Git)?,
+            ),
+            None => Box::new(WorkTree),
+        };
+        // Config holds workflow rules, so a broken one is an error rather
+        // than silently falling back to the defaults
+        let config = match backend.read_to_string(&tasks_dir.join(CONFIG_FILE)) {
+            Ok(content) => Config::parse(&content).map_err(StoreError::InvalidConfig)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(e.into()),
+        };
+        for custom in &config.statuses {
+            let name = custom.name.as_str();
+            if !matches!(Status::from_name(name), Status::Custom(_))
+                || name.is_empty()
+                || name.starts_with('.')
+                || name.contains(['/', '\\'])
+            {
+                return Err(StoreError::InvalidConfig(format!(
+                    "invalid status name '{}'",
+                    name
+                )));
+            }
+        }
 
-        Ok(Store { tasks_dir, config })
+        let mut field_names = HashSet::new();
+        for field in &config.fields {
+            let duplicate = !field_names.insert(field.name.as_str());
+            let check = if duplicate {
+                Err(format!("'{}' is declared twice", field.name))
+            } else {
+                field.validate()
+            };
+            if let Err(e) = check {
+                return Err(StoreError::InvalidConfig(format!("invalid field: {}", e)));
+            }
+        }
+
+        Ok(Store {
+            tasks_dir,
+            backend,
+            config,
+            options: options.clone(),
+            loaded: RefCell::default(),
+            index: RefCell::default(),
+            broken: RefCell::default(),
+        })
     }
 
     /// Initialize a new store
@@ -99,7 +409,12 @@ impl Store {
 
         let store = Store {
             tasks_dir: tasks_dir.clone(),
+            backend: Box::new(WorkTree),
             config: Config::default(),
+            options: StoreOptions::default(),
+            loaded: RefCell::default(),
+            index: RefCell::default(),
+            broken: RefCell::default(),
         };
 
         // Create all status directories
@@ -109,20 +424,61 @@ impl Store {
         }
 
         // Create .gitattributes for better merging
+        fs::write(tasks_dir.join(".gitattributes"), UNION_ATTRIBUTES)?;
+
+        // Keep local lock files, the index cache and the journal out of
+        // version control
         fs::write(
-            tasks_dir.join(".gitattributes"),
-            "# Use union merge for task files - concatenates both sides\n\
-             # This helps with the append-only log section\n\
-             *.md merge=union\n",
+            tasks_dir.join(".gitignore"),
+            format!("{}/\n{}\n{}\n", LOCKS_DIR, INDEX_FILE, JOURNAL_FILE),
         )?;
 
         // Create default config
         let config = Config {
             default_author: Config::default().get_author(),
+            ..Config::default()
         };
         config.save(&tasks_dir)?;
 
-        Ok(Store { tasks_dir, config })
+        Ok(Store {
+            tasks_dir,
+            backend: Box::new(WorkTree),
+            config,
+            options: StoreOptions::default(),
+            loaded: RefCell::default(),
+            index: RefCell::default(),
+            broken: RefCell::default(),
+        })
+    }
+
+    /// Merge task files with `yatl merge-driver` instead of the union
+    /// strategy: update .gitattributes and register the driver in git config.
+    /// Returns false if not in a git work tree, in which case only
+    /// .gitattributes is updated.
+    pub fn install_merge_driver(&self) -> Result<bool> {
+        let path = self.tasks_dir.join(".gitattributes");
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let union_comments: Vec<&str> = UNION_ATTRIBUTES
+            .lines()
+            .filter(|line| line.starts_with('#'))
+            .collect();
+        let mut attributes: String = existing
+            .lines()
+            .filter(|line| !line.starts_with("*.md merge=") && !union_comments.contains(line))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        attributes.push_str(&format!(
+            "# Merge task files field by field with `yatl merge-driver`\n\
+             *.md merge={}\n",
+            git::MERGE_DRIVER
+        ));
+        atomic_write(&path, &attributes)?;
+
+        if !git::is_work_tree(&self.tasks_dir) {
+            return Ok(false);
+        }
+        git::register_merge_driver(&self.tasks_dir).map_err(StoreError::Git)?;
+        Ok(true)
     }
 
     /// Get the default author
@@ -130,43 +486,131 @@ impl Store {
         self.config.get_author()
     }
 
-    /// Create a new task (in open/ directory)
-    pub fn create(&self, task: &Task) -> Result<PathBuf> {
-        let filename = format!("{}.md", task.id());
-        let path = self.status_dir(Status::Open).join(&filename);
+    /// Custom fields declared in config.yaml
+    pub fn custom_fields(&self) -> &[FieldConfig] {
+        &self.config.fields
+    }
+
+    /// The custom field declared with this name
+    pub fn custom_field(&self, name: &str) -> Option<&FieldConfig> {
+        self.config.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Apply `name=value` assignments to a task's custom fields, checking each
+    /// value against the declared type. An empty value removes the field.
+    /// Returns whether anything changed.
+    pub fn set_fields(&self, task: &mut Task, assignments: &[String]) -> Result<bool> {
+        let mut changed = false;
+        for assignment in assignments {
+            let (name, input) = assignment.split_once('=').ok_or_else(|| {
+                StoreError::InvalidField(format!(
+                    "Invalid field assignment '{}': expected name=value",
+                    assignment
+                ))
+            })?;
+            let field = self.custom_field(name).ok_or_else(|| {
+                StoreError::InvalidField(format!(
+                    "Unknown field '{}': declare it under fields in config.yaml",
+                    name
+                ))
+            })?;
+            let key = Value::String(name.to_string());
+
+            if input.is_empty() {
+                changed |= task.frontmatter.extra.remove(&key).is_some();
+                continue;
+            }
+            let value = if field.field_type == FieldType::TaskRef {
+                let referenced = self.load(&self.find(input)?)?;
+                Value::String(referenced.id().full().to_string())
+            } else {
+                field
+                    .parse(input, dates::today())
+                    .map_err(|e| StoreError::InvalidField(format!("Invalid value for {}: {}", name, e)))?
+            };
+            if task.frontmatter.extra.get(&key) != Some(&value) {
+                task.frontmatter.extra.insert(key, value);
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Give a new task the default of every custom field that has one
+    pub fn apply_field_defaults(&self, task: &mut Task) {
+        for field in &self.config.fields {
+            if let Some(default) = &field.default {
+                let key = Value::String(field.name.clone());
+                if !task.frontmatter.extra.contains_key(&key) {
+                    task.frontmatter.extra.insert(key, default.clone());
+                }
+            }
+        }
+    }
+
+    /// The assignee to filter tasks by: the current author for `--mine`,
+    /// otherwise `assignee` if given
+    fn assignee_filter(&self, mine: bool, assignee: Option<&str>) -> Result<Option<String>> {
+        if mine {
+            self.get_author().map(Some).ok_or(StoreError::UnknownAuthor)
+        } else {
+            Ok(assignee.map(str::to_string))
+        }
+    }
 
-        let content = task.to_markdown();
-        fs::write(&path, content)?;
+    /// Keep only the tasks assigned to the current author (`--mine`) or to
+    /// `assignee` (`--assignee`), if either is given
+    pub fn retain_assigned(
+        &self,
+        tasks: &mut Vec<(PathBuf, Task)>,
+        mine: bool,
+        assignee: Option<&str>,
+    ) -> Result<()> {
+        if let Some(assignee) = self.assignee_filter(mine, assignee)? {
+            tasks.retain(|(_, task)| task.frontmatter.assignee.as_ref() == Some(&assignee));
+        }
+        Ok(())
+    }
 
+    /// Create a new task in the given status directory (normally open/)
+    #[allow(dead_code)]
+    pub fn create(&self, task: &Task, status: Status) -> Result<PathBuf> {
+        let mut tx = self.transaction();
+        let path = tx.create(task, status);
+        tx.commit()?;
         Ok(path)
     }
 
-    /// Find a task by ID or prefix across all directories
+    /// Find a task by ID or prefix across all directories, including archives
     pub fn find(&self, id_or_prefix: &str) -> Result<PathBuf> {
         let mut matches = vec![];
 
         for dir in self.all_status_dirs() {
-            if !dir.exists() {
-                continue;
-            }
-
-            for entry in fs::read_dir(&dir)? {
-                let entry = entry?;
-                let path = entry.path();
+            for path in self.task_files(&dir)?.files {
+                let filename = path.file_stem().unwrap_or_default().to_string_lossy();
 
-                if path.extension().is_some_and(|e| e == "md") {
-                    let filename = path.file_stem().unwrap_or_default().to_string_lossy();
+                // Check exact match
+                if filename == id_or_prefix {
+                    return Ok(path);
+                }
 
-                    // Check exact match
-                    if filename == id_or_prefix {
-                        return Ok(path);
-                    }
+                // Check prefix match
+                let id = TaskId::from_string(filename.to_string());
+                if id.matches_prefix(id_or_prefix) {
+                    matches.push(path);
+                }
+            }
+        }
 
-                    // Check prefix match
-                    let id = TaskId::from_string(filename.to_string());
-                    if id.matches_prefix(id_or_prefix) {
-                        matches.push(path);
-                    }
+        // Only search the archives when the live directories had no exact match
+        for dir in self.archive_dirs()? {
+            for path in self.task_files(&dir)?.files {
+                let filename = path.file_stem().unwrap_or_default().to_string_lossy();
+                if filename == id_or_prefix {
+                    return Ok(path);
+                }
+                if TaskId::from_string(filename.to_string()).matches_prefix(id_or_prefix) {
+                    matches.push(path);
                 }
             }
         }
@@ -180,42 +624,555 @@ impl Store {
 
     /// Load a task from a path
     pub fn load(&self, path: &Path) -> Result<Task> {
-        let content = fs::read_to_string(path)?;
-        Task::parse(&content).map_err(StoreError::Parse)
+        let content = self.read_raw(path)?;
+        Task::parse(&content).map_err(|message| StoreError::InvalidTask {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// Read a task file without parsing it, remembering its content so a
+    /// later save can detect concurrent modification
+    pub fn read_raw(&self, path: &Path) -> Result<String> {
+        let content = self.backend.read_to_string(path)?;
+        self.loaded
+            .borrow_mut()
+            .insert(path.to_path_buf(), Fingerprint::Content(content.clone()));
+        Ok(content)
+    }
+
+    /// All task files in every status and archive directory, parseable or not
+    pub fn task_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for dir in self.all_status_dirs().into_iter().chain(self.archive_dirs()?) {
+            paths.extend(self.task_files(&dir)?.files);
+        }
+        Ok(paths)
+    }
+
+    /// IDs of all archived tasks, taken from file names without parsing
+    pub fn archived_ids(&self) -> Result<HashSet<TaskId>> {
+        let mut ids = HashSet::new();
+        for dir in self.archive_dirs()? {
+            for path in self.task_files(&dir)?.files {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                ids.insert(TaskId::from_string(stem.to_string()));
+            }
+        }
+        Ok(ids)
+    }
+
+    /// Archive directories ({status}/YYYY) under the terminal statuses
+    fn archive_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs = vec![];
+        for status in self.statuses() {
+            if self.is_terminal(&status) {
+                dirs.extend(self.task_files(&self.status_dir(&status))?.subdirs);
+            }
+        }
+        Ok(dirs)
     }
 
     /// Save a task back to its file
     pub fn save(&self, task: &Task, path: &Path) -> Result<()> {
-        let content = task.to_markdown();
-        fs::write(path, content)?;
+        let mut tx = self.transaction();
+        tx.save(task, path);
+        tx.commit()
+    }
+
+    /// Save a task and move it to a new status directory as one locked operation
+    pub fn save_to_status(&self, task: &Task, path: &Path, status: Status) -> Result<PathBuf> {
+        let mut tx = self.transaction();
+        let new_path = tx.save_to_status(task, path, status)?;
+        tx.commit()?;
+        Ok(new_path)
+    }
+
+    /// Acquire an exclusive advisory lock on a task, blocking until available
+    pub fn lock(&self, id: &TaskId) -> Result<TaskLock> {
+        self.lock_file(&format!("{}.lock", id))
+    }
+
+    /// Acquire the lock guarding the journal
+    pub fn lock_journal(&self) -> Result<TaskLock> {
+        self.lock_file(&format!("{}.lock", JOURNAL_FILE))
+    }
+
+    /// Fail with `ReadOnly` if the store reads a past revision (`--at`)
+    pub fn check_writable(&self) -> Result<()> {
+        match self.backend.revision() {
+            Some(revision) => Err(StoreError::ReadOnly(revision.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn lock_file(&self, name: &str) -> Result<TaskLock> {
+        // Every change takes a lock first, so this keeps `--at` read-only
+        self.check_writable()?;
+
+        let locks_dir = self.tasks_dir.join(LOCKS_DIR);
+        if !locks_dir.exists() {
+            fs::create_dir_all(&locks_dir)?;
+            self.ensure_ignored(&format!("{}/", LOCKS_DIR))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(locks_dir.join(name))?;
+        file.lock()?;
+
+        Ok(TaskLock { _file: file })
+    }
+
+    /// Load the journal (callers should hold `lock_journal`)
+    pub fn load_journal(&self) -> Result<Journal> {
+        Journal::load(&self.tasks_dir).map_err(StoreError::BadJournal)
+    }
+
+    /// Write the journal back (callers should hold `lock_journal`)
+    pub fn save_journal(&self, journal: &Journal) -> Result<()> {
+        atomic_write(&self.tasks_dir.join(JOURNAL_FILE), &journal.to_json())?;
+        self.ensure_ignored(JOURNAL_FILE)
+    }
+
+    /// Record a committed change in the journal so it can be undone
+    pub(crate) fn record_journal(&self, files: Vec<FileChange>) -> Result<()> {
+        let _lock = self.lock_journal()?;
+        let mut journal = self.load_journal()?;
+        journal.record(JournalEntry {
+            timestamp: chrono::Utc::now(),
+            command: self
+                .options
+                .command
+                .clone()
+                .unwrap_or_else(|| "yatl".to_string()),
+            files,
+        });
+        self.save_journal(&journal)
+    }
+
+    /// Commit changed task files to git when auto-commit is enabled
+    pub(crate) fn commit_to_git(&self, changes: &[FileChange]) {
+        if !self.options.commit && !self.config.auto_commit {
+            return;
+        }
+        if !git::is_work_tree(&self.tasks_dir) {
+            if self.options.commit {
+                eprintln!(
+                    "{} Not in a git work tree, nothing committed",
+                    "warning:".yellow()
+                );
+            }
+            return;
+        }
+
+        // One (id, title) per task, in the order the command touched them
+        let mut tasks: Vec<(String, String)> = vec![];
+        for change in changes {
+            let id = Path::new(&change.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if tasks.iter().any(|(seen, _)| *seen == id) {
+                continue;
+            }
+            let title = change
+                .after
+                .as_ref()
+                .or(change.before.as_ref())
+                .and_then(|content| Task::parse(content).ok())
+                .map_or_else(|| id.clone(), |task| task.title().to_string());
+            tasks.push((id, title));
+        }
+
+        let paths: Vec<PathBuf> = changes
+            .iter()
+            .map(|change| self.tasks_dir.join(&change.path))
+            .collect();
+        let message = git::commit_message(self.options.command.as_deref(), &tasks);
+        if let Err(e) = git::commit_paths(&self.tasks_dir, &paths, &message) {
+            eprintln!("{} Could not commit to git: {}", "warning:".yellow(), e);
+        }
+    }
+
+    /// Undo the last `count` journaled operations, newest first.
+    /// Returns the entries that were undone.
+    pub fn undo(&self, count: usize) -> Result<Vec<JournalEntry>> {
+        self.replay(count, true)
+    }
+
+    /// Redo the last `count` undone operations.
+    /// Returns the entries that were redone.
+    pub fn redo(&self, count: usize) -> Result<Vec<JournalEntry>> {
+        self.replay(count, false)
+    }
+
+    /// Move entries from one journal stack to the other, putting their files
+    /// into the state on the other side of the recorded change
+    fn replay(&self, count: usize, undo: bool) -> Result<Vec<JournalEntry>> {
+        let _lock = self.lock_journal()?;
+        let mut journal = self.load_journal()?;
+        let mut replayed = vec![];
+        let mut failure = None;
+
+        for _ in 0..count {
+            let (from, to) = if undo {
+                (&mut journal.done, &mut journal.undone)
+            } else {
+                (&mut journal.undone, &mut journal.done)
+            };
+            let Some(entry) = from.pop() else {
+                break;
+            };
+            match self.replay_entry(&entry, undo) {
+                Ok(applied) => {
+                    // The next entry for each file expects it as recorded,
+                    // not with the log entry the replay just added
+                    for (original, written) in entry.files.iter().zip(&applied.files) {
+                        let next = from.iter_mut().rev().find_map(|e| {
+                            e.files.iter_mut().find(|f| f.path == original.path)
+                        });
+                        if let Some(next) = next {
+                            let state = if undo { &mut next.after } else { &mut next.before };
+                            let written = if undo { &written.before } else { &written.after };
+                            *state = written.clone();
+                        }
+                    }
+                    to.push(applied.clone());
+                    replayed.push(applied);
+                }
+                Err(e) => {
+                    from.push(entry);
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+
+        if !replayed.is_empty() {
+            self.save_journal(&journal)?;
+        }
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        if replayed.is_empty() {
+            return Err(if undo {
+                StoreError::NothingToUndo
+            } else {
+                StoreError::NothingToRedo
+            });
+        }
+        Ok(replayed)
+    }
+
+    /// Apply one side of a journal entry. Restored tasks get a log entry
+    /// explaining the reversal; the returned entry records the content that
+    /// was actually written so it can be replayed back later.
+    fn replay_entry(&self, entry: &JournalEntry, undo: bool) -> Result<JournalEntry> {
+        let (verb, action) = if undo {
+            ("undo", "Undid")
+        } else {
+            ("redo", "Redid")
+        };
+        let message = format!(
+            "{} '{}' from {}.",
+            action,
+            entry.command,
+            entry.timestamp.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+        let author = self.get_author();
+
+        let mut applied = entry.clone();
+        let mut tx = self.transaction();
+        tx.skip_journal();
+        let mut writes = vec![];
+
+        for change in &mut applied.files {
+            let path = self.tasks_dir.join(&change.path);
+            let (expected, target) = if undo {
+                (&change.after, &mut change.before)
+            } else {
+                (&change.before, &mut change.after)
+            };
+
+            let current = if path.exists() {
+                Some(self.read_raw(&path)?)
+            } else {
+                None
+            };
+            if current != *expected {
+                return Err(StoreError::UndoConflict {
+                    verb,
+                    command: entry.command.clone(),
+                    path: change.path.clone(),
+                });
+            }
+
+            match target {
+                // Removals go first so a task moved back doesn't briefly
+                // exist twice
+                None => tx.restore(&path, None)?,
+                Some(content) => {
+                    let current_format = crate::migrate::file_version(content)
+                        .is_ok_and(|version| version == TASK_FORMAT_VERSION);
+                    if let Some(mut task) = Task::parse(content).ok().filter(|_| current_format)
+                    {
+                        task.add_log_entry(
+                            LogKind::Undo,
+                            &[("command", &entry.command)],
+                            &message,
+                            author.as_deref(),
+                        );
+                        *content = task.to_markdown();
+                    }
+                    writes.push((path, content.clone()));
+                }
+            }
+        }
+        for (path, content) in writes {
+            tx.restore(&path, Some(content))?;
+        }
+        tx.commit()?;
+
+        Ok(applied)
+    }
+
+    /// Path of a file relative to .tasks, as recorded in the journal
+    pub fn relative_path(&self, path: &Path) -> String {
+        self.index_key(path)
+    }
+
+    /// Fail if the file at `path` no longer matches what was loaded from it
+    pub(crate) fn check_unchanged(&self, path: &Path) -> Result<()> {
+        let loaded = self.loaded.borrow();
+        let Some(expected) = loaded.get(path) else {
+            return Ok(());
+        };
+
+        let unchanged = match expected {
+            Fingerprint::Content(content) => fs::read_to_string(path).map(|c| &c == content),
+            Fingerprint::Stamp(stamp) => FileStamp::of(path).map(|s| &s == stamp),
+        };
+
+        match unchanged {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(StoreError::Conflict(path.to_path_buf())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StoreError::Conflict(path.to_path_buf()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Add an entry to .tasks/.gitignore if it is not already present
+    fn ensure_ignored(&self, entry: &str) -> Result<()> {
+        let path = self.tasks_dir.join(".gitignore");
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        if existing.lines().any(|line| line.trim() == entry) {
+            return Ok(());
+        }
+
+        let mut content = existing;
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(entry);
+        content.push('\n');
+        atomic_write(&path, &content)?;
         Ok(())
     }
 
+    /// Run a closure against the index, loading it on first use
+    fn with_index<R>(&self, f: impl FnOnce(&mut Index) -> R) -> R {
+        let mut index = self.index.borrow_mut();
+        f(index.get_or_insert_with(|| Index::load(&self.tasks_dir)))
+    }
+
+    /// Key for a path in the index (relative to .tasks, `/`-separated)
+    fn index_key(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.tasks_dir).unwrap_or(path);
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// List the task files and archive subdirectories of a directory,
+    /// using the index when the directory hasn't changed since it was last
+    /// listed
+    fn task_files(&self, dir: &Path) -> Result<DirListing> {
+        let stamp = match self.backend.stamp(dir) {
+            Ok(stamp) => stamp,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(DirListing::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let key = self.index_key(dir);
+        if let Some(stamp) = stamp {
+            if let Some((files, subdirs)) =
+                self.with_index(|index| index.dir_listing(&key, stamp))
+            {
+                return Ok(DirListing {
+                    exists: true,
+                    stamp: Some(stamp),
+                    files: files.iter().map(|n| dir.join(n)).collect(),
+                    subdirs: subdirs.iter().map(|n| dir.join(n)).collect(),
+                });
+            }
+        }
+
+        let mut listing = DirListing {
+            exists: true,
+            stamp,
+            ..DirListing::default()
+        };
+        for entry in self.backend.read_dir(dir)? {
+            let path = entry.path;
+            if path.extension().is_some_and(|e| e == "md") {
+                listing.files.push(path);
+            } else if entry.is_dir
+                && path.file_name().is_some_and(is_archive_dir_name)
+            {
+                listing.subdirs.push(path);
+            }
+        }
+        listing.subdirs.sort();
+        Ok(listing)
+    }
+
     /// List all tasks in a directory
     fn list_dir(&self, dir: &Path) -> Result<Vec<(PathBuf, Task)>> {
         let mut tasks = vec![];
 
-        if !dir.exists() {
+        let DirListing {
+            exists: true,
+            stamp: dir_stamp,
+            files,
+            subdirs,
+        } = self.task_files(dir)?
+        else {
             return Ok(tasks);
-        }
+        };
 
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+        let mut names = vec![];
+        for path in files {
+            let stamp = match self.backend.stamp(&path) {
+                Ok(Some(stamp)) => stamp,
+                // Nothing to check the index against
+                Ok(None) => {
+                    match self.load(&path) {
+                        Ok(task) => tasks.push((path, task)),
+                        Err(e) if self.options.strict => return Err(e),
+                        Err(e) => self.record_broken(path, e),
+                    }
+                    continue;
+                }
+                // Removed since the directory was listed
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let key = self.index_key(&path);
+            names.push(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+            );
 
-            if path.extension().is_some_and(|e| e == "md") {
-                if let Ok(task) = self.load(&path) {
-                    tasks.push((path, task));
+            let loaded = match self.with_index(|index| index.get(&key, stamp)) {
+                Some(Ok(task)) => {
+                    self.loaded
+                        .borrow_mut()
+                        .insert(path.clone(), Fingerprint::Stamp(stamp));
+                    Ok(task)
+                }
+                Some(Err(message)) => Err(StoreError::InvalidTask {
+                    path: path.clone(),
+                    message,
+                }),
+                None => {
+                    let loaded = self.load(&path);
+                    self.with_index(|index| match &loaded {
+                        Ok(task) => index.insert_task(&key, stamp, task),
+                        Err(StoreError::InvalidTask { message, .. }) => {
+                            index.insert_error(&key, stamp, message)
+                        }
+                        Err(_) => {}
+                    });
+                    loaded
                 }
+            };
+
+            match loaded {
+                Ok(task) => tasks.push((path, task)),
+                Err(e) if self.options.strict => return Err(e),
+                Err(e) => self.record_broken(path, e),
             }
         }
 
+        let subdirs: Vec<String> = subdirs
+            .iter()
+            .filter_map(|d| d.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .collect();
+        if let Some(dir_stamp) = dir_stamp {
+            let key = self.index_key(dir);
+            self.with_index(|index| index.set_dir_listing(&key, dir_stamp, &names, &subdirs));
+        }
+
         Ok(tasks)
     }
 
+    /// Write the index back to disk if it changed
+    fn save_index(&self) -> Result<()> {
+        let json = match self.index.borrow().as_ref() {
+            Some(index) => index.to_json_if_dirty(),
+            None => None,
+        };
+        if let Some(json) = json {
+            atomic_write(&self.tasks_dir.join(INDEX_FILE), &json)?;
+            self.ensure_ignored(INDEX_FILE)?;
+        }
+        Ok(())
+    }
+
+    /// Remember a task file that could not be loaded while listing
+    fn record_broken(&self, path: PathBuf, error: StoreError) {
+        let message = match error {
+            StoreError::InvalidTask { message, .. } => message,
+            other => other.to_string(),
+        };
+        let mut broken = self.broken.borrow_mut();
+        if !broken.iter().any(|(p, _)| *p == path) {
+            broken.push((path, message));
+        }
+    }
+
+    /// Task files skipped so far because they could not be loaded
+    pub fn broken_files(&self) -> Vec<(PathBuf, String)> {
+        self.broken.borrow().clone()
+    }
+
+    /// Print a warning for each task file that was skipped while listing
+    pub fn warn_broken(&self) {
+        for (path, message) in self.broken.borrow().iter() {
+            eprintln!(
+                "{} Skipped unparseable task file {}: {}",
+                "warning:".yellow(),
+                path.display(),
+                message
+            );
+        }
+    }
+
     /// List tasks by status
     pub fn list_by_status(&self, status: Status) -> Result<Vec<(PathBuf, Task)>> {
-        self.list_dir(&self.status_dir(status))
+        self.list_dir(&self.status_dir(&status))
     }
 
     /// List all tasks across all directories
@@ -227,41 +1184,56 @@ impl Store {
         Ok(tasks)
     }
 
-    /// List all non-terminal tasks (open, in-progress, blocked)
-    pub fn list_active(&self) -> Result<Vec<(PathBuf, Task)>> {
+    /// List archived tasks
+    pub fn list_archived(&self) -> Result<Vec<(PathBuf, Task)>> {
         let mut tasks = vec![];
-        tasks.extend(self.list_by_status(Status::Open)?);
-        tasks.extend(self.list_by_status(Status::InProgress)?);
-        tasks.extend(self.list_by_status(Status::Blocked)?);
+        for dir in self.archive_dirs()? {
+            tasks.extend(self.list_dir(&dir)?);
+        }
         Ok(tasks)
     }
 
-    /// Move a task to a new status directory
-    pub fn move_to_status(&self, path: &Path, status: Status) -> Result<PathBuf> {
-        let filename = path
-            .file_name()
-            .ok_or_else(|| StoreError::Parse("Invalid path".to_string()))?;
-        let new_path = self.status_dir(status).join(filename);
-        fs::rename(path, &new_path)?;
-        Ok(new_path)
+    /// List all tasks, including archived ones
+    pub fn list_all_with_archived(&self) -> Result<Vec<(PathBuf, Task)>> {
+        let mut tasks = self.list_all()?;
+        tasks.extend(self.list_archived()?);
+        Ok(tasks)
     }
 
-    /// Get ready tasks (in open/ directory with no unresolved blockers)
+    /// List all tasks in active statuses (open, in-progress, blocked and
+    /// any custom status configured as active)
+    pub fn list_active(&self) -> Result<Vec<(PathBuf, Task)>> {
+        let mut tasks = vec![];
+        for status in self.statuses() {
+            if self.is_active(&status) {
+                tasks.extend(self.list_by_status(status)?);
+            }
+        }
+        Ok(tasks)
+    }
+
+    /// Get ready tasks (in open/ directory with no unresolved blockers and
+    /// not deferred)
     pub fn list_ready(&self) -> Result<Vec<(PathBuf, Task)>> {
         let open_tasks = self.list_by_status(Status::Open)?;
         let all_tasks = self.list_all()?;
+        // Archived tasks are always resolved
+        let archived = self.archived_ids()?;
+        let today = dates::today();
 
         let ready: Vec<(PathBuf, Task)> = open_tasks
             .into_iter()
+            .filter(|(_, task)| !task.is_deferred(today))
             .filter(|(_, task)| {
-                // Check if all blockers are resolved (in closed or cancelled)
+                // Check if all blockers are resolved (in a terminal status)
                 task.frontmatter.blocked_by.iter().all(|blocker_id| {
-                    all_tasks.iter().any(|(blocker_path, t)| {
-                        t.id() == blocker_id && {
-                            let status = self.status_from_path(blocker_path);
-                            matches!(status, Some(Status::Closed) | Some(Status::Cancelled))
-                        }
-                    })
+                    archived.contains(blocker_id)
+                        || all_tasks.iter().any(|(blocker_path, t)| {
+                            t.id() == blocker_id
+                                && self
+                                    .status_from_path(blocker_path)
+                                    .is_some_and(|status| self.is_terminal(&status))
+                        })
                 })
             })
             .collect();
@@ -269,11 +1241,84 @@ impl Store {
         Ok(ready)
     }
 
-    /// Check and unblock tasks that were waiting on a now-resolved blocker
-    /// Returns the paths of tasks that were unblocked
-    pub fn unblock_waiting_tasks(&self, closed_task_id: &TaskId) -> Result<Vec<PathBuf>> {
+    /// How many of a task's subtasks are finished (in a terminal status) out
+    /// of how many it has, or None if it has none
+    pub fn subtask_progress(&self, task: &Task) -> Result<Option<(usize, usize)>> {
+        let children = &task.frontmatter.children;
+        if children.is_empty() {
+            return Ok(None);
+        }
+        let mut done = 0;
+        for child in children {
+            if self.is_finished(child)? {
+                done += 1;
+            }
+        }
+        Ok(Some((done, children.len())))
+    }
+
+    /// Fail if `task` has unfinished subtasks and the config requires them
+    /// to be closed first
+    pub fn check_children_closed(&self, task: &Task) -> Result<()> {
+        if !self.config.require_children_closed {
+            return Ok(());
+        }
+        match self.subtask_progress(task)? {
+            Some((done, total)) if done < total => Err(StoreError::UnfinishedChildren {
+                id: task.id().clone(),
+                count: total - done,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply the `unchecked_on_close` rule before a task is closed
+    pub fn check_checklist_done(&self, task: &Task) -> Result<()> {
+        let Some((checked, total)) = task.checklist_progress() else {
+            return Ok(());
+        };
+        let count = total - checked;
+        if count == 0 {
+            return Ok(());
+        }
+        match self.config.unchecked_on_close {
+            UncheckedOnClose::Allow => Ok(()),
+            UncheckedOnClose::Warn => {
+                eprintln!(
+                    "{} {} checklist item(s) still unchecked",
+                    "warning:".yellow(),
+                    count
+                );
+                Ok(())
+            }
+            UncheckedOnClose::Refuse => Err(StoreError::UncheckedItems {
+                id: task.id().clone(),
+                count,
+            }),
+        }
+    }
+
+    /// Whether a task is in a terminal status (missing tasks are not)
+    fn is_finished(&self, id: &TaskId) -> Result<bool> {
+        match self.find(id.full()) {
+            Ok(path) => Ok(self
+                .status_from_path(&path)
+                .is_some_and(|status| self.is_terminal(&status))),
+            Err(StoreError::TaskNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stage unblocking of tasks that were waiting on a now-resolved blocker
+    /// Returns the paths the unblocked tasks will have once `tx` is committed
+    pub fn unblock_waiting_tasks(
+        &self,
+        tx: &mut Transaction<'_>,
+        closed_task_id: &TaskId,
+    ) -> Result<Vec<PathBuf>> {
         let blocked_tasks = self.list_by_status(Status::Blocked)?;
         let all_tasks = self.list_all()?;
+        let archived = self.archived_ids()?;
         let mut unblocked = vec![];
 
         for (path, task) in blocked_tasks {
@@ -283,18 +1328,23 @@ impl Store {
             }
 
             // Check if ALL blockers are now resolved
+            // (the closing task itself may not have been moved yet)
             let all_resolved = task.frontmatter.blocked_by.iter().all(|blocker_id| {
-                all_tasks.iter().any(|(blocker_path, t)| {
-                    t.id() == blocker_id && {
-                        let status = self.status_from_path(blocker_path);
-                        matches!(status, Some(Status::Closed) | Some(Status::Cancelled))
-                    }
-                })
+                blocker_id == closed_task_id
+                    || archived.contains(blocker_id)
+                    || all_tasks.iter().any(|(blocker_path, t)| {
+                        t.id() == blocker_id && {
+                            let status = tx
+                                .staged_status(blocker_id)
+                                .or_else(|| self.status_from_path(blocker_path));
+                            status.is_some_and(|status| self.is_terminal(&status))
+                        }
+                    })
             });
 
             if all_resolved {
                 // Move back to open
-                let new_path = self.move_to_status(&path, Status::Open)?;
+                let new_path = tx.move_to_status(&path, Status::Open)?;
                 unblocked.push(new_path);
             }
         }
@@ -307,3 +1357,531 @@ impl Store {
         &self.tasks_dir
     }
 }
+
+impl Drop for Store {
+    fn drop(&mut self) {
+        // The index is only a cache; failing to persist it just means the
+        // next command does more parsing
+        let _ = self.save_index();
+    }
+}
+
+/// Whether a directory name is an archive year (YYYY)
+fn is_archive_dir_name(name: &OsStr) -> bool {
+    name.to_str()
+        .is_some_and(|n| n.len() == 4 && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Write a file by writing a sibling temp file and renaming it into place,
+/// so readers never observe a partially written task
+pub(crate) fn atomic_write(path: &Path, content: &str) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = dir.join(format!(".{}.tmp-{}", filename, std::process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{run_git, temp_store};
+
+    #[test]
+    fn test_save_detects_concurrent_modification() {
+        let (root, store) = temp_store("conflict");
+        let task = Task::new("Test task", Some("brian".into()));
+        let path = store.create(&task, Status::Open).unwrap();
+
+        let mut loaded = store.load(&path).unwrap();
+
+        // Another process rewrites the file behind our back
+        let other = Store::open(&root).unwrap();
+        let mut theirs = other.load(&path).unwrap();
+        theirs.add_log("Their change", None);
+        other.save(&theirs, &path).unwrap();
+
+        loaded.add_log("Our change", None);
+        assert!(matches!(
+            store.save(&loaded, &path),
+            Err(StoreError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_move_detects_task_moved_elsewhere() {
+        let (root, store) = temp_store("moved");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let loaded = store.load(&path).unwrap();
+
+        let other = Store::open(&root).unwrap();
+        let theirs = other.load(&path).unwrap();
+        other
+            .save_to_status(&theirs, &path, Status::Closed)
+            .unwrap();
+
+        assert!(matches!(
+            store.save_to_status(&loaded, &path, Status::InProgress),
+            Err(StoreError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_leaves_no_temp_files() {
+        let (_root, store) = temp_store("atomic");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let task = store.load(&path).unwrap();
+        let new_path = store
+            .save_to_status(&task, &path, Status::InProgress)
+            .unwrap();
+
+        assert!(new_path.exists());
+        assert!(!path.exists());
+        for dir in store.all_status_dirs() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let name = entry.unwrap().file_name();
+                assert!(!name.to_string_lossy().contains(".tmp-"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_on_failure() {
+        let (_root, store) = temp_store("rollback");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let original = fs::read_to_string(&path).unwrap();
+
+        let mut task = store.load(&path).unwrap();
+        task.add_log("Closed.", None);
+
+        // The second step fails because its target directory is not a directory
+        let cancelled_dir = store.status_dir(&Status::Cancelled);
+        fs::remove_dir_all(&cancelled_dir).unwrap();
+        fs::write(&cancelled_dir, "").unwrap();
+        let mut tx = store.transaction();
+        let closed_path = tx.save_to_status(&task, &path, Status::Closed).unwrap();
+        tx.create(&Task::new("Other task", None), Status::Cancelled);
+        assert!(tx.commit().is_err());
+
+        assert!(!closed_path.exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_transaction_refuses_write_after_move() {
+        let (_root, store) = temp_store("write-after-move");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+
+        let mut tx = store.transaction();
+        let blocked_path = tx.save_to_status(&task, &path, Status::Blocked).unwrap();
+        tx.save(&task, &path);
+        assert!(matches!(tx.commit(), Err(StoreError::NotAllowed(_))));
+
+        assert!(path.exists());
+        assert!(!blocked_path.exists());
+    }
+
+    #[test]
+    fn test_transaction_refuses_unreadable_content() {
+        let (_root, store) = temp_store("unreadable");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let original = fs::read_to_string(&path).unwrap();
+
+        let mut task = store.load(&path).unwrap();
+        task.body = "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> main".to_string();
+        let mut tx = store.transaction();
+        tx.save(&task, &path);
+        assert!(matches!(tx.commit(), Err(StoreError::Unreadable { .. })));
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_list_reports_unparseable_files() {
+        let (root, store) = temp_store("broken");
+        store
+            .create(&Task::new("Good task", None), Status::Open)
+            .unwrap();
+        let broken_path = store.status_dir(&Status::Open).join("badbadba.md");
+        fs::write(&broken_path, "---\ntitle: [unterminated\n---\n").unwrap();
+
+        let tasks = store.list_all().unwrap();
+        assert_eq!(tasks.len(), 1);
+        let broken = store.broken_files();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].0, broken_path);
+
+        let strict = Store::open_with(
+            &root,
+            &StoreOptions {
+                strict: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            strict.list_all(),
+            Err(StoreError::InvalidTask { .. })
+        ));
+    }
+
+    #[test]
+    fn test_index_picks_up_changes_and_survives_corruption() {
+        let (root, store) = temp_store("index");
+        let path = store
+            .create(&Task::new("Original title", None), Status::Open)
+            .unwrap();
+        assert_eq!(store.list_all().unwrap()[0].1.title(), "Original title");
+        drop(store);
+        assert!(root.join(TASKS_DIR).join(INDEX_FILE).exists());
+
+        // Edit the file in place, as an editor would
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("Original title", "Edited title")).unwrap();
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap()[0].1.title(), "Edited title");
+        drop(store);
+
+        // A same-size edit that keeps the file's mtime is still noticed when
+        // the index was written in the same tick as the file (racy clean)
+        let stamp = fs::metadata(&path).unwrap().modified().unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("Edited title", "Edited twice")).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(stamp)
+            .unwrap();
+        File::options()
+            .write(true)
+            .open(root.join(TASKS_DIR).join(INDEX_FILE))
+            .unwrap()
+            .set_modified(stamp)
+            .unwrap();
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap()[0].1.title(), "Edited twice");
+        drop(store);
+
+        // A garbage index is discarded and rebuilt
+        fs::write(root.join(TASKS_DIR).join(INDEX_FILE), "not json").unwrap();
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.list_all().unwrap().len(), 1);
+        assert!(store
+            .find(&path.file_stem().unwrap().to_string_lossy())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_retain_assigned() {
+        let (root, store) = temp_store("assigned");
+        drop(store);
+        fs::write(
+            root.join(TASKS_DIR).join("config.yaml"),
+            "default_author: alice\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+        for (title, assignee) in [
+            ("Mine", Some("alice")),
+            ("Bob's", Some("bob")),
+            ("Nobody's", None),
+        ] {
+            let mut task = Task::new(title, None);
+            task.frontmatter.assignee = assignee.map(str::to_string);
+            store.create(&task, Status::Open).unwrap();
+        }
+        let titles = |mine: bool, assignee: Option<&str>| {
+            let mut tasks = store.list_all().unwrap();
+            store.retain_assigned(&mut tasks, mine, assignee).unwrap();
+            let mut titles: Vec<String> =
+                tasks.iter().map(|(_, t)| t.title().to_string()).collect();
+            titles.sort();
+            titles
+        };
+
+        assert_eq!(titles(true, None), ["Mine"]);
+        assert_eq!(titles(false, Some("bob")), ["Bob's"]);
+        assert!(titles(false, Some("carol")).is_empty());
+        assert_eq!(titles(false, None), ["Bob's", "Mine", "Nobody's"]);
+    }
+
+    #[test]
+    fn test_custom_statuses_and_transitions() {
+        let (root, store) = temp_store("workflow");
+        drop(store);
+        fs::write(
+            root.join(TASKS_DIR).join("config.yaml"),
+            "statuses:\n\
+             \x20 - name: review\n\
+             \x20 - name: wontfix\n\
+             \x20   terminal: true\n\
+             transitions:\n\
+             \x20 in-progress: [review, open]\n\
+             \x20 review: [closed, in-progress]\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+
+        let review = store.parse_status("review").unwrap();
+        let wontfix = store.parse_status("wontfix").unwrap();
+        assert!(store.parse_status("qa").is_err());
+        assert!(store.is_active(&review) && !store.is_terminal(&review));
+        assert!(store.is_terminal(&wontfix) && !store.is_active(&wontfix));
+
+        // Configured lists replace the defaults; others keep them
+        assert!(store
+            .check_transition(&Status::InProgress, &review)
+            .is_ok());
+        assert!(store
+            .check_transition(&Status::InProgress, &Status::Closed)
+            .is_err());
+        assert!(store.check_transition(&review, &Status::Open).is_err());
+        assert!(store
+            .check_transition(&Status::Open, &Status::InProgress)
+            .is_ok());
+
+        // Custom status directories are created on first use
+        let path = store
+            .create(&Task::new("Needs review", None), Status::Open)
+            .unwrap();
+        let task = store.load(&path).unwrap();
+        let moved = store.save_to_status(&task, &path, review.clone()).unwrap();
+        assert_eq!(store.status_from_path(&moved), Some(review));
+        assert_eq!(store.list_active().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_config_is_an_error() {
+        let (root, store) = temp_store("bad-config");
+        drop(store);
+        let config = root.join(TASKS_DIR).join("config.yaml");
+
+        fs::write(&config, "statuses:\n  - name: review\nunchecked_on_close: reject\n").unwrap();
+        match Store::open(&root) {
+            Err(StoreError::InvalidConfig(message)) => {
+                assert!(message.contains("unchecked_on_close"), "{}", message)
+            }
+            other => panic!("expected an invalid config error, got {:?}", other.err()),
+        }
+
+        // An empty config means the defaults
+        fs::write(&config, "# nothing yet\n").unwrap();
+        assert!(Store::open(&root).is_ok());
+    }
+
+    #[test]
+    fn test_refuses_to_write_newer_format() {
+        let (_root, store) = temp_store("newer-format");
+        let mut task = Task::new("From the future", None);
+        task.frontmatter.yatl_version = TASK_FORMAT_VERSION + 1;
+
+        assert!(matches!(
+            store.create(&task, Status::Open),
+            Err(StoreError::UnsupportedVersion { .. })
+        ));
+        assert!(store.list_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_archived_tasks_are_found_but_not_listed() {
+        let (root, store) = temp_store("archive");
+        let blocker = Task::new("Old blocker", None);
+        let blocker_path = store.create(&blocker, Status::Closed).unwrap();
+        let mut waiting = Task::new("Waiting task", None);
+        waiting.frontmatter.blocked_by.push(blocker.id().clone());
+        store.create(&waiting, Status::Open).unwrap();
+
+        let mut tx = store.transaction();
+        let archived_path = tx.archive(&blocker_path, 2020).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(
+            archived_path,
+            store
+                .tasks_dir()
+                .join("closed/2020")
+                .join(format!("{}.md", blocker.id()))
+        );
+
+        assert_eq!(store.find(blocker.id().full()).unwrap(), archived_path);
+        assert_eq!(store.status_from_path(&archived_path), Some(Status::Closed));
+        assert_eq!(store.list_all().unwrap().len(), 1);
+        assert_eq!(store.list_archived().unwrap().len(), 1);
+        assert_eq!(store.list_ready().unwrap().len(), 1);
+        drop(store);
+
+        // Served from the index on the next run
+        let store = Store::open(&root).unwrap();
+        assert_eq!(store.find(blocker.id().full()).unwrap(), archived_path);
+        assert_eq!(store.list_all_with_archived().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_subtask_progress() {
+        let (root, store) = temp_store("subtasks");
+        drop(store);
+        fs::write(
+            root.join(TASKS_DIR).join("config.yaml"),
+            "require_children_closed: true\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+
+        let mut parent = Task::new("Parent", None);
+        let done = Task::new("Done", None);
+        let pending = Task::new("Pending", None);
+        parent.frontmatter.children = vec![done.id().clone(), pending.id().clone()];
+        store.create(&parent, Status::Open).unwrap();
+        store.create(&done, Status::Closed).unwrap();
+        let pending_path = store.create(&pending, Status::InProgress).unwrap();
+
+        assert_eq!(store.subtask_progress(&parent).unwrap(), Some((1, 2)));
+        assert_eq!(store.subtask_progress(&done).unwrap(), None);
+        assert!(matches!(
+            store.check_children_closed(&parent),
+            Err(StoreError::UnfinishedChildren { count: 1, .. })
+        ));
+
+        let mut tx = store.transaction();
+        tx.move_to_status(&pending_path, Status::Cancelled).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(store.subtask_progress(&parent).unwrap(), Some((2, 2)));
+        assert!(store.check_children_closed(&parent).is_ok());
+    }
+
+    #[test]
+    fn test_unchecked_on_close() {
+        let (root, store) = temp_store("checklist");
+        let mut task = Task::new("Task", None);
+        task.body = "- [x] Done\n- [ ] Pending".to_string();
+        assert!(store.check_checklist_done(&task).is_ok());
+        drop(store);
+
+        fs::write(
+            root.join(TASKS_DIR).join("config.yaml"),
+            "unchecked_on_close: refuse\n",
+        )
+        .unwrap();
+        let store = Store::open(&root).unwrap();
+        assert!(matches!(
+            store.check_checklist_done(&task),
+            Err(StoreError::UncheckedItems { count: 1, .. })
+        ));
+
+        let item = task.checklist().pop().unwrap();
+        task.set_checked(&item, true);
+        assert_eq!(task.checklist_progress(), Some((2, 2)));
+        assert!(store.check_checklist_done(&task).is_ok());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_read_tasks_at_revision() {
+        let (root, store) = temp_store("at");
+        let git = |args: &[&str]| run_git(&root, args);
+        let old = Task::new("Closed since", None);
+        let old_path = store.create(&old, Status::Open).unwrap();
+        git(&["init", "-q"]);
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "Add task"]);
+
+        let mut tx = store.transaction();
+        tx.move_to_status(&old_path, Status::Closed).unwrap();
+        tx.commit().unwrap();
+        store.create(&Task::new("Newer task", None), Status::Open).unwrap();
+        drop(store);
+
+        let options = StoreOptions {
+            at: Some("HEAD".to_string()),
+            ..Default::default()
+        };
+        let store = Store::open_with(&root, &options).unwrap();
+        let tasks = store.list_all().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].1.title(), "Closed since");
+        assert_eq!(store.find(old.id().full()).unwrap(), old_path);
+        assert_eq!(store.list_ready().unwrap().len(), 1);
+        assert!(matches!(
+            store.save(&old, &old_path),
+            Err(StoreError::ReadOnly(_))
+        ));
+        assert!(matches!(store.check_writable(), Err(StoreError::ReadOnly(_))));
+
+        let options = StoreOptions {
+            at: Some("no-such-rev".to_string()),
+            ..Default::default()
+        };
+        assert!(Store::open_with(&root, &options).is_err());
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let (_root, store) = temp_store("undo");
+        let task = Task::new("Test task", None);
+        let open_path = store.create(&task, Status::Open).unwrap();
+
+        let mut task = store.load(&open_path).unwrap();
+        task.add_log("Closed.", None);
+        let closed_path = store
+            .save_to_status(&task, &open_path, Status::Closed)
+            .unwrap();
+
+        let undone = store.undo(1).unwrap();
+        assert_eq!(undone.len(), 1);
+        assert!(!closed_path.exists());
+        let restored = fs::read_to_string(&open_path).unwrap();
+        assert!(!restored.contains("Closed."));
+        assert!(restored.contains("Undid 'yatl' from"));
+
+        // Undoing the creation as well removes the file, despite the log
+        // entry the first undo added
+        store.undo(1).unwrap();
+        assert!(!open_path.exists());
+        assert!(matches!(store.undo(1), Err(StoreError::NothingToUndo)));
+
+        assert_eq!(store.redo(2).unwrap().len(), 2);
+        assert!(!open_path.exists());
+        let redone = fs::read_to_string(&closed_path).unwrap();
+        assert!(redone.contains("Closed."));
+        assert!(redone.contains("Redid 'yatl' from"));
+
+        // A new change clears the redo stack
+        store.undo(1).unwrap();
+        let mut task = store.load(&open_path).unwrap();
+        task.add_log("Edited.", None);
+        store.save(&task, &open_path).unwrap();
+        assert!(matches!(store.redo(1), Err(StoreError::NothingToRedo)));
+    }
+
+    #[test]
+    fn test_corrupt_journal_is_kept() {
+        let (_root, store) = temp_store("bad-journal");
+        let task = Task::new("Test task", None);
+        let path = store.create(&task, Status::Open).unwrap();
+        let journal = store.tasks_dir().join(JOURNAL_FILE);
+        fs::write(&journal, "{not json").unwrap();
+
+        assert!(matches!(store.undo(1), Err(StoreError::BadJournal(_))));
+
+        // Changes still go through, but don't overwrite the journal
+        let mut task = store.load(&path).unwrap();
+        task.add_log("Edited.", None);
+        store.save(&task, &path).unwrap();
+        assert_eq!(fs::read_to_string(&journal).unwrap(), "{not json");
+    }
+}
@@ -1,33 +1,71 @@
+use crate::checklist::{self, ChecklistItem};
 use crate::id::TaskId;
-use chrono::{DateTime, Utc};
+use crate::log::{self, LogEntry, LogKind};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Task status - derived from filesystem location, not stored in file
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Status {
     #[default]
     Open,
-    #[serde(rename = "in-progress")]
     InProgress,
     Blocked,
     Closed,
     Cancelled,
+    /// Extra status declared in config.yaml (e.g. "review")
+    Custom(String),
 }
 
-impl fmt::Display for Status {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Status {
+    /// The built-in statuses, in display order
+    pub const BUILTIN: [Status; 5] = [
+        Status::Open,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Closed,
+        Status::Cancelled,
+    ];
+
+    /// Map a directory name to a status. Names that aren't built-in become
+    /// `Custom`; whether they are actually configured is up to the store.
+    pub fn from_name(name: &str) -> Status {
+        match name {
+            "open" => Status::Open,
+            "in-progress" => Status::InProgress,
+            "blocked" => Status::Blocked,
+            "closed" => Status::Closed,
+            "cancelled" => Status::Cancelled,
+            other => Status::Custom(other.to_string()),
+        }
+    }
+
+    /// Directory name (and display name) for this status
+    pub fn name(&self) -> &str {
         match self {
-            Status::Open => write!(f, "open"),
-            Status::InProgress => write!(f, "in-progress"),
-            Status::Blocked => write!(f, "blocked"),
-            Status::Closed => write!(f, "closed"),
-            Status::Cancelled => write!(f, "cancelled"),
+            Status::Open => "open",
+            Status::InProgress => "in-progress",
+            Status::Blocked => "blocked",
+            Status::Closed => "closed",
+            Status::Cancelled => "cancelled",
+            Status::Custom(name) => name,
         }
     }
 }
 
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Serialize for Status {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Priority {
@@ -71,6 +109,46 @@ fn default_version() -> u32 {
     0
 }
 
+/// Frontmatter keys yatl itself uses (plus `status`, which comes from the
+/// directory), so custom fields can't take them over
+pub const BUILTIN_FIELDS: &[&str] = &[
+    "yatl_version",
+    "title",
+    "id",
+    "created",
+    "updated",
+    "author",
+    "assignee",
+    "priority",
+    "tags",
+    "blocked_by",
+    "blocks",
+    "parent",
+    "children",
+    "due",
+    "defer_until",
+    "work",
+    "status",
+];
+
+/// A stretch of time spent working on a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkInterval {
+    pub start: DateTime<Utc>,
+    /// None while the task is still in progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl WorkInterval {
+    /// Time worked, counting an interval that is still running up to `now`
+    pub fn duration(&self, now: DateTime<Utc>) -> Duration {
+        (self.end.unwrap_or(now) - self.start).max(Duration::zero())
+    }
+}
+
 /// YAML frontmatter for a task (status is NOT stored here - it's derived from directory)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskFrontmatter {
@@ -83,6 +161,9 @@ pub struct TaskFrontmatter {
     pub updated: DateTime<Utc>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,
+    /// Who is working on the task now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
     #[serde(default)]
     pub priority: Priority,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -95,6 +176,43 @@ pub struct TaskFrontmatter {
     pub parent: Option<TaskId>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<TaskId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// Hidden from `ready` and `next` until this date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defer_until: Option<NaiveDate>,
+    /// Time spent in progress, plus time logged with `yatl log --spent`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub work: Vec<WorkInterval>,
+    /// Keys yatl doesn't know (added by hand or by other tools), kept in
+    /// their original order so rewriting the file doesn't drop them
+    #[serde(flatten)]
+    pub extra: serde_yaml::Mapping,
+}
+
+impl TaskFrontmatter {
+    /// The frontmatter as a YAML mapping of field name to value, in file order
+    pub fn fields(&self) -> serde_yaml::Mapping {
+        match serde_yaml::to_value(self) {
+            Ok(serde_yaml::Value::Mapping(fields)) => fields,
+            _ => serde_yaml::Mapping::new(),
+        }
+    }
+}
+
+/// Whether a frontmatter value matches a `key=value` filter: strings
+/// case-insensitively, numbers and booleans by their text, lists if any
+/// item matches
+fn value_matches(value: &serde_yaml::Value, expected: &str) -> bool {
+    use serde_yaml::Value;
+    match value {
+        Value::String(s) => s.eq_ignore_ascii_case(expected),
+        Value::Number(n) => n.to_string() == expected,
+        Value::Bool(b) => b.to_string() == expected,
+        Value::Sequence(items) => items.iter().any(|item| value_matches(item, expected)),
+        Value::Tagged(tagged) => value_matches(&tagged.value, expected),
+        Value::Null | Value::Mapping(_) => false,
+    }
 }
 
 /// A complete task with frontmatter and body
@@ -102,7 +220,7 @@ pub struct TaskFrontmatter {
 pub struct Task {
     pub frontmatter: TaskFrontmatter,
     pub body: String,
-    pub log: String,
+    pub log: Vec<LogEntry>,
 }
 
 impl Task {
@@ -119,19 +237,26 @@ impl Task {
             created: now,
             updated: now,
             author,
+            assignee: None,
             priority: Priority::Medium,
             tags: vec![],
             blocked_by: vec![],
             blocks: vec![],
             parent: None,
             children: vec![],
+            due: None,
+            defer_until: None,
+            work: vec![],
+            extra: serde_yaml::Mapping::new(),
         };
 
-        let log = format!(
-            "---\n# Log: {} {}\n\nCreated task.\n",
-            now.format("%Y-%m-%dT%H:%M:%SZ"),
-            frontmatter.author.as_deref().unwrap_or("unknown")
-        );
+        let log = vec![LogEntry::new(
+            now,
+            frontmatter.author.as_deref().unwrap_or("unknown"),
+            LogKind::Created,
+            &[],
+            "Created task.",
+        )];
 
         Task {
             frontmatter,
@@ -142,6 +267,11 @@ impl Task {
 
     /// Parse a task from markdown content
     pub fn parse(content: &str) -> Result<Self, String> {
+        // Left behind by a line-based git merge
+        if let Some(problem) = crate::merge::damage(content) {
+            return Err(format!("{} (run 'yatl resolve' to repair)", problem));
+        }
+
         // Split on YAML frontmatter delimiters
         let parts: Vec<&str> = content.splitn(3, "---").collect();
         if parts.len() < 3 {
@@ -158,10 +288,10 @@ impl Task {
         // Log starts at first "---\n# Log:" pattern
         let (body, log) = if let Some(log_start) = rest.find("\n---\n# Log:") {
             let body = rest[..log_start].trim().to_string();
-            let log = rest[log_start + 1..].to_string(); // Include the "---\n# Log:..." part
+            let log = log::parse(&rest[log_start + 1..]); // Include the "---\n# Log:..." part
             (body, log)
         } else {
-            (rest.trim().to_string(), String::new())
+            (rest.trim().to_string(), vec![])
         };
 
         Ok(Task {
@@ -184,29 +314,53 @@ impl Task {
 
         // Log entries start with "---\n# Log:" - just add a newline separator
         md.push('\n');
-        md.push_str(&self.log);
+        md.push_str(&log::render(&self.log));
 
         md
     }
 
-    /// Add a log entry
+    /// Add a free-text comment to the log
     pub fn add_log(&mut self, message: &str, author: Option<&str>) {
+        self.add_log_entry(LogKind::Comment, &[], message, author);
+    }
+
+    /// Add a log entry recording what a command did
+    pub fn add_log_entry(
+        &mut self,
+        kind: LogKind,
+        metadata: &[(&str, &str)],
+        message: &str,
+        author: Option<&str>,
+    ) {
         let now = Utc::now();
         let author = author
             .or(self.frontmatter.author.as_deref())
             .unwrap_or("unknown");
+        let entry = LogEntry::new(now, author, kind, metadata, message.trim());
 
-        let entry = format!(
-            "\n---\n# Log: {} {}\n\n{}\n",
-            now.format("%Y-%m-%dT%H:%M:%SZ"),
-            author,
-            message
-        );
-
-        self.log.push_str(&entry);
+        // An identical entry (same second, author and message) adds nothing
+        // and would read as merge damage
+        if !self.log.contains(&entry) {
+            self.log.push(entry);
+        }
         self.frontmatter.updated = now;
     }
 
+    /// Checklist items in the body
+    pub fn checklist(&self) -> Vec<ChecklistItem> {
+        checklist::items(&self.body)
+    }
+
+    /// Checked and total checklist items, if the body has a checklist
+    pub fn checklist_progress(&self) -> Option<(usize, usize)> {
+        checklist::progress(&self.body)
+    }
+
+    /// Check or uncheck a checklist item
+    pub fn set_checked(&mut self, item: &ChecklistItem, checked: bool) {
+        self.body = checklist::set_checked(&self.body, item, checked);
+    }
+
     /// Get the task ID
     pub fn id(&self) -> &TaskId {
         &self.frontmatter.id
@@ -217,6 +371,56 @@ impl Task {
         &self.frontmatter.title
     }
 
+    /// Whether frontmatter field `key` (known to yatl or not) has `value`
+    pub fn field_matches(&self, key: &str, value: &str) -> bool {
+        self.frontmatter
+            .fields()
+            .get(key)
+            .is_some_and(|field| value_matches(field, value))
+    }
+
+    /// Whether the task was due before `today`
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.frontmatter.due.is_some_and(|due| due < today)
+    }
+
+    /// Whether the task is deferred past `today`
+    pub fn is_deferred(&self, today: NaiveDate) -> bool {
+        self.frontmatter
+            .defer_until
+            .is_some_and(|defer_until| defer_until > today)
+    }
+
+    /// Start a work interval at `now`, unless one is already running
+    pub fn start_work(&mut self, author: Option<&str>, now: DateTime<Utc>) {
+        if self.frontmatter.work.iter().any(|work| work.end.is_none()) {
+            return;
+        }
+        self.frontmatter.work.push(WorkInterval {
+            start: now,
+            end: None,
+            author: author.map(str::to_string),
+        });
+    }
+
+    /// End the running work interval at `now`
+    pub fn stop_work(&mut self, now: DateTime<Utc>) {
+        for work in &mut self.frontmatter.work {
+            if work.end.is_none() {
+                work.end = Some(now.max(work.start));
+            }
+        }
+    }
+
+    /// Total time worked on the task, up to `now` for a running interval
+    pub fn time_spent(&self, now: DateTime<Utc>) -> Duration {
+        self.frontmatter
+            .work
+            .iter()
+            .map(|work| work.duration(now))
+            .fold(Duration::zero(), |total, duration| total + duration)
+    }
+
     /// Get the task priority
     pub fn priority(&self) -> Priority {
         self.frontmatter.priority
@@ -243,4 +447,71 @@ mod tests {
         assert_eq!(parsed.title(), task.title());
         assert_eq!(parsed.id().full(), task.id().full());
     }
+
+    #[test]
+    fn test_due_and_defer_dates() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let mut task = Task::new("Test task", Some("brian".into()));
+        task.frontmatter.due = Some(date("2025-01-15"));
+        task.frontmatter.defer_until = Some(date("2025-01-10"));
+
+        let parsed = Task::parse(&task.to_markdown()).unwrap();
+        assert!(parsed.to_markdown().contains("due: 2025-01-15"));
+        assert_eq!(parsed.frontmatter.defer_until, Some(date("2025-01-10")));
+
+        assert!(!parsed.is_overdue(date("2025-01-15")));
+        assert!(parsed.is_overdue(date("2025-01-16")));
+        assert!(parsed.is_deferred(date("2025-01-09")));
+        assert!(!parsed.is_deferred(date("2025-01-10")));
+    }
+
+    #[test]
+    fn test_preserves_unknown_fields() {
+        let content = "---\n\
+            title: Task\n\
+            id: abcd1234\n\
+            created: 2025-01-15T10:00:00Z\n\
+            updated: 2025-01-15T10:00:00Z\n\
+            jira: PROJ-42\n\
+            estimate: 3\n\
+            component: auth\n\
+            ---\n\nBody\n";
+        let mut task = Task::parse(content).unwrap();
+        task.add_log("Touched.", None);
+
+        let md = task.to_markdown();
+        let jira = md.find("jira: PROJ-42").unwrap();
+        let estimate = md.find("estimate: 3").unwrap();
+        let component = md.find("component: auth").unwrap();
+        assert!(jira < estimate && estimate < component);
+        assert_eq!(Task::parse(&md).unwrap().frontmatter.extra.len(), 3);
+
+        assert!(task.field_matches("jira", "proj-42"));
+        assert!(task.field_matches("estimate", "3"));
+        assert!(task.field_matches("title", "Task"));
+        assert!(!task.field_matches("component", "billing"));
+        assert!(!task.field_matches("missing", "x"));
+    }
+
+    #[test]
+    fn test_work_intervals() {
+        let at = |s| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let mut task = Task::new("Test task", Some("brian".into()));
+        task.start_work(Some("brian"), at("2025-01-15T10:00:00Z"));
+        // Starting again while running keeps the first interval
+        task.start_work(Some("brian"), at("2025-01-15T10:10:00Z"));
+        assert_eq!(task.frontmatter.work.len(), 1);
+        assert_eq!(
+            task.time_spent(at("2025-01-15T10:30:00Z")),
+            Duration::minutes(30)
+        );
+
+        task.stop_work(at("2025-01-15T10:45:00Z"));
+        let parsed = Task::parse(&task.to_markdown()).unwrap();
+        assert_eq!(parsed.frontmatter.work, task.frontmatter.work);
+        assert_eq!(
+            parsed.time_spent(at("2025-01-16T00:00:00Z")),
+            Duration::minutes(45)
+        );
+    }
 }
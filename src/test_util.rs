@@ -0,0 +1,88 @@
+//! Fixtures shared by the unit tests
+
+use crate::id::TaskId;
+use crate::store::Store;
+use crate::task::Task;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A temporary directory, removed (with everything in it) when dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A store in a fresh temporary directory, removed when the returned
+/// directory is dropped (even if the test fails)
+pub(crate) fn temp_store(name: &str) -> (TempDir, Store) {
+    let root = TempDir(std::env::temp_dir().join(format!(
+        "yatl-test-{}-{}-{}",
+        name,
+        std::process::id(),
+        TaskId::new()
+    )));
+    fs::create_dir_all(&root).unwrap();
+    let store = Store::init(&root).unwrap();
+    (root, store)
+}
+
+/// Fail the test unless every `blocked_by` link has the matching `blocks`
+/// link on the other task, and the other way round
+pub(crate) fn assert_blocks_consistent(store: &Store) {
+    let tasks: HashMap<TaskId, Task> = store
+        .list_all_with_archived()
+        .unwrap()
+        .into_iter()
+        .map(|(_, task)| (task.id().clone(), task))
+        .collect();
+    for task in tasks.values() {
+        for blocker in &task.frontmatter.blocked_by {
+            assert!(
+                tasks[blocker].frontmatter.blocks.contains(task.id()),
+                "{} is blocked by {} but not in its blocks",
+                task.id(),
+                blocker
+            );
+        }
+        for blocked in &task.frontmatter.blocks {
+            assert!(
+                tasks[blocked].frontmatter.blocked_by.contains(task.id()),
+                "{} blocks {} but not in its blocked_by",
+                task.id(),
+                blocked
+            );
+        }
+    }
+}
+
+/// Run git in `root` as a fixed test identity, failing the test on error
+pub(crate) fn run_git(root: &Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
@@ -0,0 +1,491 @@
+//! Multi-file transactions over the task store.
+//!
+//! A command stages every file write and rename it wants to make, then
+//! commits them together. All touched tasks are locked for the duration of
+//! the commit, and if any step fails the already-applied steps are undone so
+//! the store is never left half-updated. Committed changes are recorded in
+//! the journal so `yatl undo` can reverse them.
+
+use crate::id::TaskId;
+use crate::journal::FileChange;
+use crate::migrate::file_version;
+use crate::store::{atomic_write, Fingerprint, Result, Store, StoreError, TaskLock};
+use crate::task::{Status, Task, TASK_FORMAT_VERSION};
+use colored::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single staged file operation
+#[derive(Debug, Clone)]
+enum Op {
+    /// Write a task file (creating it if `create` is set)
+    Write {
+        id: TaskId,
+        path: PathBuf,
+        content: String,
+        create: bool,
+        /// Format version of the task being written
+        version: u32,
+        /// Whether this puts back an exact earlier state of the file, which
+        /// is written as it was even if it doesn't parse
+        restore: bool,
+    },
+    /// Move a task file between status directories
+    Rename {
+        id: TaskId,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Delete a task file
+    Remove { id: TaskId, path: PathBuf },
+}
+
+impl Op {
+    fn id(&self) -> &TaskId {
+        match self {
+            Op::Write { id, .. } | Op::Rename { id, .. } | Op::Remove { id, .. } => id,
+        }
+    }
+
+    /// Every path this operation reads or writes
+    fn paths(&self) -> Vec<&PathBuf> {
+        match self {
+            Op::Write { path, .. } | Op::Remove { path, .. } => vec![path],
+            Op::Rename { from, to, .. } => vec![from, to],
+        }
+    }
+}
+
+/// How to reverse an operation that has already been applied
+enum Undo {
+    /// Put back the previous content of a file
+    Restore(PathBuf, String),
+    /// Remove a file that did not exist before
+    Remove(PathBuf),
+    /// Move a file back to where it was
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+/// A staged set of task file changes, applied atomically by `commit`
+pub struct Transaction<'a> {
+    store: &'a Store,
+    ops: Vec<Op>,
+    /// Whether to record the committed changes in the journal
+    journal: bool,
+    /// Whether to commit the changes to git (when auto-commit is enabled)
+    git_commit: bool,
+    /// Content to record as a file's "before" state instead of what is on
+    /// disk at commit time
+    originals: HashMap<PathBuf, Option<String>>,
+}
+
+impl Store {
+    /// Begin a new transaction against this store
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction {
+            store: self,
+            ops: vec![],
+            journal: true,
+            git_commit: true,
+            originals: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> Transaction<'a> {
+    /// Stage creation of a new task in the given status directory
+    pub fn create(&mut self, task: &Task, status: Status) -> PathBuf {
+        let path = self
+            .store
+            .status_dir(&status)
+            .join(format!("{}.md", task.id()));
+        self.ops.push(Op::Write {
+            id: task.id().clone(),
+            path: path.clone(),
+            content: task.to_markdown(),
+            create: true,
+            version: task.frontmatter.yatl_version,
+            restore: false,
+        });
+        path
+    }
+
+    /// Stage writing a task back to its file
+    pub fn save(&mut self, task: &Task, path: &Path) {
+        self.save_content(task, path, task.to_markdown());
+    }
+
+    /// Stage writing exact file content for a task, for callers that must
+    /// control the bytes on disk (such as format migrations)
+    pub fn save_content(&mut self, task: &Task, path: &Path, content: String) {
+        self.ops.push(Op::Write {
+            id: task.id().clone(),
+            path: path.to_path_buf(),
+            content,
+            create: false,
+            version: task.frontmatter.yatl_version,
+            restore: false,
+        });
+    }
+
+    /// Stage putting a file back into an exact earlier state: written with
+    /// `content`, or removed if `content` is None
+    pub fn restore(&mut self, path: &Path, content: Option<String>) -> Result<()> {
+        let id = path
+            .file_stem()
+            .map(|s| TaskId::from_string(s.to_string_lossy()))
+            .ok_or_else(|| StoreError::Parse("Invalid path".to_string()))?;
+        self.ops.push(match content {
+            Some(content) => Op::Write {
+                id,
+                path: path.to_path_buf(),
+                version: file_version(&content).unwrap_or(0),
+                content,
+                create: false,
+                restore: true,
+            },
+            None => Op::Remove {
+                id,
+                path: path.to_path_buf(),
+            },
+        });
+        Ok(())
+    }
+
+    /// Record what a file looked like before this command started changing
+    /// it (e.g. before an external editor ran), for the journal. None means
+    /// the file did not exist yet.
+    pub fn record_original(&mut self, path: &Path, content: Option<String>) {
+        self.originals.insert(path.to_path_buf(), content);
+    }
+
+    /// Don't record this transaction in the journal (used by undo/redo)
+    pub fn skip_journal(&mut self) {
+        self.journal = false;
+    }
+
+    /// Don't commit this transaction to git, for changes a later
+    /// transaction in the same command will include
+    pub fn skip_git_commit(&mut self) {
+        self.git_commit = false;
+    }
+
+    /// Stage moving a task file to a new status directory
+    pub fn move_to_status(&mut self, path: &Path, status: Status) -> Result<PathBuf> {
+        let dir = self.store.status_dir(&status);
+        self.move_to_dir(path, dir)
+    }
+
+    /// Stage moving a task file into the archive for its (terminal) status
+    pub fn archive(&mut self, path: &Path, year: i32) -> Result<PathBuf> {
+        let status = self
+            .store
+            .status_from_path(path)
+            .filter(|status| self.store.is_terminal(status))
+            .ok_or_else(|| {
+                StoreError::NotAllowed(format!(
+                    "Only closed or cancelled tasks can be archived: {}",
+                    path.display()
+                ))
+            })?;
+        let dir = self.store.archive_dir(&status, year);
+        self.move_to_dir(path, dir)
+    }
+
+    fn move_to_dir(&mut self, path: &Path, dir: PathBuf) -> Result<PathBuf> {
+        let id = path
+            .file_stem()
+            .map(|s| TaskId::from_string(s.to_string_lossy()))
+            .ok_or_else(|| StoreError::Parse("Invalid path".to_string()))?;
+        let filename = path
+            .file_name()
+            .ok_or_else(|| StoreError::Parse("Invalid path".to_string()))?;
+        let to = dir.join(filename);
+
+        self.ops.push(Op::Rename {
+            id,
+            from: path.to_path_buf(),
+            to: to.clone(),
+        });
+        Ok(to)
+    }
+
+    /// Stage writing a task and moving it to a new status directory
+    pub fn save_to_status(&mut self, task: &Task, path: &Path, status: Status) -> Result<PathBuf> {
+        self.save(task, path);
+        self.move_to_status(path, status)
+    }
+
+    /// Get the status a task will have once the transaction is applied,
+    /// or None if the transaction does not move it
+    pub fn staged_status(&self, id: &TaskId) -> Option<Status> {
+        self.ops.iter().rev().find_map(|op| match op {
+            Op::Rename { id: op_id, to, .. } if op_id == id => self.store.status_from_path(to),
+            Op::Write {
+                id: op_id,
+                path,
+                create: true,
+                ..
+            } if op_id == id => self.store.status_from_path(path),
+            _ => None,
+        })
+    }
+
+    /// Apply all staged operations. On failure every applied step is reverted.
+    pub fn commit(self) -> Result<()> {
+        if self.ops.is_empty() {
+            return Ok(());
+        }
+
+        let _locks = self.lock_all()?;
+        self.validate()?;
+
+        let touched = self.touched_paths();
+        let before: Vec<Option<String>> = touched
+            .iter()
+            .map(|path| match self.originals.get(*path) {
+                Some(original) => original.clone(),
+                None => fs::read_to_string(path).ok(),
+            })
+            .collect();
+
+        let mut undo: Vec<Undo> = vec![];
+        for op in &self.ops {
+            if let Err(e) = apply(op, &mut undo) {
+                let failures = rollback(undo);
+                if failures.is_empty() {
+                    return Err(e);
+                }
+                return Err(StoreError::Rollback(format!(
+                    "{}; rollback also failed: {}",
+                    e,
+                    failures.join("; ")
+                )));
+            }
+        }
+
+        // Remember what is now on disk so later saves in this process
+        // don't mistake our own changes for concurrent modifications
+        let mut loaded = self.store.loaded.borrow_mut();
+        for op in &self.ops {
+            match op {
+                Op::Write { path, content, .. } => {
+                    loaded.insert(path.clone(), Fingerprint::Content(content.clone()));
+                }
+                Op::Rename { from, to, .. } => {
+                    if let Some(content) = loaded.remove(from) {
+                        loaded.insert(to.clone(), content);
+                    }
+                }
+                Op::Remove { path, .. } => {
+                    loaded.remove(path);
+                }
+            }
+        }
+        drop(loaded);
+
+        let changes: Vec<FileChange> = touched
+            .iter()
+            .zip(before)
+            .filter_map(|(path, before)| {
+                let after = fs::read_to_string(path).ok();
+                (before != after).then(|| FileChange {
+                    path: self.store.relative_path(path),
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        if self.git_commit {
+            self.store.commit_to_git(&changes);
+        }
+        // The change itself succeeded; losing the undo record is not worth
+        // failing the command over
+        if self.journal {
+            if let Err(e) = self.store.record_journal(changes) {
+                eprintln!("{} Could not update the journal: {}", "warning:".yellow(), e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every distinct path touched by the staged operations, in order
+    fn touched_paths(&self) -> Vec<&PathBuf> {
+        let mut paths: Vec<&PathBuf> = vec![];
+        for path in self.ops.iter().flat_map(|op| op.paths()) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    /// Lock every task touched by this transaction, in a stable order
+    fn lock_all(&self) -> Result<Vec<TaskLock>> {
+        let mut ids: Vec<&TaskId> = self.ops.iter().map(|op| op.id()).collect();
+        ids.sort_by(|a, b| a.full().cmp(b.full()));
+        ids.dedup();
+        ids.into_iter().map(|id| self.store.lock(id)).collect()
+    }
+
+    /// Check that nothing changed on disk since the staged tasks were loaded,
+    /// that no task is written in a format newer than we understand or in a
+    /// form we couldn't parse again, and that no task is changed at a path
+    /// it was already moved away from
+    fn validate(&self) -> Result<()> {
+        for (i, op) in self.ops.iter().enumerate() {
+            if let Op::Write {
+                path,
+                content,
+                version,
+                restore,
+                ..
+            } = op
+            {
+                if *version > TASK_FORMAT_VERSION {
+                    return Err(StoreError::UnsupportedVersion {
+                        path: path.clone(),
+                        version: *version,
+                    });
+                }
+                // Never write a file yatl couldn't read back
+                if !restore {
+                    if let Err(message) = Task::parse(content) {
+                        return Err(StoreError::Unreadable {
+                            path: path.clone(),
+                            message,
+                        });
+                    }
+                }
+            }
+
+            // A path an earlier op moved the task away from must not be
+            // written again, or the task would end up in two places
+            let moved_away = self.ops[..i].iter().any(|prev| {
+                matches!(prev, Op::Rename { from, to, .. } if from != to && from == op_path(op))
+            });
+            if moved_away {
+                return Err(StoreError::NotAllowed(format!(
+                    "Cannot change {}: it is moved earlier in the same transaction",
+                    op_path(op).display()
+                )));
+            }
+
+            // Paths produced by an earlier op in this transaction don't exist yet
+            let staged_earlier = self.ops[..i].iter().any(|prev| match prev {
+                Op::Rename { to, .. } => to == op_path(op),
+                Op::Write { path, create, .. } => *create && path == op_path(op),
+                Op::Remove { .. } => false,
+            });
+            if staged_earlier {
+                continue;
+            }
+
+            match op {
+                Op::Write {
+                    id,
+                    path,
+                    create: true,
+                    ..
+                } => {
+                    if self.store.find(id.full()).is_ok() {
+                        return Err(StoreError::Conflict(path.clone()));
+                    }
+                }
+                Op::Write { path, .. } => self.store.check_unchanged(path)?,
+                Op::Rename { from: path, .. } | Op::Remove { path, .. } => {
+                    if !path.exists() {
+                        return Err(StoreError::Conflict(path.clone()));
+                    }
+                    self.store.check_unchanged(path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The path an operation reads from
+fn op_path(op: &Op) -> &Path {
+    match op {
+        Op::Write { path, .. } | Op::Remove { path, .. } => path,
+        Op::Rename { from, .. } => from,
+    }
+}
+
+/// Apply one operation, recording how to undo it
+fn apply(op: &Op, undo: &mut Vec<Undo>) -> Result<()> {
+    match op {
+        Op::Write { path, content, .. } => {
+            let previous = match fs::read_to_string(path) {
+                Ok(previous) => Some(previous),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            ensure_parent(path)?;
+            atomic_write(path, content)?;
+            undo.push(match previous {
+                Some(previous) => Undo::Restore(path.clone(), previous),
+                None => Undo::Remove(path.clone()),
+            });
+        }
+        Op::Rename { from, to, .. } => {
+            if from != to {
+                ensure_parent(to)?;
+                fs::rename(from, to)?;
+                undo.push(Undo::Rename {
+                    from: to.clone(),
+                    to: from.clone(),
+                });
+            }
+        }
+        Op::Remove { path, .. } => {
+            let previous = fs::read_to_string(path)?;
+            fs::remove_file(path)?;
+            undo.push(Undo::Restore(path.clone(), previous));
+        }
+    }
+    Ok(())
+}
+
+/// Create the status directory for a path if it doesn't exist yet
+/// (custom statuses get their directory on first use)
+fn ensure_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(())
+}
+
+/// Revert applied operations in reverse order, returning any failures
+fn rollback(undo: Vec<Undo>) -> Vec<String> {
+    let mut failures = vec![];
+    for step in undo.into_iter().rev() {
+        let (path, result) = match step {
+            Undo::Restore(path, content) => {
+                let result = atomic_write(&path, &content);
+                (path, result)
+            }
+            Undo::Remove(path) => {
+                let result = fs::remove_file(&path);
+                (path, result)
+            }
+            Undo::Rename { from, to } => {
+                let result = fs::rename(&from, &to);
+                (from, result)
+            }
+        };
+        if let Err(e) = result {
+            failures.push(format!("{}: {}", path.display(), e));
+        }
+    }
+    failures
+}